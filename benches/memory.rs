@@ -0,0 +1,41 @@
+use Rust_Trees::avltree::AvlTree;
+use Rust_Trees::bst::BST;
+use Rust_Trees::memory::MemoryUsage;
+use Rust_Trees::rbtree::RBTree;
+use Rust_Trees::rng::Rng;
+
+// cargo bench --bench memory: heap bytes per key of each tree for a few sizes of shuffled u32 keys,
+// then where the bytes of one node go. nothing is timed, the numbers only depend on the sizes
+const SIZES: [u32; 3] = [1_000, 10_000, 100_000];
+
+fn usages(size: u32) -> Vec<(&'static str, MemoryUsage)> {
+    let mut keys: Vec<u32> = (0..size).collect();
+    Rng::new(7).shuffle(&mut keys);
+    let mut rb = RBTree::new();
+    let mut avl = AvlTree::new();
+    let mut bst = BST::new();
+    for key in keys {
+        rb.insert(key);
+        avl.insert(key);
+        bst.insert(key);
+    }
+    vec![
+        ("Red-Black Tree", rb.memory_usage()),
+        ("AVL Tree", avl.memory_usage()),
+        ("BST", bst.memory_usage()),
+    ]
+}
+
+fn main() {
+    println!("{:<16}{:>10}{:>12}{:>12}{:>14}{:>12}", "tree", "keys", "node bytes", "overhead", "bytes/key", "total");
+    for size in SIZES {
+        for (name, usage) in usages(size) {
+            let per_key = format!("{:.1}", usage.bytes_per_key());
+            println!("{:<16}{:>10}{:>12}{:>12}{:>14}{:>12}", name, usage.keys, usage.node_bytes, usage.overhead_per_node(), per_key, usage.total());
+        }
+    }
+    println!();
+    for (name, usage) in usages(1) {
+        println!("{}: {}", name, usage.to_string().lines().last().unwrap());
+    }
+}
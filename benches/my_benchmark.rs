@@ -4,6 +4,15 @@ use std::time::Duration;
 use Rust_Trees::rbtree::RBTree;
 use Rust_Trees::avltree::AvlTree;
 use Rust_Trees::bst::BST;
+use Rust_Trees::splaytree::SplayTree;
+use Rust_Trees::treap::Treap;
+use Rust_Trees::llrbtree::LLRBTree;
+use Rust_Trees::aatree::AATree;
+use Rust_Trees::scapegoattree::ScapegoatTree;
+use Rust_Trees::wbtree::WBTree;
+use Rust_Trees::btree::{BTree, BPlusTree};
+use Rust_Trees::rng::Rng;
+use std::collections::{BTreeSet, HashSet};
 
 
 
@@ -16,177 +25,583 @@ fn bench_rbtree_insert(tree_size: u32)->RBTree<u32>{
     t
 }
 
-fn bench_rbtree_search(tree_size: u32,tree:RBTree<u32>){
-    let lowest = tree_size/10;
-    for i in 0..lowest{
-        tree.search(i);
+fn bench_avltree_insert(tree_size: u32)->AvlTree<u32>{
+    let mut t = AvlTree::new();
+    for i in 0..tree_size{
+        t.insert(i);
     }
+    t
 }
 
-fn criterion_benchmark_rbtree_insert(c: &mut Criterion) {
-    let mut group = c.benchmark_group("RB Group");
-    group.measurement_time(Duration::from_secs(13));
-    group.bench_function("insert 10000 elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_insert(black_box(10000)))
+// the operations the parameterized groups below measure, one generic bench per scenario then covers
+// the trees and the std BTreeSet baseline alike
+trait BenchSet {
+    const NAME: &'static str;
+    // a plain BST turns descending inserts into a path, so it only runs them on small sizes
+    const SORTED_LIMIT: u32 = u32::MAX;
+    fn empty() -> Self;
+    fn add(&mut self, key: u32);
+    fn remove(&mut self, key: u32);
+    fn has(&self, key: u32) -> bool;
+    // keys k with lo <= k < hi
+    fn scan(&self, lo: u32, hi: u32) -> Vec<u32>;
+    fn sum(&self) -> u64;
+}
+
+impl BenchSet for RBTree<u32> {
+    const NAME: &'static str = "Red-Black Tree";
+    fn empty() -> Self { RBTree::new() }
+    fn add(&mut self, key: u32) { self.insert(key) }
+    fn remove(&mut self, key: u32) { self.delete(key) }
+    fn has(&self, key: u32) -> bool { self.contains(&key) }
+    fn scan(&self, lo: u32, hi: u32) -> Vec<u32> { self.range(lo, hi) }
+    fn sum(&self) -> u64 { self.iter().map(u64::from).sum() }
+}
+
+impl BenchSet for AvlTree<u32> {
+    const NAME: &'static str = "AVL Tree";
+    fn empty() -> Self { AvlTree::new() }
+    fn add(&mut self, key: u32) { self.insert(key) }
+    fn remove(&mut self, key: u32) { self.delete(key) }
+    fn has(&self, key: u32) -> bool { self.contains(&key) }
+    fn scan(&self, lo: u32, hi: u32) -> Vec<u32> { self.range(lo, hi) }
+    fn sum(&self) -> u64 { self.iter().map(u64::from).sum() }
+}
+
+impl BenchSet for BST<u32> {
+    const NAME: &'static str = "BST";
+    const SORTED_LIMIT: u32 = 10_000;
+    fn empty() -> Self { BST::new() }
+    fn add(&mut self, key: u32) { self.insert(key) }
+    fn remove(&mut self, key: u32) { self.delete(key) }
+    fn has(&self, key: u32) -> bool { self.contains(&key) }
+    fn scan(&self, lo: u32, hi: u32) -> Vec<u32> { self.range(lo, hi) }
+    fn sum(&self) -> u64 { self.iter().map(u64::from).sum() }
+}
+
+impl BenchSet for BTreeSet<u32> {
+    const NAME: &'static str = "std BTreeSet";
+    fn empty() -> Self { BTreeSet::new() }
+    fn add(&mut self, key: u32) { self.insert(key); }
+    fn remove(&mut self, key: u32) { BTreeSet::remove(self, &key); }
+    fn has(&self, key: u32) -> bool { self.contains(&key) }
+    fn scan(&self, lo: u32, hi: u32) -> Vec<u32> { self.range(lo..hi).copied().collect() }
+    fn sum(&self) -> u64 { self.iter().copied().map(u64::from).sum() }
+}
+
+const SIZES: [u32; 3] = [1_000, 10_000, 100_000];
+
+#[derive(Clone, Copy, Debug)]
+enum Order {
+    Random,
+    Ascending,
+    Descending,
+}
+
+fn keys_in(order: Order, size: u32) -> Vec<u32> {
+    match order {
+        Order::Random => shuffled_keys(size),
+        Order::Ascending => (0..size).collect(),
+        Order::Descending => (0..size).rev().collect(),
+    }
+}
+
+fn build<S: BenchSet>(keys: &[u32]) -> S {
+    let mut set = S::empty();
+    for key in keys {
+        set.add(*key);
+    }
+    set
+}
+
+fn bench_insert<S: BenchSet>(group: &mut BenchmarkGroup<measurement::WallTime>, order: Order, keys: &[u32]) {
+    let size = keys.len() as u32;
+    if matches!(order, Order::Descending) && size > S::SORTED_LIMIT {
+        return;
+    }
+    group.bench_with_input(BenchmarkId::new(S::NAME, size), keys, |b, keys| {
+        b.iter(|| build::<S>(black_box(keys)))
     });
-    group.bench_function("insert 40000 elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_insert(black_box(40000)))
+}
+
+// every key of a tree built from shuffled keys deleted in the given order
+fn bench_delete<S: BenchSet>(group: &mut BenchmarkGroup<measurement::WallTime>, keys: &[u32]) {
+    let size = keys.len() as u32;
+    let built = shuffled_keys(size);
+    group.bench_with_input(BenchmarkId::new(S::NAME, size), keys, |b, keys| {
+        b.iter_batched(|| build::<S>(&built), |mut set| {
+            for key in keys {
+                set.remove(black_box(*key));
+            }
+            set
+        }, BatchSize::LargeInput)
     });
-    group.bench_function("insert 70000 elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_insert(black_box(70000)))
+}
+
+fn criterion_benchmark_insert(c: &mut Criterion) {
+    for order in [Order::Random, Order::Ascending, Order::Descending] {
+        let mut group = c.benchmark_group(format!("Insert {:?}", order));
+        group.sampling_mode(SamplingMode::Flat);
+        for size in SIZES {
+            let keys = keys_in(order, size);
+            group.throughput(Throughput::Elements(size as u64));
+            bench_insert::<RBTree<u32>>(&mut group, order, &keys);
+            bench_insert::<AvlTree<u32>>(&mut group, order, &keys);
+            bench_insert::<BST<u32>>(&mut group, order, &keys);
+            bench_insert::<BTreeSet<u32>>(&mut group, order, &keys);
+        }
+        group.finish();
+    }
+}
+
+fn criterion_benchmark_delete(c: &mut Criterion) {
+    for order in [Order::Random, Order::Ascending, Order::Descending] {
+        let mut group = c.benchmark_group(format!("Delete {:?}", order));
+        group.sampling_mode(SamplingMode::Flat);
+        for size in SIZES {
+            let keys = keys_in(order, size);
+            group.throughput(Throughput::Elements(size as u64));
+            bench_delete::<RBTree<u32>>(&mut group, &keys);
+            bench_delete::<AvlTree<u32>>(&mut group, &keys);
+            bench_delete::<BST<u32>>(&mut group, &keys);
+            bench_delete::<BTreeSet<u32>>(&mut group, &keys);
+        }
+        group.finish();
+    }
+}
+
+// every key looked up once in shuffled order, then as many keys that are not in the set
+fn bench_search<S: BenchSet>(group: &mut BenchmarkGroup<measurement::WallTime>, keys: &[u32]) {
+    let size = keys.len() as u32;
+    let set = build::<S>(keys);
+    group.bench_with_input(BenchmarkId::new(S::NAME, size), keys, |b, keys| {
+        b.iter(|| {
+            let found = keys.iter().filter(|key| set.has(black_box(**key))).count();
+            let missed = keys.iter().filter(|key| !set.has(black_box(**key + size))).count();
+            found + missed
+        })
     });
-    group.bench_function("insert 100000 elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_insert(black_box(100000)))
+}
+
+fn criterion_benchmark_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Search Hits and Misses");
+    for size in SIZES {
+        let keys = shuffled_keys(size);
+        group.throughput(Throughput::Elements(2 * size as u64));
+        bench_search::<RBTree<u32>>(&mut group, &keys);
+        bench_search::<AvlTree<u32>>(&mut group, &keys);
+        bench_search::<BST<u32>>(&mut group, &keys);
+        bench_search::<BTreeSet<u32>>(&mut group, &keys);
+    }
+    group.finish();
+}
+
+#[derive(Clone, Copy)]
+enum MixedOp {
+    Read(u32),
+    Insert(u32),
+    Delete(u32),
+}
+
+// size operations on a set holding the even keys below 2 * size: reads look up any key, writes insert
+// an odd key or delete it again when it is already there, so the set never drifts far from size keys
+fn mixed_ops(size: u32, reads: u64) -> Vec<MixedOp> {
+    let mut rng = Rng::new(13);
+    let mut present = HashSet::new();
+    (0..size).map(|_| {
+        if rng.below(100) < reads {
+            return MixedOp::Read(rng.below(2 * size as u64) as u32);
+        }
+        let key = 2 * rng.below(size as u64) as u32 + 1;
+        if present.insert(key) { MixedOp::Insert(key) } else {
+            present.remove(&key);
+            MixedOp::Delete(key)
+        }
+    }).collect()
+}
+
+fn bench_mixed<S: BenchSet>(group: &mut BenchmarkGroup<measurement::WallTime>, size: u32, ops: &[MixedOp]) {
+    let mut evens: Vec<u32> = (0..size).map(|key| 2 * key).collect();
+    Rng::new(7).shuffle(&mut evens);
+    group.bench_with_input(BenchmarkId::new(S::NAME, size), ops, |b, ops| {
+        b.iter_batched(|| build::<S>(&evens), |mut set| {
+            let mut found = 0;
+            for op in ops {
+                match *op {
+                    MixedOp::Read(key) => found += set.has(black_box(key)) as usize,
+                    MixedOp::Insert(key) => set.add(black_box(key)),
+                    MixedOp::Delete(key) => set.remove(black_box(key)),
+                }
+            }
+            (set, found)
+        }, BatchSize::LargeInput)
     });
-    group.bench_function("insert 130000 elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_insert(black_box(130000)))
+}
+
+fn criterion_benchmark_mixed(c: &mut Criterion) {
+    for reads in [90, 50, 10] {
+        let mut group = c.benchmark_group(format!("Mixed {}% Reads", reads));
+        group.sampling_mode(SamplingMode::Flat);
+        for size in SIZES {
+            let ops = mixed_ops(size, reads);
+            group.throughput(Throughput::Elements(size as u64));
+            bench_mixed::<RBTree<u32>>(&mut group, size, &ops);
+            bench_mixed::<AvlTree<u32>>(&mut group, size, &ops);
+            bench_mixed::<BST<u32>>(&mut group, size, &ops);
+            bench_mixed::<BTreeSet<u32>>(&mut group, size, &ops);
+        }
+        group.finish();
+    }
+}
+
+// 100 scans, each over a hundredth of the keys, together covering the whole set
+fn bench_scan<S: BenchSet>(group: &mut BenchmarkGroup<measurement::WallTime>, keys: &[u32]) {
+    let size = keys.len() as u32;
+    let width = size / 100;
+    let set = build::<S>(keys);
+    group.bench_function(BenchmarkId::new(S::NAME, size), |b| {
+        b.iter(|| for lo in (0..size).step_by(width as usize) { black_box(set.scan(lo, lo + width)); })
     });
+}
+
+fn criterion_benchmark_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Range Scans of 1% Each");
+    for size in SIZES {
+        let keys = shuffled_keys(size);
+        group.throughput(Throughput::Elements(size as u64));
+        bench_scan::<RBTree<u32>>(&mut group, &keys);
+        bench_scan::<AvlTree<u32>>(&mut group, &keys);
+        bench_scan::<BST<u32>>(&mut group, &keys);
+        bench_scan::<BTreeSet<u32>>(&mut group, &keys);
+    }
     group.finish();
 }
 
-fn criterion_benchmark_rbtree_search(c :&mut Criterion) {
-    let mut tree = bench_rbtree_insert(10000);
-    c.bench_function("search for the 1000 lowest elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_search(black_box(10000),tree.clone()))
+fn bench_iterate<S: BenchSet>(group: &mut BenchmarkGroup<measurement::WallTime>, keys: &[u32]) {
+    let size = keys.len() as u32;
+    let set = build::<S>(keys);
+    group.bench_function(BenchmarkId::new(S::NAME, size), |b| b.iter(|| black_box(set.sum())));
+}
+
+fn criterion_benchmark_iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Iterate in Order");
+    for size in SIZES {
+        let keys = shuffled_keys(size);
+        group.throughput(Throughput::Elements(size as u64));
+        bench_iterate::<RBTree<u32>>(&mut group, &keys);
+        bench_iterate::<AvlTree<u32>>(&mut group, &keys);
+        bench_iterate::<BST<u32>>(&mut group, &keys);
+        bench_iterate::<BTreeSet<u32>>(&mut group, &keys);
+    }
+    group.finish();
+}
+
+fn bench_llrbtree_insert(tree_size: u32)->LLRBTree<u32>{
+    let mut t = LLRBTree::new();
+    for i in 0..tree_size{
+        t.insert(i);
+    }
+    t
+}
+
+fn criterion_benchmark_llrbtree_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("LLRB Group");
+    group.measurement_time(Duration::from_secs(13));
+    group.bench_function("insert 10000 elements in the Left-Leaning Red-Black Tree", |b| {
+        b.iter(|| bench_llrbtree_insert(black_box(10000)))
     });
-    tree = bench_rbtree_insert(40000);
-    c.bench_function("4000 lowest elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_search(black_box(40000),tree.clone()))
+    group.bench_function("insert 40000 elements in the Left-Leaning Red-Black Tree", |b| {
+        b.iter(|| bench_llrbtree_insert(black_box(40000)))
     });
-    tree = bench_rbtree_insert(70000);
-    c.bench_function("7000 lowest elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_search(black_box(70000),tree.clone()))
+    group.bench_function("insert 70000 elements in the Left-Leaning Red-Black Tree", |b| {
+        b.iter(|| bench_llrbtree_insert(black_box(70000)))
     });
-    let mut tree = bench_rbtree_insert(100000);
-    c.bench_function("search for the 10000 lowest elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_search(black_box(100000),tree.clone()))
+    group.bench_function("insert 100000 elements in the Left-Leaning Red-Black Tree", |b| {
+        b.iter(|| bench_llrbtree_insert(black_box(100000)))
     });
-    let mut tree = bench_rbtree_insert(130000);
-    c.bench_function("search for the 10000 lowest elements in the Red-Black Tree", |b| {
-        b.iter(|| bench_rbtree_search(black_box(130000),tree.clone()))
+    group.bench_function("insert 130000 elements in the Left-Leaning Red-Black Tree", |b| {
+        b.iter(|| bench_llrbtree_insert(black_box(130000)))
     });
+    group.finish();
 }
 
-fn bench_avltree_insert(tree_size: u32)->AvlTree<u32>{
-    let mut t = AvlTree::new();
+fn bench_aatree_insert(tree_size: u32)->AATree<u32>{
+    let mut t = AATree::new();
     for i in 0..tree_size{
         t.insert(i);
     }
     t
 }
 
-fn bench_avltree_search(tree_size: u32,tree:AvlTree<u32>){
-    let lowest = tree_size/10;
-    for i in 0..lowest{
-        tree.search(i);
-    }
-}
-
-
-fn criterion_benchmark_avltree_insert(c: &mut Criterion) {
-    let mut group = c.benchmark_group("AVL Group");
+fn criterion_benchmark_aatree_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("AA Group");
     group.measurement_time(Duration::from_secs(13));
-    group.bench_function("insert 10000 elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_insert(black_box(10000)))
+    group.bench_function("insert 10000 elements in the AA Tree", |b| {
+        b.iter(|| bench_aatree_insert(black_box(10000)))
     });
-    group.bench_function("insert 40000 elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_insert(black_box(40000)))
+    group.bench_function("insert 40000 elements in the AA Tree", |b| {
+        b.iter(|| bench_aatree_insert(black_box(40000)))
     });
-    group.bench_function("insert 70000 elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_insert(black_box(70000)))
+    group.bench_function("insert 70000 elements in the AA Tree", |b| {
+        b.iter(|| bench_aatree_insert(black_box(70000)))
     });
-    group.bench_function("insert 100000 elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_insert(black_box(100000)))
+    group.bench_function("insert 100000 elements in the AA Tree", |b| {
+        b.iter(|| bench_aatree_insert(black_box(100000)))
     });
-    group.bench_function("insert 130000 elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_insert(black_box(130000)))
+    group.bench_function("insert 130000 elements in the AA Tree", |b| {
+        b.iter(|| bench_aatree_insert(black_box(130000)))
     });
     group.finish();
 }
-fn criterion_benchmark_avltree_search(c :&mut Criterion) {
-    let mut tree = bench_avltree_insert(10000);
-    c.bench_function("search for the 1000 lowest elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_search(black_box(10000),tree.clone()))
+
+fn bench_splaytree_insert(tree_size: u32)->SplayTree<u32>{
+    let mut t = SplayTree::new();
+    for i in 0..tree_size{
+        t.insert(i);
+    }
+    t
+}
+
+fn criterion_benchmark_splaytree_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Splay Group");
+    group.measurement_time(Duration::from_secs(13));
+    group.bench_function("insert 10000 elements in the Splay Tree", |b| {
+        b.iter(|| bench_splaytree_insert(black_box(10000)))
     });
-    tree = bench_avltree_insert(40000);
-    c.bench_function("4000 lowest elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_search(black_box(40000),tree.clone()))
+    group.bench_function("insert 40000 elements in the Splay Tree", |b| {
+        b.iter(|| bench_splaytree_insert(black_box(40000)))
     });
-    tree = bench_avltree_insert(70000);
-    c.bench_function("7000 lowest elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_search(black_box(70000),tree.clone()))
+    group.bench_function("insert 70000 elements in the Splay Tree", |b| {
+        b.iter(|| bench_splaytree_insert(black_box(70000)))
     });
-    tree = bench_avltree_insert(100000);
-    c.bench_function("search for the 10000 lowest elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_search(black_box(100000),tree.clone()))
+    group.bench_function("insert 100000 elements in the Splay Tree", |b| {
+        b.iter(|| bench_splaytree_insert(black_box(100000)))
     });
-    tree = bench_avltree_insert(130000);
-    c.bench_function("search for the 10000 lowest elements in the AVL Tree", |b| {
-        b.iter(|| bench_avltree_search(black_box(130000),tree.clone()))
+    group.bench_function("insert 130000 elements in the Splay Tree", |b| {
+        b.iter(|| bench_splaytree_insert(black_box(130000)))
     });
+    group.finish();
 }
 
-fn bench_bst_insert(tree_size: u32)->BST<u32>{
-    let mut t = BST::new();
+fn bench_treap_insert(tree_size: u32)->Treap<u32>{
+    // fixed seed so every run builds the same shape
+    let mut t = Treap::with_seed(42);
     for i in 0..tree_size{
         t.insert(i);
     }
-   t
+    t
 }
 
-fn bench_bst_search(tree_size: u32,tree:BST<u32>){
-    let lowest = tree_size/10;
-    for i in 0..lowest{
-        tree.search(i);
+fn criterion_benchmark_treap_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Treap Group");
+    group.measurement_time(Duration::from_secs(13));
+    group.bench_function("insert 10000 elements in the Treap", |b| {
+        b.iter(|| bench_treap_insert(black_box(10000)))
+    });
+    group.bench_function("insert 40000 elements in the Treap", |b| {
+        b.iter(|| bench_treap_insert(black_box(40000)))
+    });
+    group.bench_function("insert 70000 elements in the Treap", |b| {
+        b.iter(|| bench_treap_insert(black_box(70000)))
+    });
+    group.bench_function("insert 100000 elements in the Treap", |b| {
+        b.iter(|| bench_treap_insert(black_box(100000)))
+    });
+    group.bench_function("insert 130000 elements in the Treap", |b| {
+        b.iter(|| bench_treap_insert(black_box(130000)))
+    });
+    group.finish();
+}
+
+// skewed lookups: 9 out of 10 go to one of 16 hot keys, the rest are spread over the whole tree
+fn skewed_key(i: u32, tree_size: u32) -> u32 {
+    if i.is_multiple_of(10) {
+        (i * 7919) % tree_size
+    } else {
+        (i % 16) * (tree_size / 16)
     }
 }
 
+fn criterion_benchmark_skewed_search(c: &mut Criterion) {
+    let tree_size = 100000;
+    let lookups = 100000;
+    let mut group = c.benchmark_group("Skewed Search Group");
+    group.measurement_time(Duration::from_secs(13));
+    group.bench_function("100000 skewed searches in the Splay Tree", |b| {
+        // searching splays, so every run starts from a freshly built tree
+        b.iter_batched(|| bench_splaytree_insert(tree_size), |mut tree| {
+            for i in 0..lookups {
+                tree.contains(&black_box(skewed_key(i, tree_size)));
+            }
+            tree
+        }, BatchSize::LargeInput)
+    });
+    let tree = bench_avltree_insert(tree_size);
+    group.bench_function("100000 skewed searches in the AVL Tree", |b| {
+        b.iter(|| {
+            for i in 0..lookups {
+                tree.contains(&black_box(skewed_key(i, tree_size)));
+            }
+        })
+    });
+    let tree: ScapegoatTree<u32> = (0..tree_size).fold(ScapegoatTree::new(), |mut t, key| { t.insert(key); t });
+    group.bench_function("100000 skewed searches in the Scapegoat Tree", |b| {
+        b.iter(|| {
+            for i in 0..lookups {
+                tree.contains(&black_box(skewed_key(i, tree_size)));
+            }
+        })
+    });
+    let tree: WBTree<u32> = (0..tree_size).fold(WBTree::new(), |mut t, key| { t.insert(key); t });
+    group.bench_function("100000 skewed searches in the Weight-Balanced Tree", |b| {
+        b.iter(|| {
+            for i in 0..lookups {
+                tree.contains(&black_box(skewed_key(i, tree_size)));
+            }
+        })
+    });
+    let tree = bench_rbtree_insert(tree_size);
+    group.bench_function("100000 skewed searches in the Red-Black Tree", |b| {
+        b.iter(|| {
+            for i in 0..lookups {
+                tree.contains(&black_box(skewed_key(i, tree_size)));
+            }
+        })
+    });
+    group.finish();
+}
 
-// This will cause fatal runtime error: stack overflow
+// the same shuffled keys for every tree, sorted inserts would flatter the node based trees
+fn shuffled_keys(tree_size: u32) -> Vec<u32> {
+    let mut keys: Vec<u32> = (0..tree_size).collect();
+    Rng::new(7).shuffle(&mut keys);
+    keys
+}
 
-fn criterion_benchmark_bst_insert(c: &mut Criterion) {
-    let mut group = c.benchmark_group("Bst Group");
+fn criterion_benchmark_ordered_insert(c: &mut Criterion) {
+    let keys = shuffled_keys(100000);
+    let mut group = c.benchmark_group("Ordered Set Insert Group");
     group.measurement_time(Duration::from_secs(13));
-    group.bench_function("insert 10000 elements in the BST", |b| {
-        b.iter(|| bench_bst_insert(black_box(10000)))
+    group.bench_function("insert 100000 shuffled elements in the Red-Black Tree", |b| {
+        b.iter(|| {
+            let mut t = RBTree::new();
+            for key in keys.iter() {
+                t.insert(black_box(*key));
+            }
+            t
+        })
+    });
+    group.bench_function("insert 100000 shuffled elements in the AVL Tree", |b| {
+        b.iter(|| {
+            let mut t = AvlTree::new();
+            for key in keys.iter() {
+                t.insert(black_box(*key));
+            }
+            t
+        })
     });
-    group.bench_function("insert 40000 elements in the BST", |b| {
-        b.iter(|| bench_bst_insert(black_box(40000)))
+    group.bench_function("insert 100000 shuffled elements in the Scapegoat Tree", |b| {
+        b.iter(|| {
+            let mut t = ScapegoatTree::new();
+            for key in keys.iter() {
+                t.insert(black_box(*key));
+            }
+            t
+        })
     });
-    group.bench_function("insert 70000 elements in the BST", |b| {
-        b.iter(|| bench_bst_insert(black_box(70000)))
+    group.bench_function("insert 100000 shuffled elements in the Weight-Balanced Tree", |b| {
+        b.iter(|| {
+            let mut t = WBTree::new();
+            for key in keys.iter() {
+                t.insert(black_box(*key));
+            }
+            t
+        })
     });
-    group.bench_function("insert 100000 elements in the BST", |b| {
-        b.iter(|| bench_bst_insert(black_box(100000)))
+    group.bench_function("insert 100000 shuffled elements in the B-Tree", |b| {
+        b.iter(|| {
+            let mut t = BTree::new();
+            for key in keys.iter() {
+                t.insert(black_box(*key));
+            }
+            t
+        })
     });
-    group.bench_function("insert 130000 elements in the BST", |b| {
-        b.iter(|| bench_bst_insert(black_box(130000)))
+    group.bench_function("insert 100000 shuffled elements in the B+Tree", |b| {
+        b.iter(|| {
+            let mut t = BPlusTree::new();
+            for key in keys.iter() {
+                t.insert(black_box(*key));
+            }
+            t
+        })
+    });
+    group.bench_function("insert 100000 shuffled elements in the std BTreeSet", |b| {
+        b.iter(|| {
+            let mut t = BTreeSet::new();
+            for key in keys.iter() {
+                t.insert(black_box(*key));
+            }
+            t
+        })
     });
     group.finish();
 }
 
-fn criterion_benchmark_bst_search(c :&mut Criterion) {
-    let mut tree = bench_bst_insert(10000);
-    c.bench_function("search for the 1000 lowest elements in the BST", |b| {
-        b.iter(|| bench_bst_search(black_box(10000),tree.clone()))
+fn criterion_benchmark_ordered_search(c: &mut Criterion) {
+    let keys = shuffled_keys(100000);
+    let mut group = c.benchmark_group("Ordered Set Search Group");
+    group.measurement_time(Duration::from_secs(13));
+    let tree = bench_rbtree_insert(100000);
+    group.bench_function("100000 searches in the Red-Black Tree", |b| {
+        b.iter(|| for key in keys.iter() { tree.contains(black_box(key)); })
+    });
+    let tree = bench_avltree_insert(100000);
+    group.bench_function("100000 searches in the AVL Tree", |b| {
+        b.iter(|| for key in keys.iter() { tree.contains(black_box(key)); })
+    });
+    let tree: ScapegoatTree<u32> = keys.iter().fold(ScapegoatTree::new(), |mut t, key| { t.insert(*key); t });
+    group.bench_function("100000 searches in the Scapegoat Tree", |b| {
+        b.iter(|| for key in keys.iter() { tree.contains(black_box(key)); })
+    });
+    let tree: WBTree<u32> = keys.iter().fold(WBTree::new(), |mut t, key| { t.insert(*key); t });
+    group.bench_function("100000 searches in the Weight-Balanced Tree", |b| {
+        b.iter(|| for key in keys.iter() { tree.contains(black_box(key)); })
     });
-    tree = bench_bst_insert(40000);
-    c.bench_function("4000 lowest elements in the BST", |b| {
-        b.iter(|| bench_bst_search(black_box(40000),tree.clone()))
+    let tree: BTree<u32> = keys.iter().fold(BTree::new(), |mut t, key| { t.insert(*key); t });
+    group.bench_function("100000 searches in the B-Tree", |b| {
+        b.iter(|| for key in keys.iter() { tree.contains(black_box(key)); })
     });
-    tree = bench_bst_insert(70000);
-    c.bench_function("7000 lowest elements in the BST", |b| {
-        b.iter(|| bench_bst_search(black_box(70000),tree.clone()))
+    let tree: BPlusTree<u32> = keys.iter().fold(BPlusTree::new(), |mut t, key| { t.insert(*key); t });
+    group.bench_function("100000 searches in the B+Tree", |b| {
+        b.iter(|| for key in keys.iter() { tree.contains(black_box(key)); })
     });
-    let mut tree = bench_bst_insert(100000);
-    c.bench_function("search for the 10000 lowest elements in the BST", |b| {
-        b.iter(|| bench_bst_search(black_box(100000),tree.clone()))
+    let tree: BTreeSet<u32> = keys.iter().copied().collect();
+    group.bench_function("100000 searches in the std BTreeSet", |b| {
+        b.iter(|| for key in keys.iter() { tree.contains(black_box(key)); })
     });
-    let mut tree = bench_bst_insert(130000);
-    c.bench_function("search for the 10000 lowest elements in the BST", |b| {
-        b.iter(|| bench_bst_search(black_box(130000),tree.clone()))
+    group.finish();
+}
+
+fn criterion_benchmark_range_scan(c: &mut Criterion) {
+    let keys = shuffled_keys(100000);
+    let mut group = c.benchmark_group("Range Scan Group");
+    group.measurement_time(Duration::from_secs(13));
+    // 100 scans of 1000 keys each
+    let tree: BTree<u32> = keys.iter().fold(BTree::new(), |mut t, key| { t.insert(*key); t });
+    group.bench_function("100 range scans of 1000 keys in the B-Tree", |b| {
+        b.iter(|| for lo in (0..100000).step_by(1000) { black_box(tree.range(lo, lo + 1000)); })
     });
+    let tree: BPlusTree<u32> = keys.iter().fold(BPlusTree::new(), |mut t, key| { t.insert(*key); t });
+    group.bench_function("100 range scans of 1000 keys in the B+Tree", |b| {
+        b.iter(|| for lo in (0..100000).step_by(1000) { black_box(tree.range(lo, lo + 1000)); })
+    });
+    let tree: BTreeSet<u32> = keys.iter().copied().collect();
+    group.bench_function("100 range scans of 1000 keys in the std BTreeSet", |b| {
+        b.iter(|| for lo in (0..100000).step_by(1000) { black_box(tree.range(lo..lo + 1000).copied().collect::<Vec<u32>>()); })
+    });
+    group.finish();
 }
 
-criterion_group!(benches,criterion_benchmark_rbtree_insert, criterion_benchmark_avltree_insert);
+criterion_group!(benches,criterion_benchmark_insert, criterion_benchmark_delete, criterion_benchmark_search, criterion_benchmark_mixed, criterion_benchmark_scan, criterion_benchmark_iterate, criterion_benchmark_llrbtree_insert, criterion_benchmark_aatree_insert, criterion_benchmark_treap_insert, criterion_benchmark_splaytree_insert, criterion_benchmark_skewed_search, criterion_benchmark_ordered_insert, criterion_benchmark_ordered_search, criterion_benchmark_range_scan);
 criterion_main!(benches);
\ No newline at end of file
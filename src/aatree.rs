@@ -0,0 +1,555 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use crate::shape::{Attr, Shape};
+use crate::compare::{Comparator, Natural};
+
+type TreeNode<T> = Rc<RefCell<Node<T>>>;
+type Tree<T> = Option<TreeNode<T>>;
+
+#[derive(Clone)]
+struct Node<T: Display+Debug> {
+    key: T,
+    // 1 for leaves, a left child is always one level down, a right child one level down or on the
+    // same level as a horizontal link
+    level: u32,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T>
+where T: Debug+Display+Copy
+{
+    pub fn new(key: T) -> TreeNode<T> {
+        Rc::new(RefCell::new(Node {
+            key,
+            level: 1,
+            left: None,
+            right: None,
+        }))
+    }
+}
+
+impl<T> fmt::Debug for Node<T>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+         .field("key", &self.key)
+         .field("level", &self.level)
+         .field("right", &self.right)
+         .field("left", &self.left)
+         .finish()
+    }
+}
+
+fn level<T: Display+Debug>(tree: &Tree<T>) -> u32 {
+    tree.as_ref().map_or(0, |node| node.borrow().level)
+}
+
+// a left child on the same level is a left horizontal link, rotate it into a right one
+fn skew<T: Display+Debug>(h: TreeNode<T>) -> TreeNode<T> {
+    if level(&h.borrow().left) != h.borrow().level {
+        return h;
+    }
+    let l = h.borrow_mut().left.take().unwrap();
+    h.borrow_mut().left = l.borrow_mut().right.take();
+    l.borrow_mut().right = Some(h);
+    l
+}
+
+// two right horizontal links in a row, lift the middle node a level
+fn split<T: Display+Debug>(h: TreeNode<T>) -> TreeNode<T> {
+    let right_right = h.borrow().right.as_ref().and_then(|right| right.borrow().right.clone());
+    if level(&right_right) != h.borrow().level {
+        return h;
+    }
+    let r = h.borrow_mut().right.take().unwrap();
+    h.borrow_mut().right = r.borrow_mut().left.take();
+    r.borrow_mut().left = Some(h);
+    r.borrow_mut().level += 1;
+    r
+}
+
+// after a delete below h, pull h down to the level its children allow and redo the skews and splits
+fn rebalance<T: Display+Debug>(mut h: TreeNode<T>) -> TreeNode<T> {
+    let should_be = level(&h.borrow().left).min(level(&h.borrow().right)) + 1;
+    if should_be < h.borrow().level {
+        h.borrow_mut().level = should_be;
+        if let Some(right) = &h.borrow().right {
+            if right.borrow().level > should_be {
+                right.borrow_mut().level = should_be;
+            }
+        }
+    }
+    h = skew(h);
+    let right = h.borrow_mut().right.take();
+    if let Some(right) = right {
+        let right = skew(right);
+        let right_right = right.borrow_mut().right.take();
+        right.borrow_mut().right = right_right.map(skew);
+        h.borrow_mut().right = Some(right);
+    }
+    h = split(h);
+    let right = h.borrow_mut().right.take();
+    h.borrow_mut().right = right.map(split);
+    h
+}
+
+fn subtree_min<T: Display+Debug+Copy>(node: &TreeNode<T>) -> T {
+    let mut cur = node.clone();
+    loop {
+        let left = cur.borrow().left.clone();
+        match left {
+            Some(left) => cur = left,
+            None => return cur.borrow().key,
+        }
+    }
+}
+
+fn subtree_max<T: Display+Debug+Copy>(node: &TreeNode<T>) -> T {
+    let mut cur = node.clone();
+    loop {
+        let right = cur.borrow().right.clone();
+        match right {
+            Some(right) => cur = right,
+            None => return cur.borrow().key,
+        }
+    }
+}
+
+// Andersson's AA tree: a red-black tree where only right children can be red, tracked as levels
+// instead of colors. Skew and split are the only two rebalancing steps for both insert and delete.
+#[derive(Clone, Debug)]
+pub struct AATree<T: Display+Debug+Copy, C = Natural> {
+    root: Tree<T>,
+    count: usize,
+    cmp: C,
+}
+
+impl<T> AATree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C> AATree<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        AATree {
+            root: None,
+            count: 0,
+            cmp,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn insert(&mut self, key: T) {
+        let mut inserted = false;
+        let root = self.root.take();
+        self.root = Some(self.insert_node(root, key, &mut inserted));
+        if inserted {
+            self.count += 1;
+        }
+    }
+
+    fn insert_node(&self, tree: Tree<T>, key: T, inserted: &mut bool) -> TreeNode<T> {
+        let h = match tree {
+            Some(h) => h,
+            None => {
+                *inserted = true;
+                return Node::new(key);
+            }
+        };
+        let order = self.cmp.compare(&key, &h.borrow().key);
+        match order {
+            Ordering::Less => {
+                let left = h.borrow_mut().left.take();
+                h.borrow_mut().left = Some(self.insert_node(left, key, inserted));
+            },
+            Ordering::Greater => {
+                let right = h.borrow_mut().right.take();
+                h.borrow_mut().right = Some(self.insert_node(right, key, inserted));
+            },
+            // key already in tree
+            Ordering::Equal => return h,
+        }
+        split(skew(h))
+    }
+
+    pub fn delete(&mut self, key: T) {
+        let mut deleted = false;
+        let root = self.root.take();
+        self.root = self.delete_node(root, &key, &mut deleted);
+        if deleted {
+            self.count -= 1;
+        }
+    }
+
+    fn delete_node(&self, tree: Tree<T>, key: &T, deleted: &mut bool) -> Tree<T> {
+        let h = tree?;
+        let order = self.cmp.compare(key, &h.borrow().key);
+        match order {
+            Ordering::Less => {
+                let left = h.borrow_mut().left.take();
+                h.borrow_mut().left = self.delete_node(left, key, deleted);
+            },
+            Ordering::Greater => {
+                let right = h.borrow_mut().right.take();
+                h.borrow_mut().right = self.delete_node(right, key, deleted);
+            },
+            Ordering::Equal => {
+                *deleted = true;
+                let left = h.borrow_mut().left.take();
+                let right = h.borrow_mut().right.take();
+                match (left, right) {
+                    (None, None) => return None,
+                    (None, Some(right)) => {
+                        // swap in the successor and delete it from the right subtree instead
+                        let successor = subtree_min(&right);
+                        h.borrow_mut().key = successor;
+                        h.borrow_mut().right = self.delete_node(Some(right), &successor, &mut false);
+                    },
+                    (Some(left), right) => {
+                        let predecessor = subtree_max(&left);
+                        h.borrow_mut().key = predecessor;
+                        h.borrow_mut().left = self.delete_node(Some(left), &predecessor, &mut false);
+                        h.borrow_mut().right = right;
+                    }
+                }
+            }
+        }
+        Some(rebalance(h))
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.find(key).map(|node| node.borrow().key)
+    }
+
+    // the node holding key, private so callers cannot change a key and break the ordering
+    fn find(&self, key: &T) -> Tree<T> {
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    // check key order and the level rules: leaves are on level 1, left children one level down,
+    // right children at most one level down, no two right horizontal links in a row, and every node
+    // above level 1 has two children
+    pub fn validate(&self) -> Result<(), String> {
+        let size = self.check(&self.root, None, None)?;
+        if size != self.count {
+            return Err(format!("count is {} but the tree holds {} keys", self.count, size));
+        }
+        Ok(())
+    }
+
+    // returns the size of the subtree
+    fn check(&self, tree: &Tree<T>, lo: Option<T>, hi: Option<T>) -> Result<usize, String> {
+        let node = match tree {
+            Some(node) => node.borrow(),
+            None => return Ok(0),
+        };
+        if lo.is_some_and(|lo| self.cmp.compare(&node.key, &lo) != Ordering::Greater)
+            || hi.is_some_and(|hi| self.cmp.compare(&node.key, &hi) != Ordering::Less) {
+            return Err(format!("{} is out of order", node.key));
+        }
+        if level(&node.left) + 1 != node.level {
+            return Err(format!("left child of {} is not one level down", node.key));
+        }
+        let right_level = level(&node.right);
+        if right_level != node.level && right_level + 1 != node.level {
+            return Err(format!("right child of {} is neither on its level nor one down", node.key));
+        }
+        let right_right = node.right.as_ref().and_then(|right| right.borrow().right.clone());
+        if level(&right_right) >= node.level {
+            return Err(format!("two horizontal links in a row below {}", node.key));
+        }
+        if node.level > 1 && (node.left.is_none() || node.right.is_none()) {
+            return Err(format!("{} is above level 1 without two children", node.key));
+        }
+        Ok(self.check(&node.left, lo, Some(node.key))? + self.check(&node.right, Some(node.key), hi)? + 1)
+    }
+
+    pub fn height(&self) -> u32 {
+        if self.root.is_none() {
+            return 0;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        let mut height = 0;
+        // find height by breadth first search traversal
+        while !queue.is_empty() {
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                    queue.push_back(child);
+                }
+            }
+            height += 1;
+        }
+        height
+    }
+
+    pub fn leaves(&self) -> u32 {
+        let mut stack: Vec<TreeNode<T>> = self.root.clone().into_iter().collect();
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            let left = node.borrow().left.clone();
+            let right = node.borrow().right.clone();
+            if left.is_none() && right.is_none() {
+                count += 1;
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        count
+    }
+
+    // iterate over the keys in order
+    pub fn iter(&self) -> Iter<T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    pub fn print_preorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut stack: Vec<TreeNode<T>> = vec![self.root.clone().unwrap()];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", node.borrow().key);
+            stack.extend(node.borrow().right.clone());
+            stack.extend(node.borrow().left.clone());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", node.borrow().key);
+            queue.extend(node.borrow().left.clone());
+            queue.extend(node.borrow().right.clone());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout with each node's level, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T>| {
+            let node = node.borrow();
+            (vec![node.key], vec![("level", Attr::Number(node.level as u64))], vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        };
+
+        fn pretty_print<T: Display+Debug>(node: TreeNode<T>, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&node.borrow().key.to_string());
+            buffer.push_str(&format!(" (level {})", node.borrow().level));
+            buffer.push('\n');
+            for child in [node.borrow().right.clone(), node.borrow().left.clone()].into_iter().flatten() {
+                let has_children = child.borrow().left.is_some() || child.borrow().right.is_some();
+                let (branch, indent) = if has_children { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(self.root.clone().unwrap(), &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for AATree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy> {
+    stack: Vec<TreeNode<T>>,
+}
+
+impl<T> Iter<T>
+where T: Display+Debug+Copy
+{
+    fn push_left(&mut self, tree: Tree<T>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T>
+where T: Display+Debug+Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let key = node.borrow().key;
+        self.push_left(node.borrow().right.clone());
+        Some(key)
+    }
+}
+
+impl<T, C> fmt::Display for AATree<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AATree")
+         .field("root", &self.root)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+
+#[test]
+pub fn create_empty_aatree() {
+    let tree: AATree<u32> = AATree::new();
+    assert!(tree.is_empty());
+    assert_eq!(tree.height(), 0);
+    assert!(tree.last().is_none());
+    assert_eq!(tree.validate(), Ok(()));
+}
+
+#[test]
+pub fn test_aa_skew_and_split() {
+    let mut tree = AATree::new();
+    tree.insert(1);
+    tree.insert(2);
+    // 2 hangs off 1 as a horizontal link on level 1
+    assert_eq!(tree.root.as_ref().unwrap().borrow().key, 1);
+    assert_eq!(tree.height(), 2);
+    tree.insert(3);
+    // the second horizontal link gets split, 2 moves up to level 2
+    let root = tree.root.clone().unwrap();
+    assert_eq!(root.borrow().key, 2);
+    assert_eq!(root.borrow().level, 2);
+    assert_eq!(tree.validate(), Ok(()));
+}
+
+#[test]
+pub fn test_aa_sorted_inserts_and_deletes() {
+    let mut tree = AATree::new();
+    for key in 0..1024 {
+        tree.insert(key);
+    }
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.height() <= 20);
+    for key in (0..1024).step_by(2) {
+        tree.delete(key);
+    }
+    tree.delete(5000);
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.count(), 512);
+    assert_eq!(tree.first().unwrap(), 1);
+    assert_eq!(tree.last().unwrap(), 1023);
+}
+
+#[test]
+pub fn test_aa_random() {
+    let mut tree = AATree::new();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut rng = crate::rng::Rng::new(35);
+    for _ in 0..3000 {
+        let key = rng.below(500) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+            keys.remove(&key);
+        } else {
+            tree.insert(key);
+            keys.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_aa_reverse_comparator() {
+    let mut tree = AATree::with_comparator(crate::compare::Reverse);
+    for key in [3, 1, 2] {
+        tree.insert(key);
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), vec![3, 2, 1]);
+    assert_eq!(tree.validate(), Ok(()));
+}
@@ -0,0 +1,192 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::{Add, Rem};
+
+// A summary of a whole subtree stored in every node, e.g. the largest interval end below it.
+// The trees call summarize bottom-up whenever a node's children or occurrences change, so a
+// node's value is always built from its own key and the values already in its children.
+pub trait Augment<T> {
+    type Value: Copy+Debug;
+
+    // occurrences is how many times key is stored in the node, only ever above 1 in a multiset
+    fn summarize(key: &T, occurrences: usize, left: Option<Self::Value>, right: Option<Self::Value>) -> Self::Value;
+}
+
+// No augmentation, the default for every tree.
+impl<T> Augment<T> for () {
+    type Value = ();
+
+    fn summarize(_key: &T, _occurrences: usize, _left: Option<()>, _right: Option<()>) {}
+}
+
+// An associative combine with an identity, e.g. sum, min or gcd. Wrapped in Aggregate it becomes
+// an Augment, so every node caches the fold of its subtree and range folds only visit O(log n) nodes.
+pub trait Monoid<T> {
+    type Value: Copy+Debug;
+
+    fn identity() -> Self::Value;
+
+    // the value of a single key
+    fn lift(key: &T) -> Self::Value;
+
+    fn combine(a: Self::Value, b: Self::Value) -> Self::Value;
+
+    // the value of key stored n times, by repeated doubling so a multiset node costs O(log n)
+    fn repeat(key: &T, n: usize) -> Self::Value {
+        let mut value = Self::identity();
+        let mut base = Self::lift(key);
+        let mut n = n;
+        while n > 0 {
+            if n & 1 == 1 {
+                value = Self::combine(value, base);
+            }
+            base = Self::combine(base, base);
+            n >>= 1;
+        }
+        value
+    }
+}
+
+// Caches the fold of M over every subtree, e.g. `RBTree<u64, Natural, Aggregate<Sum>>`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aggregate<M>(PhantomData<M>);
+
+impl<T, M: Monoid<T>> Augment<T> for Aggregate<M> {
+    type Value = M::Value;
+
+    fn summarize(key: &T, occurrences: usize, left: Option<M::Value>, right: Option<M::Value>) -> M::Value {
+        let left = left.unwrap_or_else(M::identity);
+        let right = right.unwrap_or_else(M::identity);
+        M::combine(M::combine(left, M::repeat(key, occurrences)), right)
+    }
+}
+
+// number of keys
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Count;
+
+impl<T> Monoid<T> for Count {
+    type Value = usize;
+
+    fn identity() -> usize {
+        0
+    }
+
+    fn lift(_key: &T) -> usize {
+        1
+    }
+
+    fn combine(a: usize, b: usize) -> usize {
+        a + b
+    }
+
+    fn repeat(_key: &T, n: usize) -> usize {
+        n
+    }
+}
+
+// sum of the keys, starting from T::default()
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sum;
+
+impl<T: Copy+Debug+Default+Add<Output = T>> Monoid<T> for Sum {
+    type Value = T;
+
+    fn identity() -> T {
+        T::default()
+    }
+
+    fn lift(key: &T) -> T {
+        *key
+    }
+
+    fn combine(a: T, b: T) -> T {
+        a + b
+    }
+}
+
+// smallest key by Ord, None when there are no keys
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Min;
+
+impl<T: Copy+Debug+Ord> Monoid<T> for Min {
+    type Value = Option<T>;
+
+    fn identity() -> Option<T> {
+        None
+    }
+
+    fn lift(key: &T) -> Option<T> {
+        Some(*key)
+    }
+
+    fn combine(a: Option<T>, b: Option<T>) -> Option<T> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    fn repeat(key: &T, _n: usize) -> Option<T> {
+        Some(*key)
+    }
+}
+
+// largest key by Ord, None when there are no keys
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Max;
+
+impl<T: Copy+Debug+Ord> Monoid<T> for Max {
+    type Value = Option<T>;
+
+    fn identity() -> Option<T> {
+        None
+    }
+
+    fn lift(key: &T) -> Option<T> {
+        Some(*key)
+    }
+
+    fn combine(a: Option<T>, b: Option<T>) -> Option<T> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    fn repeat(key: &T, _n: usize) -> Option<T> {
+        Some(*key)
+    }
+}
+
+// greatest common divisor of unsigned keys, T::default() (zero) is the identity
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Gcd;
+
+impl<T: Copy+Debug+Default+PartialEq+Rem<Output = T>> Monoid<T> for Gcd {
+    type Value = T;
+
+    fn identity() -> T {
+        T::default()
+    }
+
+    fn lift(key: &T) -> T {
+        *key
+    }
+
+    fn combine(a: T, b: T) -> T {
+        let (mut a, mut b) = (a, b);
+        while b != T::default() {
+            let r = a % b;
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    fn repeat(key: &T, _n: usize) -> T {
+        *key
+    }
+}
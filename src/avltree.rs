@@ -4,92 +4,136 @@ use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt;
 use std::cmp::max;
+use std::cmp::Ordering;
 use std::collections::VecDeque;
+use std::mem::size_of;
+use std::ops::Bound;
+use crate::shape::{Attr, Shape};
+use crate::compare::{Comparator, Natural};
+use crate::augment::{Augment, Aggregate, Monoid};
+use crate::bst::BST;
+use crate::memory::{MemoryUsage, NodeFields};
 
 #[allow(non_snake_case)]
 
-type TreeNode<T> = Rc<RefCell<Node<T>>>;
-type Tree<T> = Option<TreeNode<T>>;
+type TreeNode<T, A = ()> = Rc<RefCell<Node<T, A>>>;
+type Tree<T, A = ()> = Option<TreeNode<T, A>>;
 
-#[derive(Clone, PartialEq)]
-pub struct Node<T: Ord+Display+Debug>{
+struct Node<T: Display+Debug, A: Augment<T> = ()>{
     key: T,
-    left : Tree<T>,
-    right : Tree<T>,
+    // how many times key was inserted, only ever above 1 in a multiset
+    occurrences : usize,
+    // summary of the subtree rooted here, rebuilt by update_height
+    aug : A::Value,
+    left : Tree<T, A>,
+    right : Tree<T, A>,
     height : i8,
 }
 
 
-impl <T> Node<T>
-where T: Debug+Ord+Display+Copy{
-    pub fn new(key :T) -> Tree<T>{
-        Some(Rc::new(RefCell::new(Node{key:key,left:None,right:None,height:1})))
+impl <T, A> Node<T, A>
+where T: Debug+Display+Copy, A: Augment<T>{
+    pub fn new(key :T) -> Tree<T, A>{
+        let aug = A::summarize(&key, 1, None, None);
+        Some(Rc::new(RefCell::new(Node{key:key,occurrences:1,aug,left:None,right:None,height:1})))
     }
+}
 
-    pub fn height(&self) -> i8 { 
-        self.height
+// derive would also require A: Clone and A: PartialEq, aug is left out of eq since it follows from the rest
+impl<T, A> Clone for Node<T, A>
+where T: Display+Debug+Clone, A: Augment<T>
+{
+    fn clone(&self) -> Self {
+        Node {
+            key: self.key.clone(),
+            occurrences: self.occurrences,
+            aug: self.aug,
+            left: self.left.clone(),
+            right: self.right.clone(),
+            height: self.height,
+        }
+    }
+}
+
+impl<T, A> PartialEq for Node<T, A>
+where T: Display+Debug+PartialEq, A: Augment<T>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.occurrences == other.occurrences && self.height == other.height
+            && self.left == other.left && self.right == other.right
     }
 }
 
-impl<T> fmt::Debug for Node<T>
-where T: Debug+Ord+Display+Copy
+impl<T, A> fmt::Debug for Node<T, A>
+where T: Debug+Display+Copy, A: Augment<T>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Node")
          .field("key", &self.key)
+         .field("occurrences", &self.occurrences)
          .field("right", &self.right)
          .field("left", &self.left)
          .finish()
     }
 }
 
+enum Direction {
+    Left,
+    Right
+}
+
 #[derive(Clone, Debug, PartialEq)]
-pub struct AvlTree<T:Ord + Display + Debug + Copy>{
-    root : Tree<T>,
+pub struct AvlTree<T: Display + Debug + Copy, C = Natural, A: Augment<T> = ()>{
+    root : Tree<T, A>,
     count : usize,
+    cmp : C,
+    multiset : bool,
+    rotations : u64,
 }
-trait _Tree<T>
-where T: Ord+Display+Debug+Clone+Copy{
-    fn new(key:T) -> Tree<T>;
-    fn height(&self,tree_node:Tree<T>) -> i8;
-    fn rotate_lr(&self,tree_node:Tree<T>) -> Tree<T>;
-    fn rotate_rl(&self,tree_node:Tree<T>) -> Tree<T>;
-    fn update_height(&self,tree_node:Tree<T>) -> Tree<T>;
-    fn do_insert(&self, root:Tree<T>,val: T) -> Tree<T>;
-    fn do_delete(&self,root:Tree<T>,val:T) -> Tree<T>;
-    fn balance_factor(&self,tree_node:Tree<T>) -> i8;
-    fn balance_tree(&self, tree_node:Tree<T>) -> Tree<T>;
-    fn rotate_left(&self,tree_node:Tree<T>) -> Tree<T>;
-    fn rotate_right(&self,tree_node:Tree<T>) ->Tree<T>;
-    fn find_min(&self,tree_node:Tree<T>) -> Tree<T>;
-    fn find_max(&self, tree: Tree<T>) -> Tree<T>;
-}
-
-impl <T> _Tree<T> for Tree<T>
-where T: Ord+Display+Debug+Clone+Copy{
-    fn new(key:T) -> Tree<T> {
-        Node::new(key)
-    }
-    fn height(&self,tree_node:Tree<T>) -> i8{
+trait _Tree<T, A>
+where T: Display+Debug+Clone+Copy, A: Augment<T>{
+    fn height(&self,tree_node:Tree<T, A>) -> i8;
+    fn rotate_lr(&self,tree_node:Tree<T, A>) -> Tree<T, A>;
+    fn rotate_rl(&self,tree_node:Tree<T, A>) -> Tree<T, A>;
+    fn update_height(&self,tree_node:Tree<T, A>) -> Tree<T, A>;
+    fn balance_factor(&self,tree_node:Tree<T, A>) -> i8;
+    fn balance_tree(&self, tree_node:Tree<T, A>, rotations: &mut u64) -> Tree<T, A>;
+    fn rotate_left(&self,tree_node:Tree<T, A>) -> Tree<T, A>;
+    fn rotate_right(&self,tree_node:Tree<T, A>) ->Tree<T, A>;
+    fn find_min(&self,tree_node:Tree<T, A>) -> Tree<T, A>;
+    fn find_max(&self, tree: Tree<T, A>) -> Tree<T, A>;
+}
+
+impl <T, A> _Tree<T, A> for Tree<T, A>
+where T: Display+Debug+Clone+Copy, A: Augment<T>{
+    fn height(&self,tree_node:Tree<T, A>) -> i8{
         match tree_node{
             None => 0,
             Some(node) => node.clone().borrow().height,
         }
     }
-    fn update_height(&self, tree_node:Tree<T>) ->Tree<T>{
+    fn update_height(&self, tree_node:Tree<T, A>) ->Tree<T, A>{
         match tree_node {
             None => tree_node,
             Some(node) => {
                 let left_height = self.height(node.borrow().clone().left);
                 let right_height = self.height(node.borrow().clone().right);
                 node.clone().borrow_mut().height = max(left_height,right_height) + 1;
+                // the subtree summary changes in exactly the places the height has to be redone
+                let aug = {
+                    let node = node.borrow();
+                    let left_aug = node.left.as_ref().map(|left| left.borrow().aug);
+                    let right_aug = node.right.as_ref().map(|right| right.borrow().aug);
+                    A::summarize(&node.key, node.occurrences, left_aug, right_aug)
+                };
+                node.borrow_mut().aug = aug;
                 Some(node)
             }
         }
         
     }
 
-    fn balance_factor(&self,tree_node:Tree<T>) -> i8 {
+    fn balance_factor(&self,tree_node:Tree<T, A>) -> i8 {
         match tree_node{
             Some(node) =>{
                 let left_height = self.height(node.borrow().clone().left.clone());
@@ -100,8 +144,8 @@ where T: Ord+Display+Debug+Clone+Copy{
         }
     }
 
-    fn rotate_right(&self,tree_node:Tree<T>) -> Tree<T> {
-        let final_tree: Tree<T>;
+    fn rotate_right(&self,tree_node:Tree<T, A>) -> Tree<T, A> {
+        let final_tree: Tree<T, A>;
         match tree_node{
             None => unreachable!(),
             Some(node) =>{
@@ -127,8 +171,8 @@ where T: Ord+Display+Debug+Clone+Copy{
     }
 }
 
-    fn rotate_left(&self,tree_node:Tree<T>) ->Tree<T> {
-        let final_tree:Tree<T>; 
+    fn rotate_left(&self,tree_node:Tree<T, A>) ->Tree<T, A> {
+        let final_tree:Tree<T, A>; 
         match tree_node{
             None => unreachable!(),
             Some(node) =>{
@@ -150,7 +194,7 @@ where T: Ord+Display+Debug+Clone+Copy{
             }
         }
     }
-    fn rotate_lr(&self, tree_node:Tree<T>) -> Tree<T> {
+    fn rotate_lr(&self, tree_node:Tree<T, A>) -> Tree<T, A> {
         let rotated_tree = tree_node.clone();
         match tree_node {
             Some(root) => {
@@ -163,7 +207,7 @@ where T: Ord+Display+Debug+Clone+Copy{
         }
     }
 
-    fn rotate_rl(&self,tree_node:Tree<T>) -> Tree<T>{
+    fn rotate_rl(&self,tree_node:Tree<T, A>) -> Tree<T, A>{
         let rotated_tree = tree_node.clone();
         match tree_node {
             Some(root) => {
@@ -176,90 +220,7 @@ where T: Ord+Display+Debug+Clone+Copy{
         }
     }
 
-    fn do_insert(&self,tree:Tree<T>,key: T) -> Tree<T> {
-        match tree {
-            None => {
-                let add_node = Self::new(key);
-                add_node.clone()
-            }
-            Some(root) => {
-                let clone_node = root.borrow().clone();
-                let balanced_tree :Tree<T>;
-                let updated_tree:Tree<T>;
-                let sub_node:Tree<T>;
-                if key == clone_node.key {
-                    Some(root.clone())
-                } 
-                else if key < clone_node.key {
-                    sub_node = root.borrow().left.clone();
-                    let result = self.do_insert(sub_node,key);
-                    let result_node = result;
-                    root.borrow_mut().left = result_node;
-                    let updated_tree = self.update_height(Some(root.clone()));
-                    let balanced_tree = self.balance_tree(updated_tree);
-                    balanced_tree.clone()
-                }
-                //进入右子树递归插入
-                else {
-                    sub_node = root.borrow().right.clone();
-                    let result = self.do_insert(sub_node,key);
-                    let result_node = result;
-                    root.borrow_mut().right = result_node;
-                    updated_tree = self.update_height(Some(root));
-                    balanced_tree = self.balance_tree(updated_tree);
-                    balanced_tree.clone()
-                }
-            }
-        }
-    }
-
-    fn do_delete(&self,tree:Tree<T>,key:T) -> Tree<T>{
-        let deleted_tree = tree.clone();
-        let updated_tree:Tree<T>;
-        let balanced_tree:Tree<T>;
-        if tree.is_none(){
-            return tree.clone();
-        }
-        else{
-            let sub_node_left = tree.as_ref().unwrap().borrow().left.clone();
-            let sub_node_right = tree.as_ref().unwrap().borrow().right.clone();
-            if key < tree.as_ref().unwrap().borrow().key{
-                deleted_tree.as_ref().unwrap().borrow_mut().left = self.do_delete(sub_node_left, key);
-                updated_tree = self.update_height(deleted_tree.clone());
-                balanced_tree = self.balance_tree(updated_tree);
-                return balanced_tree.clone();
-            }
-            else if key > tree.as_ref().unwrap().borrow().key{
-                deleted_tree.as_ref().unwrap().borrow_mut().right  = self.do_delete(sub_node_right, key);
-                updated_tree = self.update_height(deleted_tree.clone());
-                balanced_tree = self.balance_tree(updated_tree);
-                return balanced_tree.clone();
-            }
-            else{
-                if tree.as_ref().unwrap().borrow().left.is_none(){
-                    let temp = tree.as_ref().unwrap().borrow().right.clone();
-                    return temp.clone();
-                }
-                else if tree.as_ref().unwrap().borrow().right.is_none(){
-                    let temp = tree.as_ref().unwrap().borrow().left.clone();
-                    return temp.clone();
-                }
-                let temp = self.find_min(tree.as_ref().unwrap().borrow().right.clone());
-                deleted_tree.as_ref().unwrap().borrow_mut().key  = temp.as_ref().unwrap().borrow().key;
-                if deleted_tree.is_none(){
-                    return deleted_tree.clone();
-                }
-                else{
-                    deleted_tree.as_ref().unwrap().borrow_mut().right = self.do_delete(sub_node_right,temp.unwrap().borrow().key);
-                    updated_tree = self.update_height(deleted_tree.clone());
-                    balanced_tree = self.balance_tree(updated_tree);
-                    return balanced_tree.clone();
-                }
-            }
-        }
-    }
-
-    fn find_min(&self, tree: Tree<T>) -> Tree<T> {
+    fn find_min(&self, tree: Tree<T, A>) -> Tree<T, A> {
         match tree {
             Some(sub_tree) => {
                 let mut left = Some(sub_tree.clone());
@@ -274,7 +235,7 @@ where T: Ord+Display+Debug+Clone+Copy{
         }
     }
 
-    fn find_max(&self, tree: Tree<T>) -> Tree<T> {
+    fn find_max(&self, tree: Tree<T, A>) -> Tree<T, A> {
         match tree {
             Some(sub_tree) => {
                 let mut right = Some(sub_tree.clone());
@@ -289,16 +250,19 @@ where T: Ord+Display+Debug+Clone+Copy{
         }
     }
 
-    fn balance_tree(&self, tree_node:Tree<T>) -> Tree<T>{
+    // rotations counts single rotations, so a left-right or right-left case adds two
+    fn balance_tree(&self, tree_node:Tree<T, A>, rotations: &mut u64) -> Tree<T, A>{
         let balance_factor = self.balance_factor(tree_node.clone());
-        let balanced_tree :Tree<T>;
+        let balanced_tree :Tree<T, A>;
         if balance_factor > 1{
             let balance_factor_left = self.balance_factor(tree_node.as_ref().unwrap().borrow().left.clone());
             if balance_factor_left >= 0{
+                *rotations += 1;
                 balanced_tree = self.rotate_right(tree_node.clone());
                 return balanced_tree.clone();
             }
             else{
+                *rotations += 2;
                 return self.rotate_lr(tree_node.clone());
             }
         } 
@@ -306,9 +270,11 @@ where T: Ord+Display+Debug+Clone+Copy{
         if balance_factor < -1{
             let balance_factor_right = self.balance_factor(tree_node.as_ref().unwrap().borrow().right.clone());
             if balance_factor_right <= 0{
+                *rotations += 1;
                 return self.rotate_left(tree_node.clone());
             }
             else{
+                *rotations += 2;
                 return self.rotate_rl(tree_node.clone());
             }
         }
@@ -319,13 +285,60 @@ where T: Ord+Display+Debug+Clone+Copy{
 impl <T> AvlTree<T>
 where T: Ord+Display+Debug+Clone+Copy{
     pub fn new() -> Self{
-        AvlTree { root: None ,count: 0}
+        Self::with_comparator(Natural)
+    }
+
+    // a tree that keeps duplicate keys, counting every occurrence
+    pub fn new_multiset() -> Self{
+        Self::multiset_with_comparator(Natural)
+    }
+}
+
+impl <T, C> AvlTree<T, C>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self{
+        Self::augmented(cmp)
+    }
+
+    pub fn multiset_with_comparator(cmp: C) -> Self{
+        Self::multiset_augmented(cmp)
+    }
+}
+
+impl <T, C, A> AvlTree<T, C, A>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>, A: Augment<T>{
+    // a tree whose nodes also keep A's summary of their subtree
+    pub fn augmented(cmp: C) -> Self{
+        AvlTree { root: None ,count: 0, cmp, multiset: false, rotations: 0}
+    }
+
+    pub fn multiset_augmented(cmp: C) -> Self{
+        AvlTree { multiset: true, ..Self::augmented(cmp)}
+    }
+
+    pub fn is_multiset(&self) -> bool {
+        self.multiset
     }
 
+    // total number of keys, counting every occurrence in a multiset
     pub fn count(&self) -> usize {
         return self.count;
     }
 
+    // single rotations done while rebalancing since the tree was made, a double rotation counts twice
+    pub fn rotations(&self) -> u64 {
+        self.rotations
+    }
+
+    // how many times key is in the tree
+    pub fn count_of(&self, key: T) -> usize {
+        match self.search_node(&self.root, &key) {
+            Some(node) => node.borrow().occurrences,
+            None => 0,
+        }
+    }
+
     pub fn is_empty(&self) -> bool{
         if self.root.is_none(){
             return true;
@@ -339,12 +352,43 @@ where T: Ord+Display+Debug+Clone+Copy{
         self.root.height(self.root.clone())
     }
 
+    // check key order, that every stored height is one more than the taller child's, that no node's
+    // subtrees differ in height by more than one, and that count matches the occurrences held
+    pub fn validate(&self) -> Result<(), String> {
+        let size = self.check(&self.root, None, None)?.1;
+        if size != self.count {
+            return Err(format!("count is {} but the tree holds {} keys", self.count, size));
+        }
+        Ok(())
+    }
+
+    // returns the height and the number of keys of the subtree, occurrences included
+    fn check(&self, tree: &Tree<T, A>, lo: Option<T>, hi: Option<T>) -> Result<(i8, usize), String> {
+        let node = match tree {
+            Some(node) => node.borrow(),
+            None => return Ok((0, 0)),
+        };
+        if lo.is_some_and(|lo| self.cmp.compare(&node.key, &lo) != Ordering::Greater)
+            || hi.is_some_and(|hi| self.cmp.compare(&node.key, &hi) != Ordering::Less) {
+            return Err(format!("{} is out of order", node.key));
+        }
+        let (left_height, left_size) = self.check(&node.left, lo, Some(node.key))?;
+        let (right_height, right_size) = self.check(&node.right, Some(node.key), hi)?;
+        if node.height != max(left_height, right_height) + 1 {
+            return Err(format!("{} has height {} but its subtree is {} high", node.key, node.height, max(left_height, right_height) + 1));
+        }
+        if (left_height - right_height).abs() > 1 {
+            return Err(format!("subtrees of {} are {} and {} high", node.key, left_height, right_height));
+        }
+        Ok((node.height, left_size + right_size + node.occurrences))
+    }
+
     pub fn leaves(&self) -> u32 {
         if self.root.is_none() {
             return 0;
         }
         let root = self.root.as_ref().unwrap().clone();
-        let mut stack: Vec<Tree<T>> = Vec::new();
+        let mut stack: Vec<Tree<T, A>> = Vec::new();
         stack.push(Some(root));
 
         let mut count = 0;
@@ -373,13 +417,31 @@ where T: Ord+Display+Debug+Clone+Copy{
         count
     }
 
+    // heap bytes held by the nodes, a multiset keeps every occurrence of a key in one node
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut stack: Vec<TreeNode<T, A>> = self.root.clone().into_iter().collect();
+        let mut nodes = 0;
+        while let Some(node) = stack.pop() {
+            nodes += 1;
+            stack.extend(node.borrow().left.clone());
+            stack.extend(node.borrow().right.clone());
+        }
+        MemoryUsage::of::<Node<T, A>, T>(nodes, self.count, NodeFields {
+            child: 2 * size_of::<Tree<T, A>>(),
+            // no parent pointers, rebalancing works on the way back up the search path instead
+            parent: 0,
+            balance: size_of::<i8>(),
+            extra: size_of::<usize>() + size_of::<A::Value>(),
+        })
+    }
+
     pub fn print_inorder(&self) {
         if self.root.is_none() {
             println!("None");
             return;
         }
         let mut root = self.root.clone();
-        let mut stack: Vec<Tree<T>> = Vec::new();
+        let mut stack: Vec<Tree<T, A>> = Vec::new();
         while !stack.is_empty() || !root.is_none() {
             if root.is_some() {
                 stack.push(root.clone());
@@ -400,9 +462,9 @@ where T: Ord+Display+Debug+Clone+Copy{
             return;
         }
         let mut root = self.root.clone();
-        let mut stack: Vec<Tree<T>> = Vec::new();
+        let mut stack: Vec<Tree<T, A>> = Vec::new();
         stack.push(root);
-        let mut cur: Tree<T>;
+        let mut cur: Tree<T, A>;
         while !stack.is_empty() {
             cur = stack.pop().unwrap();
             root = cur.clone();
@@ -431,11 +493,11 @@ where T: Ord+Display+Debug+Clone+Copy{
         println!("\n");
     }
 
-    fn inorder(&self) -> VecDeque<Tree<T>> {
+    fn inorder(&self) -> VecDeque<Tree<T, A>> {
         let root = self.root.as_ref().unwrap().clone();
-        let mut queue: VecDeque<Tree<T>> = VecDeque::new();
+        let mut queue: VecDeque<Tree<T, A>> = VecDeque::new();
         queue.push_back(Some(root));
-        let mut res: VecDeque<Tree<T>> = VecDeque::new();
+        let mut res: VecDeque<Tree<T, A>> = VecDeque::new();
         while !queue.is_empty() {
             let n = queue.len();
             for _ in 0..n {
@@ -452,57 +514,276 @@ where T: Ord+Display+Debug+Clone+Copy{
     }
 
     pub fn insert(&mut self,key:T){
-        let root_node = self.root.clone();
-        let res_tree = self.root.do_insert(root_node,key);
-        self.root = res_tree;
-        self.count += 1;
+        let multiset = self.multiset;
+        match self.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(key);
+            }
+            Entry::Occupied(entry) => {
+                // key already in tree, only a multiset keeps another occurrence
+                if multiset {
+                    entry.node.borrow_mut().occurrences += 1;
+                    entry.update_path();
+                    entry.tree.count += 1;
+                }
+            }
+        }
+    }
+
+    // look key up once, returning either its node or the path down to the spot it would be inserted at
+    pub fn entry(&mut self, key: T) -> Entry<'_, T, C, A> {
+        let mut path: Vec<(TreeNode<T, A>, Direction)> = Vec::new();
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(&key, &node.borrow().key);
+            match order {
+                Ordering::Equal => {
+                    return Entry::Occupied(OccupiedEntry { tree: self, node, path });
+                }
+                Ordering::Less => {
+                    cur = node.borrow().left.clone();
+                    path.push((node, Direction::Left));
+                }
+                Ordering::Greater => {
+                    cur = node.borrow().right.clone();
+                    path.push((node, Direction::Right));
+                }
+            }
+        }
+        Entry::Vacant(VacantEntry { tree: self, key, path })
+    }
+
+    // hang child off the end of path, then update heights and rebalance back up to the root
+    fn rebalance_path(&mut self, path: Vec<(TreeNode<T, A>, Direction)>, child: Tree<T, A>) {
+        let mut sub_tree = child;
+        for (node, side) in path.into_iter().rev() {
+            match side {
+                Direction::Left => node.borrow_mut().left = sub_tree,
+                Direction::Right => node.borrow_mut().right = sub_tree,
+            }
+            let updated_tree = self.root.update_height(Some(node));
+            sub_tree = self.root.balance_tree(updated_tree, &mut self.rotations);
+        }
+        self.root = sub_tree;
     }
 
     
     pub fn delete(&mut self,key:T){
-        let root_node = self.root.clone();
-        let res_tree = self.root.do_delete(root_node.clone(),key);
-        self.root = res_tree;
-        self.count -= 1;
+        self.remove_all(key);
     }
 
-    pub fn search(&self, key: T) -> Tree<T> {
-        let dummy = Node::<T>::new(key).unwrap().borrow().clone();
-        self.search_node(&self.root, &dummy)
+    // remove every occurrence of key, returns how many were removed
+    pub fn remove_all(&mut self,key:T) -> usize{
+        let (node, path) = match self.entry(key) {
+            Entry::Vacant(_) => return 0,
+            Entry::Occupied(OccupiedEntry { node, path, .. }) => (node, path),
+        };
+        self.remove_node(path, node)
     }
-    
-    pub fn min(&self) -> Tree<T> {
-        self.root.find_min(self.root.clone())
+
+    // unlink node from the end of path and rebalance back up, returns how many occurrences it held.
+    // a node with two children takes over its successor's key and the successor's node goes instead
+    fn remove_node(&mut self, mut path: Vec<(TreeNode<T, A>, Direction)>, node: TreeNode<T, A>) -> usize {
+        let occurrences = node.borrow().occurrences;
+        let (left, right) = {
+            let node = node.borrow();
+            (node.left.clone(), node.right.clone())
+        };
+        match (left, right) {
+            (None, child) | (child, None) => self.rebalance_path(path, child),
+            (Some(_), Some(right)) => {
+                // swap in the successor, then unlink it from the bottom of the right subtree
+                path.push((node.clone(), Direction::Right));
+                let mut successor = right;
+                loop {
+                    let left = successor.borrow().left.clone();
+                    match left {
+                        Some(left) => {
+                            path.push((successor, Direction::Left));
+                            successor = left;
+                        }
+                        None => break,
+                    }
+                }
+                node.borrow_mut().key = successor.borrow().key;
+                node.borrow_mut().occurrences = successor.borrow().occurrences;
+                let child = successor.borrow().right.clone();
+                self.rebalance_path(path, child);
+            }
+        }
+        self.count -= occurrences;
+        occurrences
+    }
+
+    // remove a single occurrence of key, the node only goes once its last occurrence does
+    pub fn remove_one(&mut self,key:T) -> bool{
+        match self.entry(key) {
+            Entry::Vacant(_) => false,
+            Entry::Occupied(entry) => {
+                if entry.node.borrow().occurrences > 1 {
+                    entry.node.borrow_mut().occurrences -= 1;
+                    entry.update_path();
+                    entry.tree.count -= 1;
+                } else {
+                    entry.tree.remove_all(key);
+                }
+                true
+            }
+        }
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.search_node(&self.root, key).is_some()
     }
 
-    pub fn max(&self) -> Tree<T> {
-        self.root.find_max(self.root.clone())
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.search_node(&self.root, key).map(|node| node.borrow().key)
     }
     
-    fn search_node(&self, tree_node: &Tree<T>, node: &Node<T>) -> Tree<T> {
-        match tree_node {
-            Some(sub_tree) => {
-                let sub_tree_clone = sub_tree.borrow().clone();
-                if sub_tree_clone.key == node.key {
-                    Some(sub_tree.clone())
+    pub fn first(&self) -> Option<T> {
+        self.root.find_min(self.root.clone()).map(|node| node.borrow().key)
+    }
+
+    pub fn last(&self) -> Option<T> {
+        self.root.find_max(self.root.clone()).map(|node| node.borrow().key)
+    }
+
+    // keys k with lo <= k < hi, in the tree's order
+    pub fn range(&self, lo: T, hi: T) -> Vec<T> {
+        let mut keys: Vec<T> = Vec::new();
+        let mut stack: Vec<TreeNode<T, A>> = Vec::new();
+        let mut cur = self.root.clone();
+        loop {
+            // walk left, skipping every subtree that is entirely below lo
+            while let Some(node) = cur.take() {
+                if self.cmp.compare(&node.borrow().key, &lo) == Ordering::Less {
+                    cur = node.borrow().right.clone();
                 } else {
-                    if sub_tree_clone.key > node.key {
-                        self.search_node(&sub_tree_clone.left, node)
-                    } else {
-                        self.search_node(&sub_tree_clone.right, node)
-                    }
+                    cur = node.borrow().left.clone();
+                    stack.push(node);
                 }
-            },
-            None => {None}
+            }
+            let node = match stack.pop() {
+                Some(node) => node,
+                None => break,
+            };
+            let key = node.borrow().key;
+            if self.cmp.compare(&key, &hi) != Ordering::Less {
+                break;
+            }
+            for _ in 0..node.borrow().occurrences {
+                keys.push(key);
+            }
+            cur = node.borrow().right.clone();
+        }
+        keys
+    }
+
+    // iterate over the keys in order, a multiset repeats each key once per occurrence
+    pub fn iter(&self) -> Iter<T, A> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            repeat: None,
+        };
+        iter.push_left(self.root.clone());
+        iter
+    }
+    
+    // cursor at the first key above bound, past the end if there is none
+    pub fn lower_bound(&self, bound: Bound<&T>) -> Cursor<'_, T, C, A> {
+        let path = self.lower_bound_path(bound);
+        Cursor { tree: self, path }
+    }
+
+    // cursor at the last key below bound, past the end if there is none
+    pub fn upper_bound(&self, bound: Bound<&T>) -> Cursor<'_, T, C, A> {
+        let path = self.upper_bound_path(bound);
+        Cursor { tree: self, path }
+    }
+
+    pub fn lower_bound_mut(&mut self, bound: Bound<&T>) -> CursorMut<'_, T, C, A> {
+        let path = self.lower_bound_path(bound);
+        CursorMut { tree: self, path }
+    }
+
+    pub fn upper_bound_mut(&mut self, bound: Bound<&T>) -> CursorMut<'_, T, C, A> {
+        let path = self.upper_bound_path(bound);
+        CursorMut { tree: self, path }
+    }
+
+    // the nodes from the root down to the first key above bound, the search path up to the
+    // last node that qualified
+    fn lower_bound_path(&self, bound: Bound<&T>) -> Vec<TreeNode<T, A>> {
+        let mut path: Vec<TreeNode<T, A>> = Vec::new();
+        let mut keep = 0;
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let above = match bound {
+                Bound::Included(key) => self.cmp.compare(&node.borrow().key, key) != Ordering::Less,
+                Bound::Excluded(key) => self.cmp.compare(&node.borrow().key, key) == Ordering::Greater,
+                Bound::Unbounded => true,
+            };
+            cur = if above { node.borrow().left.clone() } else { node.borrow().right.clone() };
+            path.push(node);
+            if above {
+                keep = path.len();
+            }
         }
+        path.truncate(keep);
+        path
     }
+
+    fn upper_bound_path(&self, bound: Bound<&T>) -> Vec<TreeNode<T, A>> {
+        let mut path: Vec<TreeNode<T, A>> = Vec::new();
+        let mut keep = 0;
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let below = match bound {
+                Bound::Included(key) => self.cmp.compare(&node.borrow().key, key) != Ordering::Greater,
+                Bound::Excluded(key) => self.cmp.compare(&node.borrow().key, key) == Ordering::Less,
+                Bound::Unbounded => true,
+            };
+            cur = if below { node.borrow().right.clone() } else { node.borrow().left.clone() };
+            path.push(node);
+            if below {
+                keep = path.len();
+            }
+        }
+        path.truncate(keep);
+        path
+    }
+
+    fn search_node(&self, tree_node: &Tree<T, A>, key: &T) -> Tree<T, A> {
+        let mut cur = tree_node.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+    // the tree's layout with each node's height, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T, A>| {
+            let node = node.borrow();
+            let mut attrs = vec![("height", Attr::Number(node.height as u64))];
+            if node.occurrences > 1 {
+                attrs.push(("occurrences", Attr::Number(node.occurrences as u64)));
+            }
+            (vec![node.key], attrs, vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
     pub fn print_tree(&self) {
         if self.root.is_none() {
             println!("None");
             return;
         };
 
-        fn pretty_print<T: Ord+Display+Debug+Clone>(node: TreeNode<T>, buffer: &mut String, prefix: &mut String, child_prefix: &String) {
+        fn pretty_print<T: Display+Debug+Clone, A: Augment<T>>(node: TreeNode<T, A>, buffer: &mut String, prefix: &mut String, child_prefix: &String) {
             let node_height = node.borrow().clone().height;
             prefix.push_str(&("(".to_string() + &node_height.to_string() + &")".to_string()));
             buffer.push_str(&prefix);
@@ -534,8 +815,466 @@ where T: Ord+Display+Debug+Clone+Copy{
     }   
 }
 
-impl<T> fmt::Display for AvlTree<T>
-where T: Debug+Ord+Display+Copy
+impl<T, C, M> AvlTree<T, C, Aggregate<M>>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>, M: Monoid<T>
+{
+    // fold of every key in the tree, read straight off the root
+    pub fn aggregate(&self) -> M::Value {
+        match self.root {
+            Some(ref root) => root.borrow().aug,
+            None => M::identity(),
+        }
+    }
+
+    // fold of the keys k with lo <= k < hi, using the cached folds of every subtree fully inside the range
+    pub fn range_aggregate(&self, lo: T, hi: T) -> M::Value {
+        self.fold_range(&self.root, Some(&lo), Some(&hi))
+    }
+
+    // a None bound leaves that side open, once both are open the whole subtree counts
+    fn fold_range(&self, tree: &Tree<T, Aggregate<M>>, lo: Option<&T>, hi: Option<&T>) -> M::Value {
+        let node = match tree {
+            Some(node) => node.borrow(),
+            None => return M::identity(),
+        };
+        if lo.is_none() && hi.is_none() {
+            return node.aug;
+        }
+        if let Some(lo) = lo {
+            if self.cmp.compare(&node.key, lo) == Ordering::Less {
+                return self.fold_range(&node.right, Some(lo), hi);
+            }
+        }
+        if let Some(hi) = hi {
+            if self.cmp.compare(&node.key, hi) != Ordering::Less {
+                return self.fold_range(&node.left, lo, Some(hi));
+            }
+        }
+        // node is inside the range, so everything right of it is above lo and everything left of it below hi
+        let left = self.fold_range(&node.left, lo, None);
+        let right = self.fold_range(&node.right, None, hi);
+        M::combine(M::combine(left, M::repeat(&node.key, node.occurrences)), right)
+    }
+}
+
+pub enum Entry<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    Occupied(OccupiedEntry<'a, T, C, A>),
+    Vacant(VacantEntry<'a, T, C, A>),
+}
+
+pub struct OccupiedEntry<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a mut AvlTree<T, C, A>,
+    node: TreeNode<T, A>,
+    // the nodes above node, their summaries go stale when node's occurrences or key change
+    path: Vec<(TreeNode<T, A>, Direction)>,
+}
+
+pub struct VacantEntry<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a mut AvlTree<T, C, A>,
+    key: T,
+    // every node passed on the way down and the side taken, needed to rebalance after inserting
+    path: Vec<(TreeNode<T, A>, Direction)>,
+}
+
+impl<'a, T, C, A> Entry<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn key(&self) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    // update the stored key in place, f must not change how it compares
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(entry) => {
+                f(&mut entry.node.borrow_mut().key);
+                entry.update_path();
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    // returns a copy of the stored key, inserting default first if the entry is vacant
+    pub fn or_insert(self, default: T) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    // the tree only holds keys, so the default for a vacant entry is the key it was looked up with
+    pub fn or_default(self) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => {
+                let key = entry.key();
+                entry.insert(key)
+            }
+        }
+    }
+}
+
+impl<'a, T, C, A> OccupiedEntry<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn get(&self) -> T {
+        self.node.borrow().key
+    }
+
+    // rebuild the summaries from node up to the root, heights stay the same
+    fn update_path(&self) {
+        self.tree.root.update_height(Some(self.node.clone()));
+        for (node, _) in self.path.iter().rev() {
+            self.tree.root.update_height(Some(node.clone()));
+        }
+    }
+}
+
+impl<'a, T, C, A> VacantEntry<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn key(&self) -> T {
+        self.key
+    }
+
+    // insert key into the spot found by entry(), key must compare equal to the one looked up
+    pub fn insert(self, key: T) -> T {
+        debug_assert!(self.tree.cmp.compare(&key, &self.key) == Ordering::Equal);
+        self.tree.rebalance_path(self.path, Node::new(key));
+        self.tree.count += 1;
+        key
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy, A: Augment<T> = ()> {
+    stack: Vec<TreeNode<T, A>>,
+    // key being repeated and how many more times to yield it
+    repeat: Option<(T, usize)>,
+}
+
+impl<T, A> Iter<T, A>
+where T: Display+Debug+Copy, A: Augment<T>
+{
+    fn push_left(&mut self, tree: Tree<T, A>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T, A> Iterator for Iter<T, A>
+where T: Display+Debug+Copy, A: Augment<T>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if let Some((key, remaining)) = self.repeat {
+            if remaining > 0 {
+                self.repeat = Some((key, remaining - 1));
+                return Some(key);
+            }
+        }
+        let node = self.stack.pop()?;
+        let (key, occurrences, right) = {
+            let node = node.borrow();
+            (node.key, node.occurrences, node.right.clone())
+        };
+        self.push_left(right);
+        self.repeat = Some((key, occurrences - 1));
+        Some(key)
+    }
+}
+
+// move a root-to-node path on to the next node in order, an empty path is past the ends and steps
+// round to the first node
+fn step_next<T: Display+Debug, A: Augment<T>>(root: &Tree<T, A>, path: &mut Vec<TreeNode<T, A>>) {
+    let right = match path.last() {
+        Some(node) => node.borrow().right.clone(),
+        None => root.clone(),
+    };
+    if path.is_empty() || right.is_some() {
+        let mut cur = right;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            path.push(node);
+        }
+        return;
+    }
+    // climb until coming up out of a left subtree, that parent is next
+    while let Some(child) = path.pop() {
+        if let Some(parent) = path.last() {
+            if parent.borrow().left.as_ref().is_some_and(|left| Rc::ptr_eq(left, &child)) {
+                return;
+            }
+        }
+    }
+}
+
+fn step_prev<T: Display+Debug, A: Augment<T>>(root: &Tree<T, A>, path: &mut Vec<TreeNode<T, A>>) {
+    let left = match path.last() {
+        Some(node) => node.borrow().left.clone(),
+        None => root.clone(),
+    };
+    if path.is_empty() || left.is_some() {
+        let mut cur = left;
+        while let Some(node) = cur {
+            cur = node.borrow().right.clone();
+            path.push(node);
+        }
+        return;
+    }
+    while let Some(child) = path.pop() {
+        if let Some(parent) = path.last() {
+            if parent.borrow().right.as_ref().is_some_and(|right| Rc::ptr_eq(right, &child)) {
+                return;
+            }
+        }
+    }
+}
+
+fn is_left<T: Display+Debug, A: Augment<T>>(parent: &TreeNode<T, A>, child: &TreeNode<T, A>) -> bool {
+    parent.borrow().left.as_ref().is_some_and(|left| Rc::ptr_eq(left, child))
+}
+
+fn is_child<T: Display+Debug, A: Augment<T>>(parent: &TreeNode<T, A>, child: &TreeNode<T, A>) -> bool {
+    is_left(parent, child) || parent.borrow().right.as_ref().is_some_and(|right| Rc::ptr_eq(right, child))
+}
+
+// the node step_next would move a path on to, found without changing or copying the path
+fn next_node<T: Display+Debug+Copy, A: Augment<T>>(root: &Tree<T, A>, path: &[TreeNode<T, A>]) -> Tree<T, A> {
+    match path.last() {
+        None => root.find_min(root.clone()),
+        Some(node) if node.borrow().right.is_some() => root.find_min(node.borrow().right.clone()),
+        // the nearest ancestor the path leaves through its left child
+        Some(_) => (1..path.len()).rev().find(|&i| is_left(&path[i - 1], &path[i])).map(|i| path[i - 1].clone()),
+    }
+}
+
+fn prev_node<T: Display+Debug+Copy, A: Augment<T>>(root: &Tree<T, A>, path: &[TreeNode<T, A>]) -> Tree<T, A> {
+    match path.last() {
+        None => root.find_max(root.clone()),
+        Some(node) if node.borrow().left.is_some() => root.find_max(node.borrow().left.clone()),
+        Some(_) => (1..path.len()).rev().find(|&i| !is_left(&path[i - 1], &path[i])).map(|i| path[i - 1].clone()),
+    }
+}
+
+// a position in the tree that steps to its neighbours without starting again from the root. there
+// are no parent pointers, so it keeps the path down from the root, empty when past the ends
+pub struct Cursor<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a AvlTree<T, C, A>,
+    path: Vec<TreeNode<T, A>>,
+}
+
+impl<'a, T, C, A> Cursor<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    // a multiset key is a single position however many occurrences it has
+    pub fn key(&self) -> Option<T> {
+        self.path.last().map(|node| node.borrow().key)
+    }
+
+    pub fn move_next(&mut self) {
+        step_next(&self.tree.root, &mut self.path);
+    }
+
+    pub fn move_prev(&mut self) {
+        step_prev(&self.tree.root, &mut self.path);
+    }
+
+    pub fn peek_next(&self) -> Option<T> {
+        next_node(&self.tree.root, &self.path).map(|node| node.borrow().key)
+    }
+
+    pub fn peek_prev(&self) -> Option<T> {
+        prev_node(&self.tree.root, &self.path).map(|node| node.borrow().key)
+    }
+}
+
+pub struct CursorMut<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a mut AvlTree<T, C, A>,
+    path: Vec<TreeNode<T, A>>,
+}
+
+impl<'a, T, C, A> CursorMut<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn key(&self) -> Option<T> {
+        self.path.last().map(|node| node.borrow().key)
+    }
+
+    pub fn move_next(&mut self) {
+        step_next(&self.tree.root, &mut self.path);
+    }
+
+    pub fn move_prev(&mut self) {
+        step_prev(&self.tree.root, &mut self.path);
+    }
+
+    pub fn peek_next(&self) -> Option<T> {
+        next_node(&self.tree.root, &self.path).map(|node| node.borrow().key)
+    }
+
+    pub fn peek_prev(&self) -> Option<T> {
+        prev_node(&self.tree.root, &self.path).map(|node| node.borrow().key)
+    }
+
+    // a read-only cursor at the same position, borrowing this one
+    pub fn as_cursor(&self) -> Cursor<'_, T, C, A> {
+        Cursor { tree: self.tree, path: self.path.clone() }
+    }
+
+    // insert key just after the cursor, which stays where it is. past the end this inserts in front of
+    // the first key. panics unless key falls strictly between the current key and the next one
+    pub fn insert_after(&mut self, key: T) {
+        if let Some(current) = self.key() {
+            assert!(self.tree.cmp.compare(&key, &current) == Ordering::Greater, "key does not come after the cursor");
+        }
+        if let Some(next) = self.peek_next() {
+            assert!(self.tree.cmp.compare(&key, &next) == Ordering::Less, "key does not come before the next key");
+        }
+        // the gap is the current node's right slot if it is free, otherwise the left slot of the next
+        // node down its right subtree. past the end it is left of the first node
+        let mut path = self.sides();
+        let mut cur = match self.path.last() {
+            Some(node) => {
+                path.push((node.clone(), Direction::Right));
+                node.borrow().right.clone()
+            }
+            None => self.tree.root.clone(),
+        };
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            path.push((node, Direction::Left));
+        }
+        self.insert_at(path, key);
+    }
+
+    // insert key just before the cursor, past the end this inserts after the last key
+    pub fn insert_before(&mut self, key: T) {
+        if let Some(current) = self.key() {
+            assert!(self.tree.cmp.compare(&key, &current) == Ordering::Less, "key does not come before the cursor");
+        }
+        if let Some(prev) = self.peek_prev() {
+            assert!(self.tree.cmp.compare(&key, &prev) == Ordering::Greater, "key does not come after the previous key");
+        }
+        let mut path = self.sides();
+        let mut cur = match self.path.last() {
+            Some(node) => {
+                path.push((node.clone(), Direction::Left));
+                node.borrow().left.clone()
+            }
+            None => self.tree.root.clone(),
+        };
+        while let Some(node) = cur {
+            cur = node.borrow().right.clone();
+            path.push((node, Direction::Right));
+        }
+        self.insert_at(path, key);
+    }
+
+    // hang key at the end of path, rebalance back up it and find the cursor's node again
+    fn insert_at(&mut self, path: Vec<(TreeNode<T, A>, Direction)>, key: T) {
+        self.tree.rebalance_path(path, Node::new(key));
+        self.tree.count += 1;
+        if let Some(current) = self.key() {
+            self.repair(current);
+        }
+    }
+
+    // remove the current key, with all its occurrences in a multiset, and move on to the next one
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.path.last()?.clone();
+        let key = node.borrow().key;
+        let next = next_node(&self.tree.root, &self.path);
+        let path = self.sides();
+        self.tree.remove_node(path, node);
+        self.path.pop();
+        match next {
+            Some(next) => {
+                // the next node is either an ancestor, or below the removed node, or its key moved into
+                // the removed node's place. an ancestor ends the path, the others are found below it
+                if let Some(i) = self.path.iter().position(|node| Rc::ptr_eq(node, &next)) {
+                    self.path.truncate(i + 1);
+                }
+                let next = next.borrow().key;
+                self.repair(next);
+            }
+            None => self.path.clear(),
+        }
+        Some(key)
+    }
+
+    // the path down to the current node with the side taken below each node, as rebalance_path
+    // takes it
+    fn sides(&self) -> Vec<(TreeNode<T, A>, Direction)> {
+        self.path.iter().zip(self.path.iter().skip(1)).map(|(parent, child)| {
+            let side = if is_left(parent, child) { Direction::Left } else { Direction::Right };
+            (parent.clone(), side)
+        }).collect()
+    }
+
+    // a rotation replaces the path from the node it turned down, the links above it still hold. keep
+    // those and search down for key from the last of them
+    fn repair(&mut self, key: T) {
+        let mut keep = match (&self.tree.root, self.path.first()) {
+            (Some(root), Some(first)) if Rc::ptr_eq(root, first) => 1,
+            _ => 0,
+        };
+        while keep > 0 && keep < self.path.len() && is_child(&self.path[keep - 1], &self.path[keep]) {
+            keep += 1;
+        }
+        self.path.truncate(keep);
+        let mut cur = match self.path.last() {
+            Some(node) => {
+                let node = node.borrow();
+                match self.tree.cmp.compare(&key, &node.key) {
+                    Ordering::Equal => None,
+                    Ordering::Less => node.left.clone(),
+                    Ordering::Greater => node.right.clone(),
+                }
+            }
+            None => self.tree.root.clone(),
+        };
+        while let Some(node) = cur {
+            cur = match self.tree.cmp.compare(&key, &node.borrow().key) {
+                Ordering::Equal => None,
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+            self.path.push(node);
+        }
+    }
+}
+
+// the keys of a plain BST in a balanced tree, ordered by the same comparator
+impl<T, C> From<BST<T, C>> for AvlTree<T, C>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>
+{
+    fn from(bst: BST<T, C>) -> Self {
+        let (keys, cmp) = bst.into_parts();
+        let mut tree = Self::with_comparator(cmp);
+        for key in keys {
+            tree.insert(key);
+        }
+        tree
+    }
+}
+
+impl<T, C, A> fmt::Display for AvlTree<T, C, A>
+where T: Debug+Display+Copy, A: Augment<T>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("AvlTree")
@@ -657,10 +1396,10 @@ pub fn search_1() {
     x.insert(12);
     x.insert(3);
 
-    let y = x.search(8);
-    assert_eq!(8,y.as_ref().unwrap().borrow().clone().key);
-    let z = x.search(81);
-    assert!(z.is_none());
+    assert_eq!(x.get(&8), Some(8));
+    assert!(x.contains(&8));
+    assert_eq!(x.get(&81), None);
+    assert!(!x.contains(&81));
 }
 
 #[test]
@@ -1125,8 +1864,8 @@ pub fn test_min_max_1() {
     a.insert(9);
     a.insert(12);
     a.insert(1);
-    assert_eq!(a.min().as_ref().unwrap().borrow().key, 1);
-    assert_eq!(a.max().as_ref().unwrap().borrow().key, 455);
+    assert_eq!(a.first().unwrap(), 1);
+    assert_eq!(a.last().unwrap(), 455);
 }
 
 #[test]
@@ -1138,6 +1877,379 @@ pub fn test_min_max_2() {
     a.insert("g");
     a.insert("u");
     a.insert("c");
-    assert_eq!(a.min().as_ref().unwrap().borrow().key, "a");
-    assert_eq!(a.max().as_ref().unwrap().borrow().key, "u");
-}
\ No newline at end of file
+    assert_eq!(a.first().unwrap(), "a");
+    assert_eq!(a.last().unwrap(), "u");
+}
+#[cfg(test)]
+use crate::compare::{ByKey, Reverse};
+#[cfg(test)]
+use crate::augment::{Gcd, Max, Sum};
+
+#[test]
+pub fn test_reverse_comparator() {
+    let mut x = AvlTree::with_comparator(Reverse);
+    x.insert(5);
+    x.insert(1);
+    x.insert(9);
+    x.insert(3);
+    x.insert(7);
+    assert_eq!(x.first().unwrap(), 9);
+    assert_eq!(x.last().unwrap(), 1);
+    assert_eq!(x.range(8, 2), vec![7, 5, 3]);
+    x.delete(5);
+    assert_eq!(x.range(10, 0), vec![9, 7, 3, 1]);
+}
+
+#[test]
+pub fn test_case_insensitive_comparator() {
+    let mut x = AvlTree::with_comparator(|a: &&str, b: &&str| a.to_lowercase().cmp(&b.to_lowercase()));
+    x.insert("b");
+    x.insert("A");
+    x.insert("c");
+    assert!(x.contains(&"C"));
+    assert_eq!(x.first().unwrap(), "A");
+    assert_eq!(x.last().unwrap(), "c");
+}
+
+#[test]
+pub fn test_by_key_comparator() {
+    // order words by length only
+    let mut x = AvlTree::with_comparator(ByKey(|word: &&str| word.len()));
+    x.insert("ccc");
+    x.insert("a");
+    x.insert("bb");
+    x.insert("dddd");
+    assert_eq!(x.first().unwrap(), "a");
+    assert_eq!(x.last().unwrap(), "dddd");
+    assert_eq!(x.range("xx", "zzzz"), vec!["bb", "ccc"]);
+}
+
+#[test]
+pub fn test_multiset_insert() {
+    let mut x = AvlTree::new_multiset();
+    x.insert(5);
+    x.insert(3);
+    x.insert(5);
+    x.insert(8);
+    x.insert(5);
+    assert_eq!(x.count(), 5);
+    assert_eq!(x.count_of(5), 3);
+    assert_eq!(x.count_of(4), 0);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![3, 5, 5, 5, 8]);
+    assert_eq!(x.range(4, 9), vec![5, 5, 5, 8]);
+}
+
+#[test]
+pub fn test_multiset_remove() {
+    let mut x = AvlTree::new_multiset();
+    for key in [10, 5, 20, 5, 5, 20, 1, 7, 7] {
+        x.insert(key);
+    }
+    assert!(x.remove_one(5));
+    assert_eq!(x.count_of(5), 2);
+    assert_eq!(x.count(), 8);
+    // 5 has two children, so its successor's occurrences move into its node
+    assert_eq!(x.remove_all(5), 2);
+    assert_eq!(x.count_of(7), 2);
+    assert_eq!(x.count(), 6);
+    assert!(!x.remove_one(6));
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![1, 7, 7, 10, 20, 20]);
+}
+
+#[test]
+pub fn test_set_count_does_not_drift() {
+    let mut x = AvlTree::new();
+    x.insert(2);
+    x.insert(2);
+    assert_eq!(x.count(), 1);
+    x.delete(3);
+    assert_eq!(x.count(), 1);
+    x.delete(2);
+    assert_eq!(x.count(), 0);
+    assert!(x.is_empty());
+}
+
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct WordCount {
+    word: &'static str,
+    count: u32,
+}
+
+#[cfg(test)]
+impl fmt::Display for WordCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.word, self.count)
+    }
+}
+
+#[test]
+pub fn test_entry_counting() {
+    // words are ordered by the word alone, so the count can be updated in place
+    let mut x = AvlTree::with_comparator(ByKey(|w: &WordCount| w.word));
+    for word in ["b", "a", "b", "c", "b", "a"] {
+        x.entry(WordCount { word, count: 1 }).and_modify(|w| w.count += 1).or_default();
+    }
+    assert_eq!(x.count(), 3);
+    let counts: Vec<(&str, u32)> = x.iter().map(|w| (w.word, w.count)).collect();
+    assert_eq!(counts, vec![("a", 2), ("b", 3), ("c", 1)]);
+    assert_eq!(x.height(), 2);
+}
+
+#[test]
+pub fn test_entry_or_insert() {
+    let mut x = AvlTree::new();
+    assert_eq!(x.entry(5).or_insert(5), 5);
+    assert_eq!(x.entry(5).or_insert_with(|| panic!("occupied entry must not build a default")), 5);
+    match x.entry(7) {
+        Entry::Vacant(entry) => assert_eq!(entry.key(), 7),
+        Entry::Occupied(_) => panic!("7 was never inserted"),
+    }
+    assert_eq!(x.count(), 1);
+    assert_eq!(x.entry(3).or_insert_with(|| 3), 3);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![3, 5]);
+}
+
+#[test]
+pub fn test_range_sum_random() {
+    // sums over random ranges of a multiset, checked against a plain list after every operation
+    let mut x: AvlTree<u64, Natural, Aggregate<Sum>> = AvlTree::multiset_augmented(Natural);
+    let mut keys: Vec<u64> = Vec::new();
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for i in 0..2000 {
+        let key = next() % 300;
+        if i % 3 == 0 && !keys.is_empty() {
+            let victim = keys.swap_remove(next() as usize % keys.len());
+            assert!(x.remove_one(victim));
+        } else if i % 11 == 0 {
+            let before = keys.len();
+            keys.retain(|k| *k != key);
+            assert_eq!(x.remove_all(key), before - keys.len());
+        } else {
+            x.insert(key);
+            keys.push(key);
+        }
+        let lo = next() % 300;
+        let hi = lo + next() % 100;
+        let expected: u64 = keys.iter().filter(|k| lo <= **k && **k < hi).sum();
+        assert_eq!(x.range_aggregate(lo, hi), expected);
+        assert_eq!(x.aggregate(), keys.iter().sum::<u64>());
+    }
+}
+
+#[test]
+pub fn test_range_max_gcd() {
+    let mut maxes: AvlTree<u32, Natural, Aggregate<Max>> = AvlTree::augmented(Natural);
+    let mut gcds: AvlTree<u32, Natural, Aggregate<Gcd>> = AvlTree::augmented(Natural);
+    for key in [12, 18, 30, 7, 42, 54] {
+        maxes.insert(key);
+        gcds.insert(key);
+    }
+    assert_eq!(maxes.range_aggregate(10, 50), Some(42));
+    assert_eq!(maxes.range_aggregate(0, 7), None);
+    assert_eq!(maxes.aggregate(), Some(54));
+    assert_eq!(gcds.range_aggregate(12, 55), 6);
+    assert_eq!(gcds.aggregate(), 1);
+    gcds.delete(7);
+    assert_eq!(gcds.aggregate(), 6);
+    maxes.delete(54);
+    assert_eq!(maxes.aggregate(), Some(42));
+}
+
+#[test]
+pub fn test_cursor_walk() {
+    let mut x = AvlTree::new();
+    for key in (0..100).map(|i| i * 2) {
+        x.insert(key);
+    }
+    let mut cursor = x.lower_bound(Bound::Included(&51));
+    assert_eq!(cursor.key(), Some(52));
+    assert_eq!(cursor.peek_prev(), Some(50));
+    let mut seen = Vec::new();
+    while let Some(key) = cursor.key() {
+        seen.push(key);
+        cursor.move_next();
+    }
+    assert_eq!(seen, (26..100).map(|i| i * 2).collect::<Vec<u32>>());
+    cursor.move_next();
+    assert_eq!(cursor.key(), Some(0));
+    cursor.move_prev();
+    assert_eq!(cursor.key(), None);
+    cursor.move_prev();
+    assert_eq!(cursor.key(), Some(198));
+    // backwards all the way down
+    let mut seen = Vec::new();
+    while let Some(key) = cursor.key() {
+        seen.push(key);
+        cursor.move_prev();
+    }
+    assert_eq!(seen, (0..100).rev().map(|i| i * 2).collect::<Vec<u32>>());
+
+    assert_eq!(x.lower_bound(Bound::Excluded(&52)).key(), Some(54));
+    assert_eq!(x.upper_bound(Bound::Included(&52)).key(), Some(52));
+    assert_eq!(x.upper_bound(Bound::Excluded(&52)).key(), Some(50));
+    assert_eq!(x.lower_bound(Bound::Unbounded).key(), Some(0));
+    assert_eq!(x.upper_bound(Bound::Excluded(&0)).key(), None);
+}
+
+#[test]
+pub fn test_cursor_mut_edits() {
+    let mut x: AvlTree<u32> = AvlTree::new();
+    let mut cursor = x.lower_bound_mut(Bound::Unbounded);
+    cursor.insert_before(50);
+    cursor.move_prev();
+    assert_eq!(cursor.key(), Some(50));
+    for i in 1..50 {
+        cursor.insert_after(100 - i);
+        cursor.insert_before(i);
+    }
+    assert_eq!(cursor.key(), Some(50));
+    assert_eq!(cursor.peek_next(), Some(51));
+    assert_eq!(cursor.peek_prev(), Some(49));
+    let mut cursor = x.lower_bound_mut(Bound::Unbounded);
+    while let Some(key) = cursor.key() {
+        if key % 2 == 1 {
+            assert_eq!(cursor.remove_current(), Some(key));
+        } else {
+            cursor.move_next();
+        }
+    }
+    // 49 keys fit in an AVL tree of height at most 7
+    assert!(x.height() <= 7);
+    assert_eq!(x.count(), 49);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), (1..50).map(|i| i * 2).collect::<Vec<u32>>());
+}
+
+#[cfg(test)]
+struct Counting(Rc<std::cell::Cell<usize>>);
+
+#[cfg(test)]
+impl Comparator<u32> for Counting {
+    fn compare(&self, a: &u32, b: &u32) -> Ordering {
+        self.0.set(self.0.get() + 1);
+        a.cmp(b)
+    }
+}
+
+#[test]
+pub fn test_cursor_edits_stay_local() {
+    let compares = Rc::new(std::cell::Cell::new(0));
+    let mut x = AvlTree::with_comparator(Counting(compares.clone()));
+    for key in (0..4096).map(|i| i * 4) {
+        x.insert(key);
+    }
+    let mut cursor = x.lower_bound_mut(Bound::Included(&8000));
+    compares.set(0);
+    for _ in 0..200 {
+        let key = cursor.key().unwrap();
+        cursor.insert_after(key + 1);
+        cursor.insert_before(key - 1);
+        cursor.move_next();
+        cursor.move_next();
+    }
+    // a search from the root alone would take a dozen comparisons per edit
+    assert!(compares.get() < 400 * 8, "{} comparisons", compares.get());
+    compares.set(0);
+    for _ in 0..200 {
+        cursor.remove_current();
+    }
+    assert!(compares.get() < 200 * 8, "{} comparisons", compares.get());
+    assert_eq!(x.validate(), Ok(()));
+    assert_eq!(x.count(), 4096 + 200);
+}
+
+#[test]
+pub fn test_cursor_random_edits() {
+    let mut rng = crate::rng::Rng::new(40);
+    let mut x: AvlTree<u32> = AvlTree::new();
+    let mut keys: std::collections::BTreeSet<u32> = std::collections::BTreeSet::new();
+    for key in (0..300).map(|i| i * 1000) {
+        x.insert(key);
+        keys.insert(key);
+    }
+    let mut cursor = x.lower_bound_mut(Bound::Unbounded);
+    for _ in 0..3000 {
+        let current = cursor.key();
+        match rng.below(5) {
+            0 => cursor.move_next(),
+            1 => cursor.move_prev(),
+            2 => if cursor.remove_current().is_some() {
+                keys.remove(&current.unwrap());
+            },
+            side => {
+                // the midpoint of the gap on one side of the cursor, when there is room
+                let (lo, hi) = if side == 3 { (current, cursor.peek_next()) } else { (cursor.peek_prev(), current) };
+                let (lo, hi) = (lo.map_or(0, |lo| lo + 1), hi.unwrap_or(400_000));
+                if lo < hi {
+                    let key = lo + (hi - lo) / 2;
+                    if side == 3 { cursor.insert_after(key) } else { cursor.insert_before(key) }
+                    keys.insert(key);
+                }
+            }
+        }
+        // the cursor stays on its key, or the next one after a removal
+        let expected = match current {
+            Some(current) if !keys.contains(&current) => keys.range(current..).next().copied(),
+            _ => cursor.key(),
+        };
+        assert_eq!(cursor.key(), expected);
+    }
+    assert_eq!(x.validate(), Ok(()));
+    assert_eq!(x.iter().collect::<Vec<u32>>(), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_rotation_count() {
+    let mut tree = AvlTree::new();
+    for key in [1, 2, 3] {
+        tree.insert(key);
+    }
+    assert_eq!(tree.rotations(), 1);
+    let mut zigzag = AvlTree::new();
+    for key in [3, 1, 2] {
+        zigzag.insert(key);
+    }
+    assert_eq!(zigzag.rotations(), 2);
+    // removing 3 leaves 1 and 2 balanced
+    zigzag.delete(3);
+    assert_eq!(zigzag.rotations(), 2);
+}
+
+#[test]
+pub fn test_validate() {
+    let mut rng = crate::rng::Rng::new(48);
+    let mut tree = AvlTree::new();
+    for _ in 0..2000 {
+        let key = rng.below(200) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+        } else if !tree.contains(&key) {
+            tree.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+    }
+}
+
+#[test]
+pub fn test_memory_usage() {
+    let mut tree = AvlTree::new_multiset();
+    for key in [3u32, 1, 2, 2, 2] {
+        tree.insert(key);
+    }
+    let usage = tree.memory_usage();
+    assert_eq!((usage.nodes, usage.keys), (3, 5));
+    assert_eq!(usage.total(), 3 * usage.node_bytes);
+    assert_eq!(usage.key_bytes + usage.rc_counter_bytes + usage.borrow_flag_bytes + usage.child_bytes
+        + usage.balance_bytes + usage.extra_bytes + usage.padding_bytes, usage.node_bytes);
+    assert_eq!((usage.parent_bytes, usage.balance_bytes), (0, 1));
+    // with no parent pointer an AVL node is smaller than a red-black one
+    let mut rb = crate::rbtree::RBTree::new();
+    rb.insert(1u32);
+    assert!(usage.node_bytes < rb.memory_usage().node_bytes);
+}
@@ -3,32 +3,54 @@ use std::rc::Rc;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::mem::size_of;
+use crate::shape::Shape;
+use crate::compare::{Comparator, Natural};
+use crate::memory::{MemoryUsage, NodeFields};
 
 type TreeNode<T> = Rc<RefCell<Node<T>>>;
 type Tree<T> = Option<TreeNode<T>>;
 
 #[derive(Clone, PartialEq)]
-pub struct Node<T: Ord+Display+Debug>{
+struct Node<T: Display+Debug>{
     key: T,
     left : Tree<T>,
     right : Tree<T>,
 }
 
 impl <T> Node<T>
-where T: Debug+Ord+Display+Copy{
+where T: Debug+Display+Copy{
     pub fn new(key :T) -> Tree<T>{
         Some(Rc::new(RefCell::new(Node{key:key,left:None,right:None})))
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct BST<T:Ord + Display + Debug + Copy>{
+#[derive(Clone, Debug)]
+pub struct BST<T: Display + Debug + Copy, C = Natural>{
     root : Tree<T>,
+    count : usize,
+    cmp : C,
+    // rebalance once an insert lands deeper than this many times log2 of the count
+    rebalance_factor : Option<f64>,
+    // the node holding the largest key and its depth while known, so ascending inserts can append
+    // to it directly instead of walking down the right spine each time
+    rightmost : Option<(TreeNode<T>, usize)>,
+}
 
+// written out by hand so the rightmost hint, which only saves work on inserts, is left out
+impl<T, C> PartialEq for BST<T, C>
+where T: Display+Debug+Copy+PartialEq, C: PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root && self.count == other.count && self.cmp == other.cmp
+            && self.rebalance_factor == other.rebalance_factor
+    }
 }
 
 impl<T> fmt::Debug for Node<T>
-where T: Debug+Ord+Display+Copy
+where T: Debug+Display+Copy
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Node")
@@ -39,92 +61,763 @@ where T: Debug+Ord+Display+Copy
     }
 }
 
-trait _Tree<T>
-where T: Ord+Display+Debug+Clone+Copy{
-    fn new(key:T) -> Tree<T>;
-    fn do_insert(&self, root:Tree<T>,val: T) -> Tree<T>;
-}
-impl <T> _Tree<T> for Tree<T>
-where T: Ord+Display+Debug+Clone+Copy{
-    fn new(key:T) -> Tree<T> {
-        Node::new(key)
+// tear the tree down with an explicit stack, dropping a long path recursively would overflow the stack
+impl<T: Display+Debug> Drop for Node<T> {
+    fn drop(&mut self) {
+        let mut stack: Vec<TreeNode<T>> = [self.left.take(), self.right.take()].into_iter().flatten().collect();
+        while let Some(node) = stack.pop() {
+            // nodes still shared with a search result or a clone are left to their other owner
+            if let Ok(node) = Rc::try_unwrap(node) {
+                let mut node = node.into_inner();
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
     }
-    
+}
 
-    fn do_insert(&self,tree:Tree<T>,key: T) -> Tree<T> {
-        match tree {
+// unroll the tree into a vine, a path of right children in key order, with right rotations.
+// returns the vine and its length
+fn tree_to_vine<T: Display+Debug>(tree: Tree<T>) -> (Tree<T>, usize) {
+    let mut head: Tree<T> = None;
+    let mut tail: Tree<T> = None;
+    let mut size = 0;
+    let mut rest = tree;
+    while let Some(node) = rest {
+        let left = node.borrow_mut().left.take();
+        match left {
+            Some(left) => {
+                // rotate right until the smallest key of what is left is on top
+                node.borrow_mut().left = left.borrow_mut().right.take();
+                left.borrow_mut().right = Some(node);
+                rest = Some(left);
+            },
             None => {
-                let add_node = Self::new(key);
-                add_node.clone()
-            }
-            Some(root) => {
-                let clone_node = root.borrow().clone();
-                let sub_node:Tree<T>;
-                if key == clone_node.key {
-                    Some(root.clone())
-                } 
-                else if key < clone_node.key {
-                    sub_node = root.borrow().left.clone();
-                    let result = self.do_insert(sub_node,key);
-                    let result_node = result;
-                    root.borrow_mut().left = result_node;
-                
-                    Some(root.clone())
-                }
-                //进入右子树递归插入
-                else {
-                    sub_node = root.borrow().right.clone();
-                    let result = self.do_insert(sub_node,key);
-                    let result_node = result;
-                    root.borrow_mut().right = result_node;
-                    Some(root.clone())
+                rest = node.borrow().right.clone();
+                match &tail {
+                    None => head = Some(node.clone()),
+                    Some(tail) => tail.borrow_mut().right = Some(node.clone()),
                 }
+                tail = Some(node);
+                size += 1;
             }
         }
     }
+    (head, size)
+}
 
+// left rotate every other node along the right spine below root, count times
+fn compress<T: Display+Debug>(root: &TreeNode<T>, count: usize) {
+    let mut scanner = root.clone();
+    for _ in 0..count {
+        let child = scanner.borrow_mut().right.take().unwrap();
+        let next = child.borrow_mut().right.take().unwrap();
+        child.borrow_mut().right = next.borrow_mut().left.take();
+        next.borrow_mut().left = Some(child);
+        scanner.borrow_mut().right = Some(next.clone());
+        scanner = next;
+    }
+}
 
-
-
+// fold a vine of size nodes back into a complete tree, the bottom level filled from the left
+fn vine_to_tree<T: Display+Debug+Copy>(vine: Tree<T>, size: usize) -> Tree<T> {
+    let head = vine?;
+    // a throwaway node above the vine so the first rotation has a parent to hang off
+    let key = head.borrow().key;
+    let pseudo_root = Rc::new(RefCell::new(Node { key, left: None, right: Some(head) }));
+    // nodes beyond the largest perfect tree that fits, they end up on the bottom level
+    let perfect = 1 << (usize::BITS - 1 - (size + 1).leading_zeros());
+    let leaves = size + 1 - perfect;
+    compress(&pseudo_root, leaves);
+    let mut size = size - leaves;
+    while size > 1 {
+        size /= 2;
+        compress(&pseudo_root, size);
+    }
+    let root = pseudo_root.borrow_mut().right.take();
+    root
 }
+
 impl <T> BST<T>
 where T: Ord+Display+Debug+Clone+Copy{
 
     pub fn new() -> Self{
-        BST { root: None}
+        Self::with_comparator(Natural)
+    }
+
+    // rebalance automatically whenever an insert goes deeper than factor * log2(count), factor >= 1
+    pub fn with_rebalance_factor(factor: f64) -> Self{
+        Self::with_comparator_and_rebalance_factor(Natural, factor)
+    }
+}
+
+impl <T, C> BST<T, C>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>{
+
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self{
+        BST { root: None, count: 0, cmp, rebalance_factor: None, rightmost: None}
+    }
+
+    pub fn with_comparator_and_rebalance_factor(cmp: C, factor: f64) -> Self{
+        assert!(factor >= 1.0, "no tree is shallower than log2 of its size");
+        BST { rebalance_factor: Some(factor), ..Self::with_comparator(cmp)}
     }
 
     pub fn insert(&mut self,key:T){
-        let root_node = self.root.clone();
-        let res_tree = self.root.do_insert(root_node,key);
-        self.root = res_tree;
+        if let Some((rightmost, depth)) = self.rightmost.clone() {
+            if self.cmp.compare(&key, &rightmost.borrow().key) == Ordering::Greater {
+                // past the largest key, the new node becomes the right child of the rightmost one
+                let new_node = Node::new(key);
+                rightmost.borrow_mut().right = new_node.clone();
+                self.rightmost = Some((new_node.unwrap(), depth + 1));
+                self.inserted_at(depth + 1);
+                return;
+            }
+        }
+        // walk down to the empty spot for key, iteratively since the tree can be a path
+        let mut parent: Option<(TreeNode<T>, Ordering)> = None;
+        let mut cur = self.root.clone();
+        let mut depth = 0;
+        let mut all_right = true;
+        while let Some(node) = cur {
+            let order = self.cmp.compare(&key, &node.borrow().key);
+            cur = match order {
+                // key already in tree
+                Ordering::Equal => return,
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+            all_right &= order == Ordering::Greater;
+            parent = Some((node, order));
+            depth += 1;
+        }
+        let new_node = Node::new(key);
+        match parent {
+            None => self.root = new_node.clone(),
+            Some((parent, Ordering::Less)) => parent.borrow_mut().left = new_node.clone(),
+            Some((parent, _)) => parent.borrow_mut().right = new_node.clone(),
+        }
+        if all_right {
+            self.rightmost = Some((new_node.unwrap(), depth));
+        }
+        self.inserted_at(depth);
     }
-       
 
+    // count a new node depth edges below the root and rebalance if it went too deep
+    fn inserted_at(&mut self, depth: usize) {
+        self.count += 1;
+        if let Some(factor) = self.rebalance_factor {
+            // levels down to the new node, against the levels a balanced tree would need
+            if (depth + 1) as f64 > factor * ((self.count + 1) as f64).log2() {
+                self.rebalance();
+            }
+        }
+    }
 
-    pub fn search(&self, key: T) -> Tree<T> {
-        let dummy = Node::<T>::new(key).unwrap().borrow().clone();
-        self.search_node(&self.root, &dummy)
+    // Day-Stout-Warren: unroll into a sorted vine, then fold it into a complete tree. O(n) time and
+    // O(1) extra space, the nodes are reused
+    pub fn rebalance(&mut self){
+        self.rightmost = None;
+        let (vine, size) = tree_to_vine(self.root.take());
+        self.root = vine_to_tree(vine, size);
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.search_node(&self.root, key).is_some()
     }
-    
 
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.search_node(&self.root, key).map(|node| node.borrow().key)
+    }
     
-    fn search_node(&self, tree_node: &Tree<T>, node: &Node<T>) -> Tree<T> {
-        match tree_node {
-            Some(sub_tree) => {
-                let sub_tree_clone = sub_tree.borrow().clone();
-                if sub_tree_clone.key == node.key {
-                    Some(sub_tree.clone())
-                } else {
-                    if sub_tree_clone.key > node.key {
-                        self.search_node(&sub_tree_clone.left, node)
-                    } else {
-                        self.search_node(&sub_tree_clone.right, node)
+    fn search_node(&self, tree_node: &Tree<T>, key: &T) -> Tree<T> {
+        let mut cur = tree_node.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn delete(&mut self, key: T) {
+        // find the node and the link it hangs off, iteratively since an unbalanced tree can be a path
+        let mut parent: Tree<T> = None;
+        let mut cur = self.root.clone();
+        let node = loop {
+            let node = match cur {
+                Some(node) => node,
+                // key not in tree
+                None => return,
+            };
+            let order = self.cmp.compare(&key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => break node,
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+            parent = Some(node);
+        };
+        let left = node.borrow().left.clone();
+        let right = node.borrow().right.clone();
+        match (left, right) {
+            (Some(_), Some(right)) => {
+                // two children: take the successor's key and unlink the successor, it has no left child
+                let mut successor_parent = node.clone();
+                let mut successor = right;
+                loop {
+                    let left = successor.borrow().left.clone();
+                    match left {
+                        Some(left) => {
+                            successor_parent = successor;
+                            successor = left;
+                        },
+                        None => break,
                     }
                 }
+                node.borrow_mut().key = successor.borrow().key;
+                let successor_right = successor.borrow_mut().right.take();
+                if Rc::ptr_eq(&successor_parent, &node) {
+                    node.borrow_mut().right = successor_right;
+                } else {
+                    successor_parent.borrow_mut().left = successor_right;
+                }
             },
-            None => {None}
+            (left, right) => {
+                // at most one child, it takes the node's place
+                let child = left.or(right);
+                match parent {
+                    None => self.root = child,
+                    Some(parent) => {
+                        let is_left = parent.borrow().left.as_ref().is_some_and(|left| Rc::ptr_eq(left, &node));
+                        if is_left {
+                            parent.borrow_mut().left = child;
+                        } else {
+                            parent.borrow_mut().right = child;
+                        }
+                    }
+                }
+            }
+        }
+        self.count -= 1;
+        // the rightmost node or its depth may have changed, the next all-right insert finds it again
+        self.rightmost = None;
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    // keys k with lo <= k < hi, in the tree's order
+    pub fn range(&self, lo: T, hi: T) -> Vec<T> {
+        let mut keys: Vec<T> = Vec::new();
+        let mut stack: Vec<TreeNode<T>> = Vec::new();
+        let mut cur = self.root.clone();
+        loop {
+            // walk left, skipping every subtree that is entirely below lo
+            while let Some(node) = cur.take() {
+                if self.cmp.compare(&node.borrow().key, &lo) == Ordering::Less {
+                    cur = node.borrow().right.clone();
+                } else {
+                    cur = node.borrow().left.clone();
+                    stack.push(node);
+                }
+            }
+            let node = match stack.pop() {
+                Some(node) => node,
+                None => break,
+            };
+            let key = node.borrow().key;
+            if self.cmp.compare(&key, &hi) != Ordering::Less {
+                break;
+            }
+            keys.push(key);
+            cur = node.borrow().right.clone();
+        }
+        keys
+    }
+
+    pub fn height(&self) -> u32 {
+        if self.root.is_none() {
+            return 0;
         }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        let mut height = 0;
+        // find height by breadth first search traversal
+        while !queue.is_empty() {
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                    queue.push_back(child);
+                }
+            }
+            height += 1;
+        }
+        height
+    }
+
+    // check that the keys come out in strictly increasing order and that count matches them. walks
+    // with the iterator's stack, a BST fed sorted keys is one long path
+    pub fn validate(&self) -> Result<(), String> {
+        let mut previous: Option<T> = None;
+        let mut size = 0;
+        for key in self.iter() {
+            if previous.is_some_and(|previous| self.cmp.compare(&previous, &key) != Ordering::Less) {
+                return Err(format!("{} is out of order", key));
+            }
+            previous = Some(key);
+            size += 1;
+        }
+        if size != self.count {
+            return Err(format!("count is {} but the tree holds {} keys", self.count, size));
+        }
+        if self.rightmost.as_ref().is_some_and(|(node, _)| node.borrow().right.is_some()) {
+            return Err("the rightmost hint has a right child".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn leaves(&self) -> u32 {
+        let mut stack: Vec<TreeNode<T>> = self.root.clone().into_iter().collect();
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            let left = node.borrow().left.clone();
+            let right = node.borrow().right.clone();
+            if left.is_none() && right.is_none() {
+                count += 1;
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        count
+    }
+
+    // heap bytes held by the nodes, which carry nothing but a key and two children
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::of::<Node<T>, T>(self.count, self.count, NodeFields {
+            child: 2 * size_of::<Tree<T>>(),
+            parent: 0,
+            balance: 0,
+            extra: 0,
+        })
+    }
+
+    // iterate over the keys in order
+    pub fn iter(&self) -> Iter<T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    pub fn print_preorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut stack: Vec<TreeNode<T>> = vec![self.root.clone().unwrap()];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", node.borrow().key);
+            stack.extend(node.borrow().right.clone());
+            stack.extend(node.borrow().left.clone());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", node.borrow().key);
+            queue.extend(node.borrow().left.clone());
+            queue.extend(node.borrow().right.clone());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T>| {
+            let node = node.borrow();
+            (vec![node.key], Vec::new(), vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        };
+
+        // an explicit stack of (node, prefix, child prefix), a degenerate tree would overflow recursion
+        let mut buffer = String::new();
+        let mut stack = vec![(self.root.clone().unwrap(), String::new(), String::new())];
+        while let Some((node, prefix, child_prefix)) = stack.pop() {
+            buffer.push_str(&prefix);
+            buffer.push_str(&node.borrow().key.to_string());
+            buffer.push('\n');
+            // pushed left first so the right child is printed first, as in the other trees
+            for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                let has_children = child.borrow().left.is_some() || child.borrow().right.is_some();
+                let (branch, indent) = if has_children { ("├── ", "│   ") } else { ("└── ", "    ") };
+                stack.push((child, format!("{}{}", child_prefix, branch), format!("{}{}", child_prefix, indent)));
+            }
+        }
+        println!("{}", buffer);
+    }
+
+    // the keys in order and the comparator, for building other trees out of this one
+    pub(crate) fn into_parts(self) -> (Vec<T>, C) {
+        (self.iter().collect(), self.cmp)
+    }
+}
+
+impl<T> Default for BST<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy> {
+    stack: Vec<TreeNode<T>>,
+}
+
+impl<T> Iter<T>
+where T: Display+Debug+Copy
+{
+    fn push_left(&mut self, tree: Tree<T>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T>
+where T: Display+Debug+Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let key = node.borrow().key;
+        self.push_left(node.borrow().right.clone());
+        Some(key)
+    }
+}
+
+impl<T, C> fmt::Display for BST<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BST")
+         .field("root", &self.root)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+
+#[test]
+pub fn test_rebalance_sorted_inserts() {
+    let mut tree = BST::new();
+    for key in 0..1000 {
+        tree.insert(key);
+    }
+    // inserted in order, the tree is a path
+    assert_eq!(tree.height(), 1000);
+    tree.rebalance();
+    assert_eq!(tree.height(), 10);
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), (0..1000).collect::<Vec<u32>>());
+    for key in [0, 511, 999] {
+        assert!(tree.contains(&key));
+    }
+    assert!(!tree.contains(&1000));
+}
+
+#[test]
+pub fn test_rebalance_small_trees() {
+    for size in 0..70u32 {
+        let mut tree = BST::new();
+        for key in (0..size).rev() {
+            tree.insert(key);
+        }
+        tree.rebalance();
+        // a complete tree, no level beyond what size keys need
+        assert_eq!(tree.height(), u32::BITS - size.leading_zeros());
+        assert_eq!(tree.into_parts().0, (0..size).collect::<Vec<u32>>());
+    }
+}
+
+#[test]
+pub fn test_auto_rebalance() {
+    let mut tree = BST::with_rebalance_factor(2.0);
+    for key in 0..2000 {
+        tree.insert(key);
+        assert!(tree.height() as f64 <= 2.0 * ((tree.count() + 1) as f64).log2() + 1.0);
+    }
+    tree.insert(5);
+    assert_eq!(tree.count(), 2000);
+}
+
+#[test]
+pub fn test_convert_bst() {
+    let mut tree = BST::with_comparator(crate::compare::Reverse);
+    for key in 0..100 {
+        tree.insert(key);
+    }
+    let avl = crate::avltree::AvlTree::from(tree.clone());
+    assert_eq!(avl.count(), 100);
+    assert!(avl.height() <= 8);
+    assert_eq!(avl.iter().collect::<Vec<u32>>(), (0..100).rev().collect::<Vec<u32>>());
+    let rb = crate::rbtree::RBTree::from(tree);
+    assert_eq!(rb.count(), 100);
+    assert_eq!(rb.iter().collect::<Vec<u32>>(), (0..100).rev().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn create_empty_bst() {
+    let bst: BST<u32> = BST::new();
+    assert!(bst.is_empty());
+    assert_eq!(bst.count(), 0);
+    assert_eq!(bst.height(), 0);
+    assert_eq!(bst.leaves(), 0);
+    assert!(bst.first().is_none());
+}
+
+#[test]
+pub fn insert_into_bst_1() {
+    let mut x = BST::new();
+    x.insert(8);
+    x.insert(3);
+    x.insert(10);
+    x.insert(1);
+    x.insert(6);
+    x.insert(3);
+    // no rotations, the shape follows the insertion order
+    assert_eq!(x.root.as_ref().unwrap().borrow().key, 8);
+    assert_eq!(x.root.as_ref().unwrap().borrow().left.as_ref().unwrap().borrow().key, 3);
+    assert_eq!(x.root.as_ref().unwrap().borrow().right.as_ref().unwrap().borrow().key, 10);
+    assert_eq!(x.count(), 5);
+    assert_eq!(x.height(), 3);
+    assert_eq!(x.leaves(), 3);
+}
+
+#[test]
+pub fn search_bst_1() {
+    let mut x = BST::new();
+    for key in [9, 8, 12, 3] {
+        x.insert(key);
+    }
+    assert_eq!(x.get(&8).unwrap(), 8);
+    assert!(!x.contains(&81));
+}
+
+#[test]
+pub fn delete_bst_leaf_and_single_child() {
+    let mut x = BST::new();
+    for key in [8, 3, 10, 1, 14] {
+        x.insert(key);
+    }
+    x.delete(1);
+    assert!(x.root.as_ref().unwrap().borrow().left.as_ref().unwrap().borrow().left.is_none());
+    // 10 only has a right child, 14 moves up
+    x.delete(10);
+    assert_eq!(x.root.as_ref().unwrap().borrow().right.as_ref().unwrap().borrow().key, 14);
+    x.delete(99);
+    assert_eq!(x.count(), 3);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![3, 8, 14]);
+}
+
+#[test]
+pub fn delete_bst_two_children() {
+    let mut x = BST::new();
+    for key in [8, 3, 10, 1, 6, 4, 7, 14] {
+        x.insert(key);
+    }
+    // 3 is replaced by its successor 4
+    x.delete(3);
+    assert_eq!(x.root.as_ref().unwrap().borrow().left.as_ref().unwrap().borrow().key, 4);
+    // the root too, by 10 which is its right child
+    x.delete(8);
+    assert_eq!(x.root.as_ref().unwrap().borrow().key, 10);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![1, 4, 6, 7, 10, 14]);
+    for key in [1, 4, 6, 7, 10, 14] {
+        x.delete(key);
+    }
+    assert!(x.is_empty());
+    assert_eq!(x.count(), 0);
+}
+
+#[test]
+pub fn test_bst_min_max() {
+    let mut a = BST::new();
+    for key in ["f", "a", "d", "g", "u", "c"] {
+        a.insert(key);
+    }
+    assert_eq!(a.first().unwrap(), "a");
+    assert_eq!(a.last().unwrap(), "u");
+    assert_eq!(a.range("b", "g"), vec!["c", "d", "f"]);
+}
+
+#[test]
+pub fn test_bst_random() {
+    let mut tree = BST::new();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut rng = crate::rng::Rng::new(38);
+    for _ in 0..3000 {
+        let key = rng.below(500) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+            keys.remove(&key);
+        } else {
+            tree.insert(key);
+            keys.insert(key);
+        }
+        assert_eq!(tree.count(), keys.len());
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_bst_reverse_comparator() {
+    let mut x = BST::with_comparator(crate::compare::Reverse);
+    for key in [5, 1, 9, 3, 7] {
+        x.insert(key);
+    }
+    assert_eq!(x.first().unwrap(), 9);
+    assert_eq!(x.last().unwrap(), 1);
+    assert_eq!(x.range(8, 2), vec![7, 5, 3]);
+}
+
+#[test]
+pub fn test_bst_million_sorted_keys() {
+    // a path a million nodes deep, every walk over it has to be iterative
+    let mut x = BST::new();
+    for key in 0..1_000_000 {
+        x.insert(key);
+    }
+    assert_eq!(x.count(), 1_000_000);
+    assert_eq!(x.get(&999_999).unwrap(), 999_999);
+    assert_eq!(x.last().unwrap(), 999_999);
+    x.delete(500_000);
+    x.insert(1_000_000);
+    assert_eq!(x.count(), 1_000_000);
+    assert_eq!(x.leaves(), 1);
+    assert_eq!(x.iter().nth(500_000), Some(500_001));
+}
+
+#[test]
+pub fn test_bst_rightmost_hint() {
+    let mut x = BST::with_rebalance_factor(2.0);
+    for key in [50, 60, 70, 55, 80, 40, 90] {
+        x.insert(key);
+    }
+    x.delete(90);
+    x.insert(85);
+    x.insert(95);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![40, 50, 55, 60, 70, 80, 85, 95]);
+    assert_eq!(x.last().unwrap(), 95);
+}
+
+#[test]
+pub fn test_validate() {
+    let mut rng = crate::rng::Rng::new(48);
+    let mut tree = BST::new();
+    for _ in 0..2000 {
+        let key = rng.below(200) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+        } else if !tree.contains(&key) {
+            tree.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    // a long sorted run goes through the rightmost hint
+    let mut sorted = BST::new();
+    for key in 0..100_000u32 {
+        sorted.insert(key);
+    }
+    assert_eq!(sorted.validate(), Ok(()));
+}
+
+#[test]
+pub fn test_memory_usage() {
+    let mut tree = BST::new();
+    for key in [50u32, 30, 70, 20] {
+        tree.insert(key);
+    }
+    let usage = tree.memory_usage();
+    assert_eq!((usage.nodes, usage.keys), (4, 4));
+    assert_eq!(usage.bytes_per_key(), usage.node_bytes as f64);
+    assert_eq!((usage.parent_bytes, usage.balance_bytes, usage.extra_bytes), (0, 0, 0));
+    // nothing but the Rc and RefCell bookkeeping, the key, two children and padding
+    assert_eq!(usage.overhead_per_node(), usage.rc_counter_bytes + usage.borrow_flag_bytes + usage.child_bytes + usage.padding_bytes);
+    tree.delete(30);
+    assert_eq!(tree.memory_usage().total(), 3 * usage.node_bytes);
+}
+
+#[test]
+pub fn test_eq_ignores_rightmost_hint() {
+    let mut x = BST::new();
+    let mut y = BST::new();
+    for key in [1, 2, 3, 4] {
+        x.insert(key);
+        y.insert(key);
     }
- 
+    // the same chain of keys, but the delete dropped y's hint
+    y.insert(5);
+    y.delete(5);
+    assert!(x.rightmost.is_some() && y.rightmost.is_none());
+    assert_eq!(x, y);
+    y.delete(4);
+    assert_ne!(x, y);
 }
@@ -0,0 +1,1099 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::mem;
+use crate::shape::Shape;
+use crate::compare::{Comparator, Natural};
+
+// children per node when no order is given, enough keys per node to fill a few cache lines
+const DEFAULT_ORDER: usize = 32;
+
+// what inserting into a subtree reports back to its parent
+enum Inserted<T, N> {
+    Duplicate,
+    Done,
+    // the subtree split, the parent has to take the separator and the new right sibling
+    Split(T, N),
+}
+
+// node keys printed as [1, 2, 3]
+fn label<T: Display>(keys: &[T]) -> String {
+    let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+    format!("[{}]", keys.join(", "))
+}
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    keys: Vec<T>,
+    // empty for a leaf, otherwise one more than keys
+    children: Vec<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new() -> Self {
+        Node { keys: Vec::new(), children: Vec::new() }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+// B-tree of a given order: a node holds up to order - 1 sorted keys in one Vec and up to order
+// children, and every node but the root stays at least half full. Lookups touch O(log_order n)
+// nodes instead of O(log2 n) separately allocated ones.
+#[derive(Clone, Debug)]
+pub struct BTree<T: Display+Debug+Copy, C = Natural> {
+    root: Node<T>,
+    order: usize,
+    count: usize,
+    cmp: C,
+}
+
+impl<T> BTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // order is the most children a node can have, at least 3
+    pub fn with_order(order: usize) -> Self {
+        Self::with_comparator_and_order(Natural, order)
+    }
+}
+
+impl<T, C> BTree<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_comparator_and_order(cmp, DEFAULT_ORDER)
+    }
+
+    pub fn with_comparator_and_order(cmp: C, order: usize) -> Self {
+        assert!(order >= 3, "a B-tree needs an order of at least 3");
+        BTree {
+            root: Node::new(),
+            order,
+            count: 0,
+            cmp,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn insert(&mut self, key: T) {
+        match Self::insert_node(&mut self.root, key, &self.cmp, self.order) {
+            Inserted::Duplicate => return,
+            Inserted::Done => {},
+            Inserted::Split(separator, right) => {
+                // the root split, so the tree grows a level
+                let left = mem::replace(&mut self.root, Node::new());
+                self.root.keys.push(separator);
+                self.root.children = vec![left, right];
+            }
+        }
+        self.count += 1;
+    }
+
+    fn insert_node(node: &mut Node<T>, key: T, cmp: &C, order: usize) -> Inserted<T, Node<T>> {
+        let pos = match node.keys.binary_search_by(|k| cmp.compare(k, &key)) {
+            Ok(_) => return Inserted::Duplicate,
+            Err(pos) => pos,
+        };
+        if node.is_leaf() {
+            node.keys.insert(pos, key);
+        } else {
+            match Self::insert_node(&mut node.children[pos], key, cmp, order) {
+                Inserted::Split(separator, right) => {
+                    node.keys.insert(pos, separator);
+                    node.children.insert(pos + 1, right);
+                },
+                other => return other,
+            }
+        }
+        if node.keys.len() < order {
+            return Inserted::Done;
+        }
+        // too many keys, move the middle one up and the ones after it into a new right sibling
+        let mid = node.keys.len() / 2;
+        let mut right = Node::new();
+        right.keys = node.keys.split_off(mid + 1);
+        let separator = node.keys.pop().unwrap();
+        if !node.is_leaf() {
+            right.children = node.children.split_off(mid + 1);
+        }
+        Inserted::Split(separator, right)
+    }
+
+    pub fn delete(&mut self, key: T) {
+        if Self::delete_node(&mut self.root, &key, &self.cmp, self.order) {
+            self.count -= 1;
+        }
+        if self.root.keys.is_empty() && !self.root.is_leaf() {
+            // the root lost its last key to a merge, its only child takes over
+            self.root = self.root.children.pop().unwrap();
+        }
+    }
+
+    fn delete_node(node: &mut Node<T>, key: &T, cmp: &C, order: usize) -> bool {
+        match node.keys.binary_search_by(|k| cmp.compare(k, key)) {
+            Ok(pos) => {
+                if node.is_leaf() {
+                    node.keys.remove(pos);
+                    return true;
+                }
+                // swap in the predecessor, then delete the predecessor from the left subtree
+                let mut cur = &node.children[pos];
+                while !cur.is_leaf() {
+                    cur = cur.children.last().unwrap();
+                }
+                let predecessor = *cur.keys.last().unwrap();
+                node.keys[pos] = predecessor;
+                Self::delete_node(&mut node.children[pos], &predecessor, cmp, order);
+                Self::fix_child(node, pos, order);
+                true
+            },
+            Err(pos) => {
+                if node.is_leaf() {
+                    return false;
+                }
+                let removed = Self::delete_node(&mut node.children[pos], key, cmp, order);
+                if removed {
+                    Self::fix_child(node, pos, order);
+                }
+                removed
+            }
+        }
+    }
+
+    // refill children[pos] if it dropped below half full, from a sibling or by merging with one
+    fn fix_child(node: &mut Node<T>, pos: usize, order: usize) {
+        let min_keys = order.div_ceil(2) - 1;
+        if node.children[pos].keys.len() >= min_keys {
+            return;
+        }
+        if pos > 0 && node.children[pos - 1].keys.len() > min_keys {
+            // rotate the left sibling's last key up and the separator down
+            let (before, after) = node.children.split_at_mut(pos);
+            let left = &mut before[pos - 1];
+            let child = &mut after[0];
+            let separator = mem::replace(&mut node.keys[pos - 1], left.keys.pop().unwrap());
+            child.keys.insert(0, separator);
+            if !left.is_leaf() {
+                child.children.insert(0, left.children.pop().unwrap());
+            }
+        } else if pos + 1 < node.children.len() && node.children[pos + 1].keys.len() > min_keys {
+            let (before, after) = node.children.split_at_mut(pos + 1);
+            let child = &mut before[pos];
+            let right = &mut after[0];
+            let separator = mem::replace(&mut node.keys[pos], right.keys.remove(0));
+            child.keys.push(separator);
+            if !right.is_leaf() {
+                child.children.push(right.children.remove(0));
+            }
+        } else {
+            // both siblings are at the minimum, merge with one of them around their separator
+            let pos = if pos > 0 { pos - 1 } else { pos };
+            let right = node.children.remove(pos + 1);
+            let separator = node.keys.remove(pos);
+            let left = &mut node.children[pos];
+            left.keys.push(separator);
+            left.keys.extend(right.keys);
+            left.children.extend(right.children);
+        }
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        let mut node = &self.root;
+        loop {
+            match node.keys.binary_search_by(|k| self.cmp.compare(k, key)) {
+                Ok(pos) => return Some(node.keys[pos]),
+                Err(pos) => {
+                    if node.is_leaf() {
+                        return None;
+                    }
+                    node = &node.children[pos];
+                }
+            }
+        }
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut node = &self.root;
+        while !node.is_leaf() {
+            node = &node.children[0];
+        }
+        node.keys.first().copied()
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut node = &self.root;
+        while !node.is_leaf() {
+            node = node.children.last().unwrap();
+        }
+        node.keys.last().copied()
+    }
+
+    // number of levels, every leaf is at the same depth
+    pub fn height(&self) -> u32 {
+        if self.is_empty() {
+            return 0;
+        }
+        let mut height = 1;
+        let mut node = &self.root;
+        while !node.is_leaf() {
+            node = &node.children[0];
+            height += 1;
+        }
+        height
+    }
+
+    // number of leaf nodes
+    pub fn leaves(&self) -> u32 {
+        if self.is_empty() {
+            return 0;
+        }
+        let mut stack = vec![&self.root];
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            if node.is_leaf() {
+                count += 1;
+            }
+            stack.extend(node.children.iter());
+        }
+        count
+    }
+
+    // keys k with lo <= k < hi, in the tree's order
+    pub fn range(&self, lo: T, hi: T) -> Vec<T> {
+        fn collect<T: Copy, C: Comparator<T>>(node: &Node<T>, lo: &T, hi: &T, cmp: &C, keys: &mut Vec<T>) -> bool {
+            // skip the keys and subtrees entirely below lo
+            let start = node.keys.partition_point(|k| cmp.compare(k, lo) == Ordering::Less);
+            for pos in start..=node.keys.len() {
+                if !node.is_leaf() && !collect(&node.children[pos], lo, hi, cmp, keys) {
+                    return false;
+                }
+                if pos < node.keys.len() {
+                    if cmp.compare(&node.keys[pos], hi) != Ordering::Less {
+                        return false;
+                    }
+                    keys.push(node.keys[pos]);
+                }
+            }
+            true
+        }
+
+        let mut keys = Vec::new();
+        collect(&self.root, &lo, &hi, &self.cmp, &mut keys);
+        keys
+    }
+
+    // iterate over the keys in order
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(&self.root);
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    // one node at a time, a node's keys before its children
+    pub fn print_preorder(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", label(&node.keys));
+            stack.extend(node.children.iter().rev());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+        let mut queue = VecDeque::new();
+        queue.push_back(&self.root);
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", label(&node.keys));
+            queue.extend(node.children.iter());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        let root = if self.count == 0 { None } else { Some(&self.root) };
+        Shape::build(root, false, |node: &&Node<T>| {
+            (node.keys.clone(), Vec::new(), node.children.iter().map(Some).collect())
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+
+        fn pretty_print<T: Display>(node: &Node<T>, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&label(&node.keys));
+            buffer.push('\n');
+            for (i, child) in node.children.iter().enumerate() {
+                let (branch, indent) = if i + 1 < node.children.len() { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(&self.root, &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for BTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, T> {
+    // nodes on the way down and the index of the next key to hand out in each
+    stack: Vec<(&'a Node<T>, usize)>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn push_left(&mut self, node: &'a Node<T>) {
+        let mut node = node;
+        loop {
+            self.stack.push((node, 0));
+            if node.is_leaf() {
+                break;
+            }
+            node = &node.children[0];
+        }
+    }
+}
+
+impl<T: Copy> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let (node, pos) = self.stack.pop()?;
+            if pos < node.keys.len() {
+                self.stack.push((node, pos + 1));
+                if !node.is_leaf() {
+                    self.push_left(&node.children[pos + 1]);
+                }
+                return Some(node.keys[pos]);
+            }
+        }
+    }
+}
+
+impl<T, C> fmt::Display for BTree<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BTree")
+         .field("root", &self.root)
+         .field("order", &self.order)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PlusNode<T> {
+    // in an internal node keys[i] is the smallest key that can be under children[i + 1]
+    keys: Vec<T>,
+    // indices into the arena, empty for a leaf
+    children: Vec<usize>,
+    // the leaf to the right, only used by leaves
+    next: Option<usize>,
+}
+
+impl<T> PlusNode<T> {
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+// B+tree: internal nodes only route, every key is stored in a leaf and the leaves are chained left
+// to right, so a range scan finds its first leaf once and then just follows next. Nodes sit in one
+// arena Vec and point at each other by index, freed slots are reused.
+#[derive(Clone, Debug)]
+pub struct BPlusTree<T: Display+Debug+Copy, C = Natural> {
+    nodes: Vec<PlusNode<T>>,
+    free: Vec<usize>,
+    root: usize,
+    order: usize,
+    count: usize,
+    cmp: C,
+}
+
+impl<T> BPlusTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // order is the most children an internal node can have and one more than the most keys in a leaf
+    pub fn with_order(order: usize) -> Self {
+        Self::with_comparator_and_order(Natural, order)
+    }
+}
+
+impl<T, C> BPlusTree<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_comparator_and_order(cmp, DEFAULT_ORDER)
+    }
+
+    pub fn with_comparator_and_order(cmp: C, order: usize) -> Self {
+        assert!(order >= 3, "a B+tree needs an order of at least 3");
+        BPlusTree {
+            nodes: vec![PlusNode { keys: Vec::new(), children: Vec::new(), next: None }],
+            free: Vec::new(),
+            root: 0,
+            order,
+            count: 0,
+            cmp,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn alloc(&mut self, node: PlusNode<T>) -> usize {
+        match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            },
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn release(&mut self, idx: usize) {
+        self.nodes[idx].keys = Vec::new();
+        self.nodes[idx].children = Vec::new();
+        self.nodes[idx].next = None;
+        self.free.push(idx);
+    }
+
+    // which child of an internal node key belongs under
+    fn child_pos(&self, idx: usize, key: &T) -> usize {
+        self.nodes[idx].keys.partition_point(|k| self.cmp.compare(k, key) != Ordering::Greater)
+    }
+
+    fn find_leaf(&self, key: &T) -> usize {
+        let mut idx = self.root;
+        while !self.nodes[idx].is_leaf() {
+            idx = self.nodes[idx].children[self.child_pos(idx, key)];
+        }
+        idx
+    }
+
+    fn first_leaf(&self) -> usize {
+        let mut idx = self.root;
+        while !self.nodes[idx].is_leaf() {
+            idx = self.nodes[idx].children[0];
+        }
+        idx
+    }
+
+    pub fn insert(&mut self, key: T) {
+        match self.insert_node(self.root, key) {
+            Inserted::Duplicate => return,
+            Inserted::Done => {},
+            Inserted::Split(separator, right) => {
+                let left = self.root;
+                self.root = self.alloc(PlusNode { keys: vec![separator], children: vec![left, right], next: None });
+            }
+        }
+        self.count += 1;
+    }
+
+    fn insert_node(&mut self, idx: usize, key: T) -> Inserted<T, usize> {
+        if self.nodes[idx].is_leaf() {
+            let pos = match self.nodes[idx].keys.binary_search_by(|k| self.cmp.compare(k, &key)) {
+                Ok(_) => return Inserted::Duplicate,
+                Err(pos) => pos,
+            };
+            self.nodes[idx].keys.insert(pos, key);
+            if self.nodes[idx].keys.len() < self.order {
+                return Inserted::Done;
+            }
+            // split the leaf and link the new half in after it, its first key is copied up
+            let mid = self.nodes[idx].keys.len() / 2;
+            let keys = self.nodes[idx].keys.split_off(mid);
+            let separator = keys[0];
+            let next = self.nodes[idx].next;
+            let right = self.alloc(PlusNode { keys, children: Vec::new(), next });
+            self.nodes[idx].next = Some(right);
+            return Inserted::Split(separator, right);
+        }
+        let pos = self.child_pos(idx, &key);
+        match self.insert_node(self.nodes[idx].children[pos], key) {
+            Inserted::Split(separator, right) => {
+                self.nodes[idx].keys.insert(pos, separator);
+                self.nodes[idx].children.insert(pos + 1, right);
+            },
+            other => return other,
+        }
+        if self.nodes[idx].children.len() <= self.order {
+            return Inserted::Done;
+        }
+        // split the internal node, the middle separator moves up instead of being copied
+        let mid = self.nodes[idx].keys.len() / 2;
+        let keys = self.nodes[idx].keys.split_off(mid + 1);
+        let separator = self.nodes[idx].keys.pop().unwrap();
+        let children = self.nodes[idx].children.split_off(mid + 1);
+        let right = self.alloc(PlusNode { keys, children, next: None });
+        Inserted::Split(separator, right)
+    }
+
+    pub fn delete(&mut self, key: T) {
+        if self.delete_node(self.root, &key) {
+            self.count -= 1;
+        }
+        if self.nodes[self.root].children.len() == 1 {
+            let old_root = self.root;
+            self.root = self.nodes[old_root].children[0];
+            self.release(old_root);
+        }
+    }
+
+    fn delete_node(&mut self, idx: usize, key: &T) -> bool {
+        if self.nodes[idx].is_leaf() {
+            return match self.nodes[idx].keys.binary_search_by(|k| self.cmp.compare(k, key)) {
+                Ok(pos) => {
+                    self.nodes[idx].keys.remove(pos);
+                    true
+                },
+                Err(_) => false,
+            };
+        }
+        // separators equal to a deleted key can stay, they still split the children correctly
+        let pos = self.child_pos(idx, key);
+        if !self.delete_node(self.nodes[idx].children[pos], key) {
+            return false;
+        }
+        self.fix_child(idx, pos);
+        true
+    }
+
+    // refill children[pos] of idx if it dropped below half full, from a sibling or by merging with one
+    fn fix_child(&mut self, idx: usize, pos: usize) {
+        let child = self.nodes[idx].children[pos];
+        let leaf = self.nodes[child].is_leaf();
+        let min_keys = if leaf { self.order / 2 } else { self.order.div_ceil(2) - 1 };
+        if self.nodes[child].keys.len() >= min_keys {
+            return;
+        }
+        let left = if pos > 0 { Some(self.nodes[idx].children[pos - 1]) } else { None };
+        let right = self.nodes[idx].children.get(pos + 1).copied();
+        if let Some(left) = left.filter(|left| self.nodes[*left].keys.len() > min_keys) {
+            let key = self.nodes[left].keys.pop().unwrap();
+            if leaf {
+                self.nodes[child].keys.insert(0, key);
+                self.nodes[idx].keys[pos - 1] = key;
+            } else {
+                // rotate through the parent, the separator comes down and the sibling's last key goes up
+                let separator = mem::replace(&mut self.nodes[idx].keys[pos - 1], key);
+                let moved = self.nodes[left].children.pop().unwrap();
+                self.nodes[child].keys.insert(0, separator);
+                self.nodes[child].children.insert(0, moved);
+            }
+        } else if let Some(right) = right.filter(|right| self.nodes[*right].keys.len() > min_keys) {
+            let key = self.nodes[right].keys.remove(0);
+            if leaf {
+                self.nodes[child].keys.push(key);
+                self.nodes[idx].keys[pos] = self.nodes[right].keys[0];
+            } else {
+                let separator = mem::replace(&mut self.nodes[idx].keys[pos], key);
+                let moved = self.nodes[right].children.remove(0);
+                self.nodes[child].keys.push(separator);
+                self.nodes[child].children.push(moved);
+            }
+        } else {
+            // both siblings are at the minimum, merge the right one of a pair into the left one
+            let pos = if pos > 0 { pos - 1 } else { pos };
+            let left = self.nodes[idx].children[pos];
+            let right = self.nodes[idx].children.remove(pos + 1);
+            let separator = self.nodes[idx].keys.remove(pos);
+            let keys = mem::take(&mut self.nodes[right].keys);
+            let children = mem::take(&mut self.nodes[right].children);
+            if leaf {
+                self.nodes[left].next = self.nodes[right].next;
+            } else {
+                self.nodes[left].keys.push(separator);
+            }
+            self.nodes[left].keys.extend(keys);
+            self.nodes[left].children.extend(children);
+            self.release(right);
+        }
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        let leaf = &self.nodes[self.find_leaf(key)];
+        match leaf.keys.binary_search_by(|k| self.cmp.compare(k, key)) {
+            Ok(pos) => Some(leaf.keys[pos]),
+            Err(_) => None,
+        }
+    }
+
+    pub fn first(&self) -> Option<T> {
+        self.nodes[self.first_leaf()].keys.first().copied()
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut idx = self.root;
+        while !self.nodes[idx].is_leaf() {
+            idx = *self.nodes[idx].children.last().unwrap();
+        }
+        self.nodes[idx].keys.last().copied()
+    }
+
+    // number of levels, every leaf is at the same depth
+    pub fn height(&self) -> u32 {
+        if self.is_empty() {
+            return 0;
+        }
+        let mut height = 1;
+        let mut idx = self.root;
+        while !self.nodes[idx].is_leaf() {
+            idx = self.nodes[idx].children[0];
+            height += 1;
+        }
+        height
+    }
+
+    // number of leaf nodes, counted along the leaf chain
+    pub fn leaves(&self) -> u32 {
+        if self.is_empty() {
+            return 0;
+        }
+        let mut count = 0;
+        let mut leaf = Some(self.first_leaf());
+        while let Some(idx) = leaf {
+            count += 1;
+            leaf = self.nodes[idx].next;
+        }
+        count
+    }
+
+    // keys k with lo <= k < hi, one descent to lo and then a walk along the leaves
+    pub fn range(&self, lo: T, hi: T) -> Vec<T> {
+        let mut keys = Vec::new();
+        let mut leaf = Some(self.find_leaf(&lo));
+        let mut start = self.nodes[leaf.unwrap()].keys.partition_point(|k| self.cmp.compare(k, &lo) == Ordering::Less);
+        while let Some(idx) = leaf {
+            for key in &self.nodes[idx].keys[start..] {
+                if self.cmp.compare(key, &hi) != Ordering::Less {
+                    return keys;
+                }
+                keys.push(*key);
+            }
+            leaf = self.nodes[idx].next;
+            start = 0;
+        }
+        keys
+    }
+
+    // iterate over the keys in order along the leaf chain
+    pub fn iter(&self) -> PlusIter<'_, T> {
+        PlusIter {
+            nodes: &self.nodes,
+            leaf: Some(self.first_leaf()),
+            pos: 0,
+        }
+    }
+
+    pub fn print_inorder(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    // one node at a time, a node's keys before its children
+    pub fn print_preorder(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+        let mut stack = vec![self.root];
+        while let Some(idx) = stack.pop() {
+            print!(" {} ", label(&self.nodes[idx].keys));
+            stack.extend(self.nodes[idx].children.iter().rev());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+        let mut queue = VecDeque::new();
+        queue.push_back(self.root);
+        while let Some(idx) = queue.pop_front() {
+            print!(" {} ", label(&self.nodes[idx].keys));
+            queue.extend(self.nodes[idx].children.iter());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        let root = if self.count == 0 { None } else { Some(self.root) };
+        Shape::build(root, false, |idx: &usize| {
+            let node = &self.nodes[*idx];
+            (node.keys.clone(), Vec::new(), node.children.iter().map(|child| Some(*child)).collect())
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.is_empty() {
+            println!("None");
+            return;
+        }
+
+        fn pretty_print<T: Display>(nodes: &[PlusNode<T>], idx: usize, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&label(&nodes[idx].keys));
+            buffer.push('\n');
+            let children = &nodes[idx].children;
+            for (i, child) in children.iter().enumerate() {
+                let (branch, indent) = if i + 1 < children.len() { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(nodes, *child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(&self.nodes, self.root, &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for BPlusTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct PlusIter<'a, T> {
+    nodes: &'a [PlusNode<T>],
+    leaf: Option<usize>,
+    pos: usize,
+}
+
+impl<T: Copy> Iterator for PlusIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let idx = self.leaf?;
+            if let Some(key) = self.nodes[idx].keys.get(self.pos) {
+                self.pos += 1;
+                return Some(*key);
+            }
+            self.leaf = self.nodes[idx].next;
+            self.pos = 0;
+        }
+    }
+}
+
+impl<T, C> fmt::Display for BPlusTree<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BPlusTree")
+         .field("root", &self.root)
+         .field("nodes", &self.nodes)
+         .field("order", &self.order)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+
+// check key order, node fill and leaf depth, returns the subtree's keys in order
+#[cfg(test)]
+fn check_btree(node: &Node<u32>, order: usize, is_root: bool, depth: u32, leaf_depth: &mut Option<u32>) -> Vec<u32> {
+    assert!(node.keys.len() < order);
+    if !is_root {
+        assert!(node.keys.len() >= order.div_ceil(2) - 1);
+    }
+    if node.is_leaf() {
+        assert_eq!(*leaf_depth.get_or_insert(depth), depth);
+        return node.keys.clone();
+    }
+    assert_eq!(node.children.len(), node.keys.len() + 1);
+    let mut keys = Vec::new();
+    for (i, child) in node.children.iter().enumerate() {
+        let child_keys = check_btree(child, order, false, depth + 1, leaf_depth);
+        if i > 0 {
+            assert!(child_keys[0] > node.keys[i - 1]);
+        }
+        if i < node.keys.len() {
+            assert!(*child_keys.last().unwrap() < node.keys[i]);
+        }
+        keys.extend(child_keys);
+        if i < node.keys.len() {
+            keys.push(node.keys[i]);
+        }
+    }
+    keys
+}
+
+// check separators, node fill, leaf depth and that the leaf chain visits the leaves in order
+#[cfg(test)]
+fn check_bplustree(tree: &BPlusTree<u32>) {
+    fn walk(tree: &BPlusTree<u32>, idx: usize, depth: u32, leaves: &mut Vec<(usize, u32)>) -> Vec<u32> {
+        let node = &tree.nodes[idx];
+        assert!(node.keys.len() < tree.order);
+        if node.is_leaf() {
+            if idx != tree.root {
+                assert!(node.keys.len() >= tree.order / 2);
+            }
+            leaves.push((idx, depth));
+            return node.keys.clone();
+        }
+        if idx != tree.root {
+            assert!(node.children.len() >= tree.order.div_ceil(2));
+        }
+        assert_eq!(node.children.len(), node.keys.len() + 1);
+        let mut keys = Vec::new();
+        for (i, child) in node.children.iter().enumerate() {
+            let child_keys = walk(tree, *child, depth + 1, leaves);
+            if i > 0 {
+                assert!(child_keys[0] >= node.keys[i - 1]);
+            }
+            if i < node.keys.len() {
+                assert!(*child_keys.last().unwrap() < node.keys[i]);
+            }
+            keys.extend(child_keys);
+        }
+        keys
+    }
+
+    let mut leaves = Vec::new();
+    let keys = walk(tree, tree.root, 0, &mut leaves);
+    assert!(leaves.iter().all(|(_, depth)| *depth == leaves[0].1));
+    let mut chain = Vec::new();
+    let mut leaf = Some(tree.first_leaf());
+    while let Some(idx) = leaf {
+        chain.push(idx);
+        leaf = tree.nodes[idx].next;
+    }
+    assert_eq!(chain, leaves.iter().map(|(idx, _)| *idx).collect::<Vec<usize>>());
+    assert_eq!(keys, tree.iter().collect::<Vec<u32>>());
+    assert_eq!(keys.len(), tree.count());
+}
+
+#[test]
+pub fn create_empty_btrees() {
+    let btree: BTree<u32> = BTree::new();
+    let bplus: BPlusTree<u32> = BPlusTree::new();
+    assert!(btree.is_empty() && bplus.is_empty());
+    assert_eq!(btree.height() + bplus.height(), 0);
+    assert_eq!(btree.first(), None);
+    assert_eq!(bplus.last(), None);
+    assert_eq!(bplus.range(0, 10), vec![]);
+}
+
+#[test]
+pub fn test_btree_split_root() {
+    let mut tree = BTree::with_order(3);
+    for key in [10, 20, 30] {
+        tree.insert(key);
+    }
+    // the third key overflows the root, the middle one moves up
+    assert_eq!(tree.root.keys, vec![20]);
+    assert_eq!(tree.height(), 2);
+    assert_eq!(tree.leaves(), 2);
+    tree.insert(20);
+    assert_eq!(tree.count(), 3);
+    tree.delete(10);
+    // the root merged back into a single leaf
+    assert_eq!(tree.height(), 1);
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), vec![20, 30]);
+}
+
+#[test]
+pub fn test_btree_random() {
+    for order in [3, 4, 5, 8] {
+        let mut tree = BTree::with_order(order);
+        let mut keys = std::collections::BTreeSet::new();
+        let mut rng = crate::rng::Rng::new(order as u64);
+        for _ in 0..2000 {
+            let key = rng.below(400) as u32;
+            if rng.below(3) == 0 {
+                tree.delete(key);
+                keys.remove(&key);
+            } else {
+                tree.insert(key);
+                keys.insert(key);
+            }
+            assert_eq!(tree.count(), keys.len());
+        }
+        let checked = check_btree(&tree.root, order, true, 0, &mut None);
+        assert_eq!(checked, keys.iter().copied().collect::<Vec<u32>>());
+        assert_eq!(tree.range(100, 200), keys.range(100..200).copied().collect::<Vec<u32>>());
+        assert_eq!(tree.first(), keys.first().copied());
+        assert_eq!(tree.last(), keys.last().copied());
+        for key in 0..400 {
+            assert_eq!(tree.contains(&key), keys.contains(&key));
+        }
+    }
+}
+
+#[test]
+pub fn test_bplustree_random() {
+    for order in [3, 4, 5, 8] {
+        let mut tree = BPlusTree::with_order(order);
+        let mut keys = std::collections::BTreeSet::new();
+        let mut rng = crate::rng::Rng::new(order as u64 + 100);
+        for i in 0..2000u32 {
+            let key = rng.below(400) as u32;
+            if rng.below(3) == 0 {
+                tree.delete(key);
+                keys.remove(&key);
+            } else {
+                tree.insert(key);
+                keys.insert(key);
+            }
+            if i.is_multiple_of(100) {
+                check_bplustree(&tree);
+            }
+        }
+        check_bplustree(&tree);
+        assert_eq!(tree.range(100, 200), keys.range(100..200).copied().collect::<Vec<u32>>());
+        assert_eq!(tree.first(), keys.first().copied());
+        assert_eq!(tree.last(), keys.last().copied());
+        for key in 0..400 {
+            assert_eq!(tree.contains(&key), keys.contains(&key));
+        }
+        // deleting everything shrinks back to one empty leaf and reuses the freed slots
+        for key in keys.iter() {
+            tree.delete(*key);
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.nodes.len() - tree.free.len(), 1);
+    }
+}
+
+#[test]
+pub fn test_bplustree_range_crosses_leaves() {
+    let mut tree = BPlusTree::with_order(4);
+    for key in (0..100).rev() {
+        tree.insert(key * 2);
+    }
+    check_bplustree(&tree);
+    assert!(tree.leaves() > 10);
+    assert_eq!(tree.range(11, 31), vec![12, 14, 16, 18, 20, 22, 24, 26, 28, 30]);
+    assert_eq!(tree.range(190, 1000), vec![190, 192, 194, 196, 198]);
+    assert_eq!(tree.range(50, 50), vec![]);
+}
+
+#[test]
+pub fn test_btrees_reverse_comparator() {
+    let mut btree = BTree::with_comparator_and_order(crate::compare::Reverse, 3);
+    let mut bplus = BPlusTree::with_comparator_and_order(crate::compare::Reverse, 3);
+    for key in 0..20 {
+        btree.insert(key);
+        bplus.insert(key);
+    }
+    assert_eq!(btree.iter().collect::<Vec<u32>>(), (0..20).rev().collect::<Vec<u32>>());
+    assert_eq!(bplus.iter().collect::<Vec<u32>>(), (0..20).rev().collect::<Vec<u32>>());
+    assert_eq!(btree.range(15, 10), vec![15, 14, 13, 12, 11]);
+    assert_eq!(bplus.range(15, 10), vec![15, 14, 13, 12, 11]);
+    assert_eq!(btree.first(), Some(19));
+}
+
+#[test]
+#[should_panic]
+pub fn test_btree_order_too_small() {
+    let _tree: BTree<u32> = BTree::with_order(2);
+}
+
+#[test]
+pub fn test_shape() {
+    let mut btree = BTree::with_order(3);
+    let mut bplus = BPlusTree::with_order(3);
+    for key in 1..=5 {
+        btree.insert(key);
+        bplus.insert(key);
+    }
+    let shape = btree.shape();
+    assert!(!shape.binary);
+    assert_eq!(shape.nodes.iter().map(|node| node.keys.len()).sum::<usize>(), 5);
+    assert_eq!(shape.root().unwrap().children.len(), shape.root().unwrap().keys.len() + 1);
+    // a B+tree repeats separators above its leaves, the leaves alone hold every key once
+    let shape = bplus.shape();
+    let leaf_keys: Vec<u32> = shape.nodes.iter().filter(|node| node.children.is_empty()).flat_map(|node| node.keys.clone()).collect();
+    assert_eq!(leaf_keys, vec![1, 2, 3, 4, 5]);
+    assert!(BTree::<u32>::new().shape().root().is_none());
+}
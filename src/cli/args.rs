@@ -0,0 +1,153 @@
+// how command results are printed, sentences for people or one JSON object per command for programs
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+// the type keys are parsed as, every tree in the CLI is built over one of these
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum KeyType {
+    #[default]
+    U32,
+    I64,
+    Str,
+    F64,
+}
+
+// the order stress inserts and deletes keys in, see cli::stress::generate
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Workload {
+    #[default]
+    Random,
+    Sorted,
+    Reverse,
+    Zipfian,
+    Sawtooth,
+}
+
+// what the command line asked for: the tree to run, a file of keys to start it from and, for batch
+// mode, where to read commands from
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub tree: String,
+    pub load: Option<String>,
+    // draw the tree full screen and animate its steps instead of showing the menu
+    pub tui: bool,
+    // only for stress
+    pub workload: Workload,
+    pub size: Option<usize>,
+    pub seed: Option<u64>,
+    pub check_every: Option<usize>,
+    // percent of operations that insert a key already there or delete one that is not
+    pub redundant: Option<u64>,
+    pub script: Option<String>,
+    pub format: Format,
+    pub key_type: KeyType,
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--script" => {
+                    // no path means the commands come from stdin
+                    let path = args.next_if(|next| !next.starts_with("--"));
+                    options.script = Some(path.unwrap_or_else(|| "-".to_string()));
+                }
+                "--load" => {
+                    options.load = Some(args.next().ok_or("--load takes a path")?);
+                }
+                "--format" => {
+                    options.format = match args.next().as_deref() {
+                        Some("text") => Format::Text,
+                        Some("json") => Format::Json,
+                        _ => return Err("--format takes text or json".to_string()),
+                    };
+                }
+                "--key-type" => {
+                    options.key_type = match args.next().as_deref() {
+                        Some("u32") => KeyType::U32,
+                        Some("i64") => KeyType::I64,
+                        Some("string") => KeyType::Str,
+                        Some("f64") => KeyType::F64,
+                        _ => return Err("--key-type takes u32, i64, string or f64".to_string()),
+                    };
+                }
+                "--workload" => {
+                    options.workload = match args.next().as_deref() {
+                        Some("random") => Workload::Random,
+                        Some("sorted") => Workload::Sorted,
+                        Some("reverse") => Workload::Reverse,
+                        Some("zipfian") => Workload::Zipfian,
+                        Some("sawtooth") => Workload::Sawtooth,
+                        _ => return Err("--workload takes random, sorted, reverse, zipfian or sawtooth".to_string()),
+                    };
+                }
+                "--size" => options.size = Some(number(&arg, args.next())?),
+                "--seed" => options.seed = Some(number(&arg, args.next())?),
+                "--check-every" => options.check_every = Some(number(&arg, args.next())?),
+                "--redundant" => options.redundant = Some(number(&arg, args.next())?),
+                "tui" if !options.tui => options.tui = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ if options.tree.is_empty() => options.tree = arg,
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+        if options.tui && (options.script.is_some() || options.format == Format::Json) {
+            return Err("tui reads commands from the terminal and only prints text".to_string());
+        }
+        let stress_only = options.workload != Workload::default() || options.size.is_some() || options.seed.is_some()
+            || options.check_every.is_some() || options.redundant.is_some();
+        if stress_only && options.tree != "stress" {
+            return Err("--workload, --size, --seed, --check-every and --redundant only apply to stress".to_string());
+        }
+        if options.size == Some(0) || options.check_every == Some(0) {
+            return Err("--size and --check-every have to be above 0".to_string());
+        }
+        if options.redundant.is_some_and(|percent| percent > 100) {
+            return Err("--redundant takes a percentage from 0 to 100".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn number<N: std::str::FromStr>(option: &str, value: Option<String>) -> Result<N, String> {
+    value.and_then(|value| value.parse().ok()).ok_or(format!("{} takes a whole number", option))
+}
+
+#[cfg(test)]
+fn parse(line: &str) -> Result<Options, String> {
+    Options::parse(line.split_whitespace().map(String::from))
+}
+
+#[test]
+pub fn test_parse_options() {
+    assert_eq!(parse("rb"), Ok(Options { tree: "rb".to_string(), ..Options::default() }));
+    assert_eq!(parse("avl --script ops.txt"), Ok(Options { tree: "avl".to_string(), script: Some("ops.txt".to_string()), ..Options::default() }));
+    assert_eq!(parse("--script - splay"), Ok(Options { tree: "splay".to_string(), script: Some("-".to_string()), ..Options::default() }));
+    assert_eq!(parse("bst --script"), Ok(Options { tree: "bst".to_string(), script: Some("-".to_string()), ..Options::default() }));
+    assert_eq!(parse("rb --format json").map(|options| options.format), Ok(Format::Json));
+    assert_eq!(parse("rb --script --format json"), Ok(Options { tree: "rb".to_string(), script: Some("-".to_string()), format: Format::Json, ..Options::default() }));
+    assert_eq!(parse("avl --key-type string").map(|options| options.key_type), Ok(KeyType::Str));
+    assert_eq!(parse("avl --key-type f64 --format json").map(|options| options.key_type), Ok(KeyType::F64));
+    assert_eq!(parse("bst --load tree.json --script").map(|options| options.load), Ok(Some("tree.json".to_string())));
+    assert!(parse("bst --load").is_err());
+    assert_eq!(parse("tui rb"), Ok(Options { tree: "rb".to_string(), tui: true, ..Options::default() }));
+    assert!(parse("tui rb --script ops.txt").is_err());
+    let stress = parse("stress --workload zipfian --size 500 --seed 7 --check-every 50").unwrap();
+    assert_eq!((stress.workload, stress.size, stress.seed, stress.check_every), (Workload::Zipfian, Some(500), Some(7), Some(50)));
+    assert!(parse("stress --size lots").is_err());
+    assert!(parse("stress --size 0").is_err());
+    assert!(parse("rb --workload sorted").is_err());
+    assert_eq!(parse("stress --redundant 20").unwrap().redundant, Some(20));
+    assert!(parse("stress --redundant 101").is_err());
+    assert!(parse("avl --redundant 5").is_err());
+    assert!(parse("avl --key-type u8").is_err());
+    assert!(parse("rb --format xml").is_err());
+    assert!(parse("rb --verbose").is_err());
+    assert!(parse("rb avl").is_err());
+}
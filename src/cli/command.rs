@@ -0,0 +1,311 @@
+use std::fmt;
+use crate::MenuTree;
+use crate::cli::args::Format;
+use crate::cli::json;
+use crate::cli::key::Key;
+use crate::cli::snapshot;
+
+// one action on the tree, picked from the numbered menu or read from a line of a script
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command<K> {
+    Insert(K),
+    Delete(K),
+    Leaves,
+    Height,
+    Inorder,
+    IsEmpty,
+    Print,
+    Count,
+    Search(K),
+    Min,
+    Max,
+    Preorder,
+    Levelorder,
+    Debug,
+    Load(String),
+    Save(String),
+    Undo,
+    Redo,
+    History,
+    Replay,
+    Quit,
+}
+
+impl<K: Key> Command<K> {
+    // a script line such as "insert 5", blank lines and lines starting with # are skipped as None.
+    // keys are single words, so a string key cannot contain spaces. a path is the rest of the line
+    pub fn parse(line: &str) -> Result<Option<Command<K>>, String> {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            None => return Ok(None),
+            Some(name) if name.starts_with('#') => return Ok(None),
+            Some(name) => name,
+        };
+        if name == "load" || name == "save" {
+            let path = line.trim()[name.len()..].trim();
+            if path.is_empty() {
+                return Err(format!("`{}` needs a path", name));
+            }
+            let path = path.to_string();
+            return Ok(Some(if name == "load" { Command::Load(path) } else { Command::Save(path) }));
+        }
+        let command = match name {
+            "insert" => Command::Insert(parse_key(name, words.next())?),
+            "delete" => Command::Delete(parse_key(name, words.next())?),
+            "search" => Command::Search(parse_key(name, words.next())?),
+            "leaves" => Command::Leaves,
+            "height" => Command::Height,
+            "inorder" => Command::Inorder,
+            "empty" => Command::IsEmpty,
+            "print" => Command::Print,
+            "count" => Command::Count,
+            "min" => Command::Min,
+            "max" => Command::Max,
+            "preorder" => Command::Preorder,
+            "levelorder" => Command::Levelorder,
+            "debug" => Command::Debug,
+            "undo" => Command::Undo,
+            "redo" => Command::Redo,
+            "history" => Command::History,
+            "replay" => Command::Replay,
+            "quit" | "exit" => Command::Quit,
+            _ => return Err(format!("unknown command `{}`", name)),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("unexpected `{}` after `{}`", extra, name)),
+            None => Ok(Some(command)),
+        }
+    }
+
+    // the word a script uses for the command, also the "op" of its JSON output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Insert(_) => "insert",
+            Command::Delete(_) => "delete",
+            Command::Leaves => "leaves",
+            Command::Height => "height",
+            Command::Inorder => "inorder",
+            Command::IsEmpty => "empty",
+            Command::Print => "print",
+            Command::Count => "count",
+            Command::Search(_) => "search",
+            Command::Min => "min",
+            Command::Max => "max",
+            Command::Preorder => "preorder",
+            Command::Levelorder => "levelorder",
+            Command::Debug => "debug",
+            Command::Load(_) => "load",
+            Command::Save(_) => "save",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::History => "history",
+            Command::Replay => "replay",
+            Command::Quit => "quit",
+        }
+    }
+
+    // insert, delete and search change or look at one key, everything else reports on the whole tree
+    pub fn prints_output(&self) -> bool {
+        !matches!(self, Command::Insert(_) | Command::Delete(_) | Command::Quit)
+    }
+
+    // the commands a session records, undoes and replays
+    pub fn changes_tree(&self) -> bool {
+        matches!(self, Command::Insert(_) | Command::Delete(_) | Command::Load(_))
+    }
+}
+
+// the command as a script line that parses back to it
+impl<K: Key> fmt::Display for Command<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Insert(key) | Command::Delete(key) | Command::Search(key) => write!(f, "{} {}", self.name(), key),
+            Command::Load(path) | Command::Save(path) => write!(f, "{} {}", self.name(), path),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+fn parse_key<K: Key>(name: &str, word: Option<&str>) -> Result<K, String> {
+    let word = word.ok_or(format!("`{}` needs a key", name))?;
+    K::parse_key(word).ok_or(format!("`{}` is not a valid key", word))
+}
+
+// carry out command on tree, printing whatever it reports in the chosen format. only load and save
+// can fail, when the file cannot be read or written
+pub fn run_command<K: Key, T: MenuTree<K>>(tree: &mut T, command: Command<K>, format: Format) -> Result<(), String> {
+    match format {
+        Format::Text => run_text(tree, command)?,
+        Format::Json => {
+            if command != Command::Quit {
+                println!("{}", run_json(tree, command)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_text<K: Key, T: MenuTree<K>>(tree: &mut T, command: Command<K>) -> Result<(), String> {
+    match command {
+        Command::Insert(key) => tree.insert(key),
+        Command::Delete(key) => tree.delete(key),
+        Command::Leaves => println!("There are {} leaf node(s) in the tree.", tree.leaves()),
+        Command::Height => println!("The height of the tree is {}", tree.height()),
+        Command::Inorder => tree.print_inorder(),
+        Command::IsEmpty => println!("Is tree empty? {}", tree.is_empty()),
+        Command::Print => tree.print_tree(),
+        Command::Count => println!("There are {} node(s) in the tree", tree.count()),
+        Command::Search(key) => println!("{:?}", tree.search(key)),
+        Command::Min => println!("{:?}", tree.min()),
+        Command::Max => println!("{:?}", tree.max()),
+        Command::Preorder => tree.print_preorder(),
+        Command::Levelorder => tree.print_levelorder(),
+        Command::Debug => println!("{:#?}", tree),
+        Command::Load(path) => println!("Loaded {} key(s) from {}", snapshot::load(tree, &path)?, path),
+        Command::Save(path) => println!("Saved {} key(s) to {}", snapshot::save(tree, &path)?, path),
+        Command::Undo | Command::Redo | Command::History | Command::Replay => return Err(no_history(&command)),
+        Command::Quit => {}
+    }
+    Ok(())
+}
+
+// one object per command, {"op":..,"key":..,"result":..} with key only for commands that take one and
+// path in place of key for load and save
+fn run_json<K: Key, T: MenuTree<K>>(tree: &mut T, command: Command<K>) -> Result<String, String> {
+    let result = match &command {
+        // checked first so the trees' own "already in tree" and "not found" messages stay out of the output
+        Command::Insert(key) => {
+            let inserted = !tree.contains(*key);
+            if inserted {
+                tree.insert(*key);
+            }
+            inserted.to_string()
+        }
+        Command::Delete(key) => {
+            let deleted = tree.contains(*key);
+            if deleted {
+                tree.delete(*key);
+            }
+            deleted.to_string()
+        }
+        Command::Leaves => tree.leaves().to_string(),
+        Command::Height => tree.height().to_string(),
+        Command::Inorder => json::keys(&tree.inorder().iter().collect::<Vec<&K>>()),
+        Command::IsEmpty => tree.is_empty().to_string(),
+        Command::Print => json::shape(&tree.shape()),
+        Command::Count => tree.count().to_string(),
+        Command::Search(key) => json::key(tree.search(*key).as_ref()),
+        Command::Min => json::key(tree.min().as_ref()),
+        Command::Max => json::key(tree.max().as_ref()),
+        Command::Preorder => json::keys(&tree.preorder().iter().collect::<Vec<&K>>()),
+        Command::Levelorder => json::keys(&tree.levelorder().iter().collect::<Vec<&K>>()),
+        Command::Debug => json::string(&format!("{:?}", tree)),
+        Command::Load(path) => snapshot::load(tree, path)?.to_string(),
+        Command::Save(path) => snapshot::save(tree, path)?.to_string(),
+        Command::Undo | Command::Redo | Command::History | Command::Replay => return Err(no_history(&command)),
+        Command::Quit => "null".to_string(),
+    };
+    Ok(json_object(&command, result))
+}
+
+// the object run_json prints for command, given its result as a JSON value
+pub fn json_object<K: Key>(command: &Command<K>, result: String) -> String {
+    let mut fields = vec![("op", json::string(command.name()))];
+    match command {
+        Command::Insert(key) | Command::Delete(key) | Command::Search(key) => fields.push(("key", key.to_json())),
+        Command::Load(path) | Command::Save(path) => fields.push(("path", json::string(path))),
+        _ => {}
+    }
+    fields.push(("result", result));
+    json::object(&fields)
+}
+
+// undo, redo, history and replay go through a Session, a bare tree has no history to use
+pub fn no_history<K: Key>(command: &Command<K>) -> String {
+    format!("`{}` needs the history of a session", command.name())
+}
+
+#[test]
+pub fn test_parse_commands() {
+    assert_eq!(Command::<u32>::parse("insert 5"), Ok(Some(Command::Insert(5))));
+    assert_eq!(Command::<u32>::parse("  delete   12 "), Ok(Some(Command::Delete(12))));
+    assert_eq!(Command::<u32>::parse("height"), Ok(Some(Command::Height)));
+    assert_eq!(Command::<u32>::parse("exit"), Ok(Some(Command::Quit)));
+    assert_eq!(Command::<u32>::parse(""), Ok(None));
+    assert_eq!(Command::<u32>::parse("# build a small tree"), Ok(None));
+}
+
+#[test]
+pub fn test_parse_errors() {
+    assert!(Command::<u32>::parse("insert").is_err());
+    assert!(Command::<u32>::parse("insert five").is_err());
+    assert!(Command::<u32>::parse("insert -1").is_err());
+    assert!(Command::<u32>::parse("height 3").is_err());
+    assert!(Command::<u32>::parse("rotate 3").is_err());
+    assert!(Command::<u32>::parse("insert 2.5").is_err());
+}
+
+#[test]
+pub fn test_parse_paths() {
+    assert_eq!(Command::<u32>::parse("load keys.txt"), Ok(Some(Command::Load("keys.txt".to_string()))));
+    assert_eq!(Command::<u32>::parse("  save my trees/out.json "), Ok(Some(Command::Save("my trees/out.json".to_string()))));
+    assert!(Command::<u32>::parse("load").is_err());
+    assert!(Command::<u32>::parse("save   ").is_err());
+}
+
+#[test]
+pub fn test_parse_other_key_types() {
+    assert_eq!(Command::<i64>::parse("insert -3"), Ok(Some(Command::Insert(-3))));
+    assert_eq!(Command::<f64>::parse("search 2.5"), Ok(Some(Command::Search(2.5))));
+    assert_eq!(Command::<&str>::parse("delete pear"), Ok(Some(Command::Delete("pear"))));
+    assert!(Command::<&str>::parse("insert two words").is_err());
+}
+
+#[test]
+pub fn test_names_parse_back() {
+    for command in [Command::Insert(1u32), Command::Delete(2), Command::Search(3), Command::Leaves, Command::Height,
+                    Command::Inorder, Command::IsEmpty, Command::Print, Command::Count, Command::Min, Command::Max,
+                    Command::Preorder, Command::Levelorder, Command::Debug, Command::Load("keys.txt".to_string()),
+                    Command::Save("out.json".to_string()), Command::Undo, Command::Redo, Command::History,
+                    Command::Replay, Command::Quit] {
+        assert_eq!(Command::parse(&command.to_string()), Ok(Some(command)));
+    }
+}
+
+#[test]
+pub fn test_json_output() {
+    let mut tree = Rust_Trees::avltree::AvlTree::<u32>::new();
+    assert_eq!(run_json(&mut tree, Command::Insert(5)).unwrap(), "{\"op\":\"insert\",\"key\":5,\"result\":true}");
+    assert_eq!(run_json(&mut tree, Command::Insert(5)).unwrap(), "{\"op\":\"insert\",\"key\":5,\"result\":false}");
+    run_json(&mut tree, Command::Insert(7)).unwrap();
+    assert_eq!(run_json(&mut tree, Command::Height).unwrap(), "{\"op\":\"height\",\"result\":2}");
+    assert_eq!(run_json(&mut tree, Command::Inorder).unwrap(), "{\"op\":\"inorder\",\"result\":[5,7]}");
+    assert_eq!(run_json(&mut tree, Command::Search(6)).unwrap(), "{\"op\":\"search\",\"key\":6,\"result\":null}");
+    assert_eq!(run_json(&mut tree, Command::Print).unwrap(),
+        "{\"op\":\"print\",\"result\":{\"key\":5,\"height\":2,\"left\":null,\"right\":{\"key\":7,\"height\":1,\"left\":null,\"right\":null}}}");
+    assert_eq!(run_json(&mut tree, Command::Delete(9)).unwrap(), "{\"op\":\"delete\",\"key\":9,\"result\":false}");
+
+    let mut words = Rust_Trees::rbtree::RBTree::<&str>::new();
+    run_json(&mut words, Command::Insert("pear")).unwrap();
+    run_json(&mut words, Command::Insert("apple")).unwrap();
+    assert_eq!(run_json(&mut words, Command::Inorder).unwrap(), "{\"op\":\"inorder\",\"result\":[\"apple\",\"pear\"]}");
+    assert_eq!(run_json(&mut words, Command::Search("fig")).unwrap(), "{\"op\":\"search\",\"key\":\"fig\",\"result\":null}");
+}
+
+#[test]
+pub fn test_json_output_bplus() {
+    let mut tree = Rust_Trees::btree::BPlusTree::<u32>::with_order(3);
+    for key in 1..=9 {
+        run_json(&mut tree, Command::Insert(key)).unwrap();
+    }
+    for key in [3, 4, 7] {
+        run_json(&mut tree, Command::Delete(key)).unwrap();
+    }
+    // separators copied from deleted keys stay in the internal nodes but are not listed as keys
+    assert!(tree.shape().preorder().contains(&&7));
+    let keys = "[1,2,5,6,8,9]";
+    for command in [Command::Inorder, Command::Preorder, Command::Levelorder] {
+        let expected = format!("{{\"op\":\"{}\",\"result\":{}}}", command.name(), keys);
+        assert_eq!(run_json(&mut tree, command).unwrap(), expected);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use Rust_Trees::avltree::AvlTree;
+use Rust_Trees::bst::BST;
+use Rust_Trees::compare::Comparator;
+use Rust_Trees::rbtree::RBTree;
+use crate::MenuTree;
+use crate::cli::args::{Format, Options};
+use crate::cli::command::{Command, run_command};
+use crate::cli::key::Key;
+use crate::cli::snapshot;
+
+// the same keys in the three trees, to see how each one balances them
+pub struct Trees<K: Key, C> {
+    rb: RBTree<K, C>,
+    avl: AvlTree<K, C>,
+    bst: BST<K, C>,
+}
+
+impl<K: Key, C: Comparator<K>+Debug+Clone> Trees<K, C> {
+    pub fn new(cmp: C) -> Self {
+        Trees {
+            rb: RBTree::with_comparator(cmp.clone()),
+            avl: AvlTree::with_comparator(cmp.clone()),
+            bst: BST::with_comparator(cmp),
+        }
+    }
+
+    // insert, delete and load go to all three trees and redraw them. save writes the BST, whose
+    // pre-order rebuilds it exactly, and every other command reports on each tree in turn
+    pub fn run(&mut self, command: Command<K>) -> Result<(), String> {
+        match &command {
+            Command::Insert(_) | Command::Delete(_) => {
+                run_command(&mut self.rb, command.clone(), Format::Text)?;
+                run_command(&mut self.avl, command.clone(), Format::Text)?;
+                run_command(&mut self.bst, command, Format::Text)?;
+                println!("{}", self.draw());
+            }
+            Command::Load(path) => {
+                let loaded = snapshot::load(&mut self.rb, path)?;
+                snapshot::load(&mut self.avl, path)?;
+                snapshot::load(&mut self.bst, path)?;
+                println!("Loaded {} key(s) from {}", loaded, path);
+                println!("{}", self.draw());
+            }
+            Command::Save(path) => println!("Saved {} key(s) to {}", snapshot::save(&self.bst, path)?, path),
+            Command::Print => println!("{}", self.draw()),
+            Command::Undo | Command::Redo | Command::History | Command::Replay => {
+                return Err(format!("compare mode keeps no history for `{}`", command.name()));
+            }
+            Command::Quit => {}
+            _ => {
+                println!("Red Black Tree:");
+                run_command(&mut self.rb, command.clone(), Format::Text)?;
+                println!("AVL Tree:");
+                run_command(&mut self.avl, command.clone(), Format::Text)?;
+                println!("Binary Search Tree:");
+                run_command(&mut self.bst, command, Format::Text)?;
+            }
+        }
+        Ok(())
+    }
+
+    // the three trees next to each other, each headed by its height, leaf count and rotations so far
+    pub fn draw(&self) -> String {
+        // a plain BST never rotates
+        let columns = [
+            column("Red Black Tree", &self.rb, self.rb.rotations()),
+            column("AVL Tree", &self.avl, self.avl.rotations()),
+            column("Binary Search Tree", &self.bst, 0),
+        ];
+        let widths: Vec<usize> = columns.iter()
+            .map(|lines| lines.iter().map(|line| line.chars().count()).max().unwrap_or(0))
+            .collect();
+        let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+        let mut out = String::new();
+        for row in 0..rows {
+            let mut line = String::new();
+            for (lines, width) in columns.iter().zip(&widths) {
+                let cell = lines.get(row).map(String::as_str).unwrap_or("");
+                line.push_str(cell);
+                line.push_str(&" ".repeat(width - cell.chars().count() + 4));
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn column<K: Key, T: MenuTree<K>>(name: &str, tree: &T, rotations: u64) -> Vec<String> {
+    let mut lines = vec![
+        name.to_string(),
+        format!("height {}, leaves {}, rotations {}", tree.height(), tree.leaves(), rotations),
+        String::new(),
+    ];
+    let shape = tree.shape().lines();
+    if shape.is_empty() {
+        lines.push("(empty)".to_string());
+    }
+    lines.extend(shape);
+    lines
+}
+
+// read commands from the --script file, or from stdin with a prompt, and run each on all three trees
+pub fn run<K: Key, C: Comparator<K>+Debug+Clone>(cmp: C, options: &Options) {
+    if options.format == Format::Json {
+        eprintln!("compare only prints text");
+        std::process::exit(1);
+    }
+    let mut trees = Trees::new(cmp);
+    if let Some(path) = &options.load {
+        if let Err(message) = trees.run(Command::Load(path.clone())) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    }
+    let result = match options.script.as_deref() {
+        Some("-") => run_lines(&mut trees, io::stdin().lock(), false),
+        Some(path) => match File::open(path) {
+            Ok(file) => run_lines(&mut trees, BufReader::new(file), false),
+            Err(err) => Err(format!("could not open {}: {}", path, err)),
+        },
+        None => {
+            println!("Comparing a Red Black Tree, an AVL Tree and a Binary Search Tree.");
+            println!("Enter commands such as insert 5, delete 5, height, print or quit.");
+            run_lines(&mut trees, io::stdin().lock(), true)
+        }
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+}
+
+// a script stops at its first bad line, interactively the line is reported and the next one read
+fn run_lines<K: Key, C: Comparator<K>+Debug+Clone, R: BufRead>(trees: &mut Trees<K, C>, input: R, interactive: bool) -> Result<(), String> {
+    let prompt = || {
+        if interactive {
+            print!("> ");
+            io::stdout().flush().ok();
+        }
+    };
+    prompt();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.map_err(|err| format!("could not read line {}: {}", number + 1, err))?;
+        let result = match Command::<K>::parse(&line) {
+            Ok(Some(Command::Quit)) => break,
+            Ok(Some(command)) => trees.run(command),
+            Ok(None) => Ok(()),
+            Err(message) => Err(message),
+        };
+        match result {
+            Err(message) if interactive => println!("{}", message),
+            Err(message) => return Err(format!("line {}: {}", number + 1, message)),
+            Ok(()) => {}
+        }
+        prompt();
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_draw() {
+    let mut trees = Trees::new(Rust_Trees::compare::Natural);
+    for key in [1u32, 2, 3] {
+        trees.rb.insert(key);
+        trees.avl.insert(key);
+        trees.bst.insert(key);
+    }
+    assert_eq!(trees.draw(), [
+        "Red Black Tree                     AVL Tree                           Binary Search Tree",
+        "height 2, leaves 2, rotations 1    height 2, leaves 2, rotations 1    height 3, leaves 1, rotations 0",
+        "",
+        "2 (black)                          2 (height=2)                       1",
+        "├── 1 (red)                        ├── 1 (height=1)                   ├── ·",
+        "└── 3 (red)                        └── 3 (height=1)                   └── 2",
+        "                                                                          ├── ·",
+        "                                                                          └── 3",
+        "",
+    ].join("\n"));
+}
@@ -0,0 +1,392 @@
+use Rust_Trees::shape::{Attr, Shape};
+
+// just enough JSON writing for one object per command, the crate has no dependencies to pull serde from
+
+// how a key is written out
+pub trait ToJson {
+    fn to_json(&self) -> String;
+}
+
+impl ToJson for u32 {
+    fn to_json(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToJson for i64 {
+    fn to_json(&self) -> String {
+        self.to_string()
+    }
+}
+
+// JSON has no NaN or infinities, those are written as the strings "NaN", "inf" and "-inf"
+impl ToJson for f64 {
+    fn to_json(&self) -> String {
+        if self.is_finite() {
+            self.to_string()
+        } else {
+            string(&self.to_string())
+        }
+    }
+}
+
+impl ToJson for &str {
+    fn to_json(&self) -> String {
+        string(self)
+    }
+}
+
+pub fn string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn key<K: ToJson>(key: Option<&K>) -> String {
+    match key {
+        Some(key) => key.to_json(),
+        None => "null".to_string(),
+    }
+}
+
+pub fn keys<K: ToJson>(keys: &[&K]) -> String {
+    let items: Vec<String> = keys.iter().map(|key| key.to_json()).collect();
+    format!("[{}]", items.join(","))
+}
+
+// fields are written in the order given, their values already encoded
+pub fn object(fields: &[(&str, String)]) -> String {
+    let items: Vec<String> = fields.iter().map(|(name, value)| format!("{}:{}", string(name), value)).collect();
+    format!("{{{}}}", items.join(","))
+}
+
+fn attr(attr: &Attr) -> String {
+    match attr {
+        Attr::Text(text) => string(text),
+        Attr::Number(number) => number.to_string(),
+    }
+}
+
+enum Step {
+    Node(Option<usize>),
+    Text(&'static str),
+}
+
+// the tree as nested objects, {"key":..,"left":..,"right":..} for a binary tree and {"keys":[..],
+// "children":[..]} otherwise, with the node's attributes alongside. an explicit stack instead of
+// recursion, a plain BST can be a very long path
+pub fn shape<K: ToJson>(shape: &Shape<K>) -> String {
+    let mut out = String::new();
+    let mut stack = vec![Step::Node(if shape.nodes.is_empty() { None } else { Some(0) })];
+    while let Some(step) = stack.pop() {
+        let index = match step {
+            Step::Text(text) => {
+                out.push_str(text);
+                continue;
+            }
+            Step::Node(None) => {
+                out.push_str("null");
+                continue;
+            }
+            Step::Node(Some(index)) => index,
+        };
+        let node = &shape.nodes[index];
+        out.push('{');
+        if shape.binary {
+            out.push_str(&format!("\"key\":{}", node.keys[0].to_json()));
+        } else {
+            out.push_str(&format!("\"keys\":{}", keys(&node.keys.iter().collect::<Vec<&K>>())));
+        }
+        for (name, value) in &node.attrs {
+            out.push_str(&format!(",{}:{}", string(name), attr(value)));
+        }
+        // pushed in reverse, the stack pops them back in order
+        if shape.binary {
+            stack.push(Step::Text("}"));
+            stack.push(Step::Node(node.children[1]));
+            stack.push(Step::Text(",\"right\":"));
+            stack.push(Step::Node(node.children[0]));
+            stack.push(Step::Text(",\"left\":"));
+        } else {
+            stack.push(Step::Text("]}"));
+            for (i, child) in node.children.iter().enumerate().rev() {
+                stack.push(Step::Node(*child));
+                if i > 0 {
+                    stack.push(Step::Text(","));
+                }
+            }
+            stack.push(Step::Text(",\"children\":["));
+        }
+    }
+    out
+}
+
+#[test]
+pub fn test_json_string() {
+    assert_eq!(string("plain"), "\"plain\"");
+    assert_eq!(string("say \"hi\"\\\n"), "\"say \\\"hi\\\"\\\\\\n\"");
+    assert_eq!(string("\u{1}"), "\"\\u0001\"");
+}
+
+#[test]
+pub fn test_json_object() {
+    assert_eq!(object(&[("op", string("height")), ("result", 4.to_string())]), "{\"op\":\"height\",\"result\":4}");
+    assert_eq!(keys(&[&1u32, &2, &3]), "[1,2,3]");
+    assert_eq!(key::<u32>(None), "null");
+    assert_eq!(keys(&[&-1i64, &2]), "[-1,2]");
+    assert_eq!(keys(&[&0.5f64, &f64::NAN, &f64::NEG_INFINITY]), "[0.5,\"NaN\",\"-inf\"]");
+    assert_eq!(keys(&[&"a", &"b\"c"]), "[\"a\",\"b\\\"c\"]");
+}
+
+#[test]
+pub fn test_json_shape() {
+    use Rust_Trees::rbtree::RBTree;
+    use Rust_Trees::btree::BTree;
+    let mut rb = RBTree::new();
+    for key in [2u32, 1, 3] {
+        rb.insert(key);
+    }
+    assert_eq!(shape(&rb.shape()),
+        "{\"key\":2,\"color\":\"black\",\"left\":{\"key\":1,\"color\":\"red\",\"left\":null,\"right\":null},\"right\":{\"key\":3,\"color\":\"red\",\"left\":null,\"right\":null}}");
+    let mut btree = BTree::with_order(3);
+    for key in [1u32, 2, 3] {
+        btree.insert(key);
+    }
+    assert_eq!(shape(&btree.shape()), "{\"keys\":[2],\"children\":[{\"keys\":[1],\"children\":[]},{\"keys\":[3],\"children\":[]}]}");
+    assert_eq!(shape(&RBTree::<u32>::new().shape()), "null");
+}
+
+// a parsed JSON value, numbers keep their text so integer keys come back exactly
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    Str(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+// read a whole document, enough of the grammar to load back what this module writes
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error("unexpected text after the value"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, what: &str) -> String {
+        format!("{} at character {}", what, self.pos + 1)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected `{}`", expected)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Value::Str(self.string()?)),
+            Some('t') => self.literal("true", Value::Bool(true)),
+            Some('f') => self.literal("false", Value::Bool(false)),
+            Some('n') => self.literal("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(self.number()),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                self.pos -= 1;
+                return Err(self.error(&format!("expected `{}`", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    // the digits, sign, point and exponent as written, the key type decides what they mean
+    fn number(&mut self) -> Value {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c)) {
+            self.pos += 1;
+        }
+        Value::Number(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(out),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("unknown escape")),
+                    };
+                    out.push(c);
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    // the four hex digits after \u, and the low half that follows a high surrogate
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or(self.error("invalid \\u escape"));
+        }
+        if self.next() != Some('\\') || self.next() != Some('u') {
+            return Err(self.error("unpaired surrogate"));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate"));
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or(self.error("invalid \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next().and_then(|c| c.to_digit(16)).ok_or(self.error("expected four hex digits"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            self.expect(':')?;
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Object(fields)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_json_parse() {
+    let value = parse(" {\"key_type\": \"u32\", \"keys\": [3, 1, -2.5e3], \"ok\": true, \"none\": null} ").unwrap();
+    assert_eq!(value.field("key_type"), Some(&Value::Str("u32".to_string())));
+    assert_eq!(value.field("keys"), Some(&Value::Array(vec![
+        Value::Number("3".to_string()), Value::Number("1".to_string()), Value::Number("-2.5e3".to_string()),
+    ])));
+    assert_eq!(value.field("ok"), Some(&Value::Bool(true)));
+    assert_eq!(value.field("none"), Some(&Value::Null));
+    assert_eq!(value.field("missing"), None);
+    assert_eq!(parse("[]"), Ok(Value::Array(Vec::new())));
+    assert_eq!(parse("{}"), Ok(Value::Object(Vec::new())));
+}
+
+#[test]
+pub fn test_json_parse_strings() {
+    // whatever string() writes has to read back the same
+    for text in ["plain", "say \"hi\"\\\n\t", "\u{1}", "caf\u{e9} \u{1f333}"] {
+        assert_eq!(parse(&string(text)), Ok(Value::Str(text.to_string())));
+    }
+    assert_eq!(parse("\"\\ud83c\\udf33\""), Ok(Value::Str("\u{1f333}".to_string())));
+}
+
+#[test]
+pub fn test_json_parse_errors() {
+    for text in ["", "[1, 2", "{\"a\" 1}", "[1,]", "tru", "\"open", "\"\\ud83c\"", "[1] 2", "{1: 2}"] {
+        assert!(parse(text).is_err(), "{} should not parse", text);
+    }
+}
@@ -0,0 +1,74 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display};
+use crate::cli::json::ToJson;
+
+// a key type the CLI can read from a script line or the menu and write back out
+pub trait Key: Copy+Display+Debug+PartialEq+ToJson+'static {
+    // the --key-type spelling, also stored in saved snapshots
+    const NAME: &'static str;
+    fn parse_key(word: &str) -> Option<Self>;
+}
+
+impl Key for u32 {
+    const NAME: &'static str = "u32";
+
+    fn parse_key(word: &str) -> Option<Self> {
+        word.parse().ok()
+    }
+}
+
+impl Key for i64 {
+    const NAME: &'static str = "i64";
+
+    fn parse_key(word: &str) -> Option<Self> {
+        word.parse().ok()
+    }
+}
+
+// nan and inf parse too, the trees order floats with TotalOrder
+impl Key for f64 {
+    const NAME: &'static str = "f64";
+
+    fn parse_key(word: &str) -> Option<Self> {
+        word.parse().ok()
+    }
+}
+
+impl Key for &'static str {
+    const NAME: &'static str = "string";
+
+    fn parse_key(word: &str) -> Option<Self> {
+        Some(intern(word))
+    }
+}
+
+// the trees need Copy keys, so every distinct word is leaked once and shared from then on
+fn intern(word: &str) -> &'static str {
+    thread_local! {
+        static WORDS: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+    }
+    WORDS.with(|words| {
+        let mut words = words.borrow_mut();
+        if let Some(interned) = words.get(word) {
+            return *interned;
+        }
+        let interned: &'static str = Box::leak(word.to_string().into_boxed_str());
+        words.insert(interned);
+        interned
+    })
+}
+
+#[test]
+pub fn test_parse_keys() {
+    assert_eq!(u32::parse_key("7"), Some(7));
+    assert_eq!(u32::parse_key("-7"), None);
+    assert_eq!(i64::parse_key("-7"), Some(-7));
+    assert_eq!(f64::parse_key("2.5"), Some(2.5));
+    assert!(f64::parse_key("nan").unwrap().is_nan());
+    assert_eq!(f64::parse_key("two"), None);
+    let word = <&str>::parse_key("apple").unwrap();
+    assert_eq!(word, "apple");
+    // the same word comes back as the same leaked string
+    assert!(std::ptr::eq(word, <&str>::parse_key("apple").unwrap()));
+}
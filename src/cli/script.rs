@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use crate::MenuTree;
+use crate::cli::args::Format;
+use crate::cli::command::Command;
+use crate::cli::key::Key;
+use crate::cli::session::Session;
+
+// run the commands in path, or in stdin when path is "-"
+pub fn run_file<K: Key, T: MenuTree<K>, F: Fn() -> T>(session: &mut Session<K, T, F>, path: &str, format: Format) -> Result<(), String> {
+    if path == "-" {
+        return run_script(session, io::stdin().lock(), format);
+    }
+    let file = File::open(path).map_err(|err| format!("could not open {}: {}", path, err))?;
+    run_script(session, BufReader::new(file), format)
+}
+
+// run one command per line, in order, without menus or banners so the output only depends on the
+// script. stops at the first line that does not parse or fails, or at quit
+pub fn run_script<K: Key, T: MenuTree<K>, F: Fn() -> T, R: BufRead>(session: &mut Session<K, T, F>, input: R, format: Format) -> Result<(), String> {
+    for (number, line) in input.lines().enumerate() {
+        let line = line.map_err(|err| format!("could not read line {}: {}", number + 1, err))?;
+        match Command::<K>::parse(&line).map_err(|err| format!("line {}: {}", number + 1, err))? {
+            Some(Command::Quit) => break,
+            Some(command) => session.run(command, format).map_err(|err| format!("line {}: {}", number + 1, err))?,
+            None => {}
+        }
+    }
+    Ok(())
+}
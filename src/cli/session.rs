@@ -0,0 +1,215 @@
+use crate::MenuTree;
+use crate::cli::args::Format;
+use crate::cli::command::{Command, json_object, run_command};
+use crate::cli::json;
+use crate::cli::key::Key;
+use crate::cli::snapshot;
+
+// a tree together with the inserts, deletes and loads applied to it. the trees share nodes when
+// cloned, so there are no snapshots to go back to: undo rebuilds the tree from fresh() and every
+// change but the last one
+pub struct Session<K, T, F> {
+    pub tree: T,
+    fresh: F,
+    done: Vec<Command<K>>,
+    undone: Vec<Command<K>>,
+}
+
+impl<K: Key, T: MenuTree<K>, F: Fn() -> T> Session<K, T, F> {
+    pub fn new(fresh: F) -> Self {
+        Session { tree: fresh(), fresh, done: Vec::new(), undone: Vec::new() }
+    }
+
+    pub fn history(&self) -> &[Command<K>] {
+        &self.done
+    }
+
+    // the change redo would make
+    pub fn next_redo(&self) -> Option<&Command<K>> {
+        self.undone.last()
+    }
+
+    // make a change without printing anything and add it to the history if it did anything, returns
+    // how many keys it inserted or deleted
+    pub fn record(&mut self, command: Command<K>) -> Result<usize, String> {
+        let changed = apply(&mut self.tree, &command)?;
+        if changed > 0 {
+            self.done.push(command);
+            self.undone.clear();
+        }
+        Ok(changed)
+    }
+
+    // run command on the tree, or step through the history for undo, redo, history and replay
+    pub fn run(&mut self, command: Command<K>, format: Format) -> Result<(), String> {
+        if command.changes_tree() {
+            let changed = self.record(command.clone())?;
+            match format {
+                Format::Text => println!("{}", describe(&command, changed)),
+                // true or false for an insert or delete, the number of keys read for a load
+                Format::Json => {
+                    let result = if matches!(command, Command::Load(_)) { changed.to_string() } else { (changed > 0).to_string() };
+                    println!("{}", json_object(&command, result));
+                }
+            }
+            return Ok(());
+        }
+        if !matches!(command, Command::Undo | Command::Redo | Command::History | Command::Replay) {
+            return run_command(&mut self.tree, command, format);
+        }
+        let (text, json) = self.run_history(&command)?;
+        match format {
+            Format::Text => println!("{}", text),
+            Format::Json => println!("{}", json::object(&[("op", json::string(command.name())), ("result", json)])),
+        }
+        Ok(())
+    }
+
+    // carry out undo, redo, history or replay, returning what to report as text and as a JSON value
+    pub fn run_history(&mut self, command: &Command<K>) -> Result<(String, String), String> {
+        let report = match command {
+            Command::Undo => {
+                let kept = self.done.len().checked_sub(1).ok_or("nothing to undo")?;
+                // rebuilt before the change moves to undone, so a load that fails to read again
+                // leaves both the tree and the history as they were
+                self.tree = self.rebuild(&self.done[..kept])?;
+                let last = self.done.pop().unwrap();
+                let report = (format!("Undid {}", last), json::string(&last.to_string()));
+                self.undone.push(last);
+                report
+            }
+            Command::Redo => {
+                let next = self.undone.pop().ok_or("nothing to redo")?;
+                apply(&mut self.tree, &next)?;
+                let report = (format!("Redid {}", next), json::string(&next.to_string()));
+                self.done.push(next);
+                report
+            }
+            Command::History => {
+                let lines: Vec<String> = self.history().iter().map(|command| command.to_string()).collect();
+                let text = if lines.is_empty() { "No operations yet".to_string() } else { lines.join("\n") };
+                let items: Vec<String> = lines.iter().map(|line| json::string(line)).collect();
+                (text, format!("[{}]", items.join(",")))
+            }
+            Command::Replay => {
+                self.tree = self.rebuild(&self.done)?;
+                (format!("Replayed {} operation(s) on a fresh tree", self.done.len()), self.done.len().to_string())
+            }
+            _ => return Err(format!("`{}` is not a history command", command.name())),
+        };
+        Ok(report)
+    }
+
+    // a fresh tree with changes applied again, a load reads its file again
+    fn rebuild(&self, changes: &[Command<K>]) -> Result<T, String> {
+        let mut tree = (self.fresh)();
+        for command in changes {
+            apply(&mut tree, command)?;
+        }
+        Ok(tree)
+    }
+}
+
+// what a change did, given how many keys it inserted or deleted
+pub fn describe<K: Key>(command: &Command<K>, changed: usize) -> String {
+    match (command, changed) {
+        (Command::Insert(key), 0) => format!("{} is already in the tree", key),
+        (Command::Insert(key), _) => format!("Inserted {}", key),
+        (Command::Delete(key), 0) => format!("{} is not in the tree", key),
+        (Command::Delete(key), _) => format!("Deleted {}", key),
+        (Command::Load(path), loaded) => format!("Loaded {} key(s) from {}", loaded, path),
+        _ => String::new(),
+    }
+}
+
+// make a change without printing anything, returns how many keys went in or out
+fn apply<K: Key, T: MenuTree<K>>(tree: &mut T, command: &Command<K>) -> Result<usize, String> {
+    let changed = match command {
+        Command::Insert(key) if !tree.contains(*key) => {
+            tree.insert(*key);
+            1
+        }
+        Command::Delete(key) if tree.contains(*key) => {
+            tree.delete(*key);
+            1
+        }
+        Command::Load(path) => snapshot::load(tree, path)?,
+        _ => 0,
+    };
+    Ok(changed)
+}
+
+#[cfg(test)]
+type AvlSession = Session<u32, Rust_Trees::avltree::AvlTree<u32>, fn() -> Rust_Trees::avltree::AvlTree<u32>>;
+
+#[cfg(test)]
+fn keys(session: &AvlSession) -> Vec<u32> {
+    session.tree.shape().inorder().into_iter().copied().collect()
+}
+
+#[test]
+pub fn test_undo_redo() {
+    let mut session: AvlSession = Session::new(Rust_Trees::avltree::AvlTree::new);
+    for key in [5, 3, 8] {
+        session.run(Command::Insert(key), Format::Text).unwrap();
+    }
+    session.run(Command::Height, Format::Text).unwrap();
+    session.run(Command::Delete(3), Format::Text).unwrap();
+    assert_eq!(keys(&session), vec![5, 8]);
+    // only the changes are recorded
+    assert_eq!(session.history(), &[Command::Insert(5), Command::Insert(3), Command::Insert(8), Command::Delete(3)]);
+
+    session.run(Command::Undo, Format::Text).unwrap();
+    assert_eq!(keys(&session), vec![3, 5, 8]);
+    session.run(Command::Undo, Format::Text).unwrap();
+    assert_eq!(keys(&session), vec![3, 5]);
+    session.run(Command::Redo, Format::Text).unwrap();
+    assert_eq!(keys(&session), vec![3, 5, 8]);
+    assert_eq!(session.history().len(), 3);
+
+    // a new change drops what was left to redo
+    session.run(Command::Insert(1), Format::Text).unwrap();
+    assert!(session.run(Command::Redo, Format::Text).is_err());
+    session.run(Command::Replay, Format::Text).unwrap();
+    assert_eq!(keys(&session), vec![1, 3, 5, 8]);
+
+    for _ in 0..4 {
+        session.run(Command::Undo, Format::Text).unwrap();
+    }
+    assert!(session.tree.is_empty());
+    assert!(session.run(Command::Undo, Format::Text).is_err());
+}
+
+#[test]
+pub fn test_only_changes_recorded() {
+    let mut session: AvlSession = Session::new(Rust_Trees::avltree::AvlTree::new);
+    session.run(Command::Insert(5), Format::Text).unwrap();
+    // a duplicate insert and a delete of a missing key leave nothing to undo
+    session.run(Command::Insert(5), Format::Text).unwrap();
+    session.run(Command::Delete(9), Format::Json).unwrap();
+    assert_eq!(session.history(), &[Command::Insert(5)]);
+    session.run(Command::Undo, Format::Text).unwrap();
+    assert!(session.tree.is_empty());
+}
+
+#[test]
+pub fn test_failed_undo_keeps_history() {
+    let path = snapshot::temp_path("undo.txt");
+    std::fs::write(&path, "1, 2").unwrap();
+    let mut session: AvlSession = Session::new(Rust_Trees::avltree::AvlTree::new);
+    session.run(Command::Load(path.clone()), Format::Text).unwrap();
+    session.run(Command::Insert(3), Format::Text).unwrap();
+    // undoing the insert has to read the file again
+    std::fs::remove_file(&path).unwrap();
+    assert!(session.run(Command::Undo, Format::Text).is_err());
+    assert_eq!(keys(&session), vec![1, 2, 3]);
+    assert_eq!(session.history(), &[Command::Load(path.clone()), Command::Insert(3)]);
+    assert!(session.run(Command::Redo, Format::Text).is_err());
+    // once the file is back the same undo goes through and can be redone
+    std::fs::write(&path, "1, 2").unwrap();
+    session.run(Command::Undo, Format::Text).unwrap();
+    assert_eq!(keys(&session), vec![1, 2]);
+    session.run(Command::Redo, Format::Text).unwrap();
+    assert_eq!(keys(&session), vec![1, 2, 3]);
+    std::fs::remove_file(&path).unwrap();
+}
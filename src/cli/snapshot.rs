@@ -0,0 +1,143 @@
+use std::fs;
+use crate::MenuTree;
+use crate::cli::json::{self, Value};
+use crate::cli::key::Key;
+
+// a saved tree is {"key_type":"u32","keys":[..]} with the keys in pre-order, so loading it into a plain
+// BST rebuilds the same shape. the balancing trees rebalance as the keys go back in
+pub fn save<K: Key, T: MenuTree<K>>(tree: &T, path: &str) -> Result<usize, String> {
+    let keys = tree.preorder();
+    let keys: Vec<&K> = keys.iter().collect();
+    let text = json::object(&[("key_type", json::string(K::NAME)), ("keys", json::keys(&keys))]);
+    fs::write(path, text + "\n").map_err(|err| format!("could not write {}: {}", path, err))?;
+    Ok(keys.len())
+}
+
+// insert every key in path the tree does not hold yet, returns how many went in
+pub fn load<K: Key, T: MenuTree<K>>(tree: &mut T, path: &str) -> Result<usize, String> {
+    let mut loaded = 0;
+    for key in read_keys::<K>(path)? {
+        if !tree.contains(key) {
+            tree.insert(key);
+            loaded += 1;
+        }
+    }
+    Ok(loaded)
+}
+
+// the keys of a snapshot written by save, or of a plain list separated by commas or new lines
+pub fn read_keys<K: Key>(path: &str) -> Result<Vec<K>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("could not read {}: {}", path, err))?;
+    let keys = if text.trim_start().starts_with('{') { snapshot_keys(&text) } else { list_keys(&text) };
+    keys.map_err(|err| format!("{}: {}", path, err))
+}
+
+fn snapshot_keys<K: Key>(text: &str) -> Result<Vec<K>, String> {
+    let snapshot = json::parse(text)?;
+    match snapshot.field("key_type") {
+        Some(Value::Str(name)) if name == K::NAME => {}
+        Some(Value::Str(name)) => return Err(format!("the snapshot holds {} keys, not {}", name, K::NAME)),
+        _ => return Err("the snapshot has no key_type".to_string()),
+    }
+    let items = match snapshot.field("keys") {
+        Some(Value::Array(items)) => items,
+        _ => return Err("the snapshot has no keys".to_string()),
+    };
+    // string keys and the non-finite floats are written as strings, everything else as numbers
+    items.iter().map(|item| match item {
+        Value::Number(word) | Value::Str(word) => parse_key(word),
+        _ => Err(format!("{:?} is not a key", item)),
+    }).collect()
+}
+
+fn list_keys<K: Key>(text: &str) -> Result<Vec<K>, String> {
+    text.split([',', '\n']).map(str::trim).filter(|word| !word.is_empty()).map(parse_key).collect()
+}
+
+fn parse_key<K: Key>(word: &str) -> Result<K, String> {
+    K::parse_key(word).ok_or(format!("`{}` is not a valid key", word))
+}
+
+#[cfg(test)]
+pub fn temp_path(name: &str) -> String {
+    std::env::temp_dir().join(format!("rust-trees-{}-{}", std::process::id(), name)).to_string_lossy().into_owned()
+}
+
+#[test]
+pub fn test_load_list() {
+    let path = temp_path("keys.txt");
+    fs::write(&path, "5, 3\n8\n\n3,1\r\n").unwrap();
+    let mut tree = Rust_Trees::avltree::AvlTree::<u32>::new();
+    // the second 3 is already in the tree
+    assert_eq!(load(&mut tree, &path), Ok(4));
+    assert_eq!(tree.shape().inorder(), vec![&1, &3, &5, &8]);
+    fs::write(&path, "1,two").unwrap();
+    assert!(load(&mut tree, &path).unwrap_err().contains("`two` is not a valid key"));
+    fs::remove_file(&path).unwrap();
+    assert!(load(&mut tree, &path).is_err());
+}
+
+#[test]
+pub fn test_save_and_load() {
+    let path = temp_path("tree.json");
+    let mut tree = Rust_Trees::bst::BST::<u32>::new();
+    for key in [5, 2, 8, 1, 3, 9] {
+        tree.insert(key);
+    }
+    assert_eq!(save(&tree, &path), Ok(6));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "{\"key_type\":\"u32\",\"keys\":[5,2,1,3,8,9]}\n");
+    // pre-order puts every key back where it was
+    let mut copy = Rust_Trees::bst::BST::<u32>::new();
+    assert_eq!(load(&mut copy, &path), Ok(6));
+    assert_eq!(copy.shape().levelorder(), tree.shape().levelorder());
+    // a snapshot only loads as the key type it was saved with
+    assert!(read_keys::<i64>(&path).unwrap_err().contains("holds u32 keys"));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+pub fn test_save_and_load_words() {
+    let path = temp_path("words.json");
+    let mut tree = Rust_Trees::rbtree::RBTree::<&str>::new();
+    for word in ["pear", "apple", "fig"] {
+        tree.insert(word);
+    }
+    assert_eq!(save(&tree, &path), Ok(3));
+    assert_eq!(read_keys::<&str>(&path).map(|mut words| { words.sort(); words }), Ok(vec!["apple", "fig", "pear"]));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+pub fn test_save_and_load_bplus() {
+    let path = temp_path("bplus.json");
+    let mut tree = Rust_Trees::btree::BPlusTree::<u32>::new();
+    for key in 1..300 {
+        tree.insert(key);
+    }
+    for key in (1..300).step_by(2) {
+        tree.delete(key);
+    }
+    // separators copied from deleted odd keys stay in the internal nodes but are not saved
+    assert_eq!(save(&tree, &path), Ok(149));
+    let evens: Vec<u32> = (2..300).step_by(2).collect();
+    assert_eq!(read_keys::<u32>(&path), Ok(evens.clone()));
+    let mut copy = Rust_Trees::btree::BPlusTree::<u32>::new();
+    assert_eq!(load(&mut copy, &path), Ok(149));
+    assert_eq!(copy.iter().collect::<Vec<u32>>(), evens);
+
+    let mut rng = Rust_Trees::rng::Rng::new(1);
+    let mut tree = Rust_Trees::btree::BPlusTree::<u32>::new();
+    let mut held = std::collections::BTreeSet::new();
+    for _ in 0..2000 {
+        let key = rng.below(400) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+            held.remove(&key);
+        } else if held.insert(key) {
+            tree.insert(key);
+        }
+    }
+    assert_eq!(save(&tree, &path), Ok(held.len()));
+    assert_eq!(read_keys::<u32>(&path), Ok(held.into_iter().collect::<Vec<u32>>()));
+    fs::remove_file(&path).unwrap();
+}
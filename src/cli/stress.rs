@@ -0,0 +1,292 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::process;
+use std::time::{Duration, Instant};
+use Rust_Trees::avltree::AvlTree;
+use Rust_Trees::bst::BST;
+use Rust_Trees::rbtree::RBTree;
+use Rust_Trees::rng::Rng;
+use crate::MenuTree;
+use crate::cli::args::{Options, Workload};
+
+const DEFAULT_SIZE: usize = 10_000;
+const DEFAULT_SEED: u64 = 1;
+// validate this many times over a run unless --check-every says otherwise
+const DEFAULT_CHECKS: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Insert(u32),
+    Delete(u32),
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Insert(key) => write!(f, "insert {}", key),
+            Op::Delete(key) => write!(f, "delete {}", key),
+        }
+    }
+}
+
+// size operations that never insert a key already there or delete one that is not, so every tree
+// sees exactly the same changes:
+//   random    toggles keys picked uniformly from 0..size
+//   zipfian   toggles keys picked with Zipf's law, key 0 most often, so a few keys churn constantly
+//   sorted    inserts ascending keys for two thirds of the run, then deletes every other one
+//   reverse   the same with descending keys
+//   sawtooth  inserts runs of ascending keys, deleting the top half of each run before the next
+// with redundant above 0, that percentage of the operations is instead a duplicate insert or a
+// delete of a missing key, see mix_redundant
+pub fn generate(workload: Workload, size: usize, seed: u64, redundant: u64) -> Vec<Op> {
+    let mut rng = Rng::new(seed);
+    let inserts = (size - size / 3) as u32;
+    let ops: Vec<Op> = match workload {
+        Workload::Random => toggles(size, || rng.below(size as u64) as u32),
+        Workload::Zipfian => {
+            // cdf[k] is the chance of picking a key no larger than k, with key k weighted 1 / (k + 1)
+            let mut cdf: Vec<f64> = (1..=size).map(|rank| 1.0 / rank as f64).collect();
+            for k in 1..size {
+                cdf[k] += cdf[k - 1];
+            }
+            let total = cdf[size - 1];
+            toggles(size, || {
+                let u = rng.next_u64() as f64 / u64::MAX as f64 * total;
+                cdf.partition_point(|&p| p < u).min(size - 1) as u32
+            })
+        }
+        Workload::Sorted => (0..inserts).map(Op::Insert).chain((0..inserts).step_by(2).map(Op::Delete)).collect(),
+        Workload::Reverse => (0..inserts).rev().map(Op::Insert).chain((0..inserts).rev().step_by(2).map(Op::Delete)).collect(),
+        Workload::Sawtooth => {
+            let tooth = ((size as f64).sqrt() as u32).max(2);
+            (0..).step_by(tooth as usize).flat_map(|base: u32| {
+                let up = (base..base + tooth).map(Op::Insert);
+                let down = (base + tooth / 2..base + tooth).rev().map(Op::Delete);
+                up.chain(down)
+            }).take(size).collect()
+        }
+    };
+    let ops: Vec<Op> = ops.into_iter().take(size).collect();
+    if redundant == 0 {
+        return ops;
+    }
+    mix_redundant(ops, redundant, &mut rng)
+}
+
+// put a duplicate insert or a delete of a missing key in front of about percent in a hundred of ops,
+// keeping to as many operations as there were. these leave the tree as it was, which is exactly
+// what bookkeeping such as a count can get wrong
+fn mix_redundant(ops: Vec<Op>, percent: u64, rng: &mut Rng) -> Vec<Op> {
+    let size = ops.len();
+    // the keys present, each with its place in keys so a random one can be picked and dropped quickly
+    let mut keys: Vec<u32> = Vec::new();
+    let mut places: HashMap<u32, usize> = HashMap::new();
+    let mut mixed = Vec::with_capacity(size);
+    for op in ops {
+        if rng.below(100) < percent {
+            if !keys.is_empty() && rng.below(2) == 0 {
+                mixed.push(Op::Insert(keys[rng.below(keys.len() as u64) as usize]));
+            } else {
+                // at most size keys are present, so most picks below twice that are missing
+                let missing = loop {
+                    let key = rng.below(2 * size as u64 + 2) as u32;
+                    if !places.contains_key(&key) {
+                        break key;
+                    }
+                };
+                mixed.push(Op::Delete(missing));
+            }
+        }
+        match op {
+            Op::Insert(key) => {
+                places.insert(key, keys.len());
+                keys.push(key);
+            }
+            Op::Delete(key) => {
+                let place = places.remove(&key).unwrap();
+                keys.swap_remove(place);
+                if let Some(&moved) = keys.get(place) {
+                    places.insert(moved, place);
+                }
+            }
+        }
+        mixed.push(op);
+    }
+    mixed.truncate(size);
+    mixed
+}
+
+// size operations on keys from pick, each inserting the key if it is missing and deleting it if not
+fn toggles<F: FnMut() -> u32>(size: usize, mut pick: F) -> Vec<Op> {
+    let mut present = HashSet::new();
+    (0..size).map(|_| {
+        let key = pick();
+        if present.insert(key) { Op::Insert(key) } else {
+            present.remove(&key);
+            Op::Delete(key)
+        }
+    }).collect()
+}
+
+// what stress reads from a tree besides the menu operations
+trait Stressed: MenuTree<u32> {
+    fn validate(&self) -> Result<(), String>;
+    fn rotations(&self) -> u64;
+    fn apply(&mut self, op: Op) {
+        match op {
+            Op::Insert(key) => self.insert(key),
+            Op::Delete(key) => self.delete(key),
+        }
+    }
+}
+
+impl Stressed for RBTree<u32> {
+    fn validate(&self) -> Result<(), String> { <RBTree<u32>>::validate(self) }
+    fn rotations(&self) -> u64 { <RBTree<u32>>::rotations(self) }
+    // insert and delete print a line for every duplicate or missing key, these are the same
+    // operations without it
+    fn apply(&mut self, op: Op) {
+        match op {
+            Op::Insert(key) => { self.entry(key).or_insert(key); }
+            Op::Delete(key) => { self.remove_all(key); }
+        }
+    }
+}
+
+impl Stressed for AvlTree<u32> {
+    fn validate(&self) -> Result<(), String> { <AvlTree<u32>>::validate(self) }
+    fn rotations(&self) -> u64 { <AvlTree<u32>>::rotations(self) }
+}
+
+impl Stressed for BST<u32> {
+    fn validate(&self) -> Result<(), String> { <BST<u32>>::validate(self) }
+    // a plain BST never rotates
+    fn rotations(&self) -> u64 { 0 }
+}
+
+// how a tree came out of a run, time only counts the operations and not the checks
+#[derive(Debug)]
+pub struct Report {
+    pub name: &'static str,
+    pub height: u32,
+    pub count: usize,
+    pub rotations: u64,
+    pub time: Duration,
+}
+
+// apply ops in batches of check_every, validating the tree after each batch and checking its count
+// and the keys the batch touched against a set given the same operations
+fn stress<T: Stressed>(name: &'static str, mut tree: T, ops: &[Op], check_every: usize) -> Result<Report, String> {
+    let mut time = Duration::ZERO;
+    let mut reference = HashSet::new();
+    let mut done = 0;
+    for batch in ops.chunks(check_every) {
+        let start = Instant::now();
+        for op in batch {
+            tree.apply(*op);
+        }
+        time += start.elapsed();
+        for op in batch {
+            match *op {
+                Op::Insert(key) => reference.insert(key),
+                Op::Delete(key) => reference.remove(&key),
+            };
+        }
+        done += batch.len();
+        let result = tree.validate().and_then(|()| match tree.count() {
+            held if held == reference.len() => Ok(()),
+            held => Err(format!("count is {} after {} operations that leave {} keys", held, done, reference.len())),
+        });
+        let result = result.and_then(|()| {
+            for op in batch {
+                let (Op::Insert(key) | Op::Delete(key)) = *op;
+                match (tree.contains(key), reference.contains(&key)) {
+                    (true, false) => return Err(format!("{} is still in the tree", key)),
+                    (false, true) => return Err(format!("{} is missing from the tree", key)),
+                    _ => {}
+                }
+            }
+            Ok(())
+        });
+        result.map_err(|err| format!("{} is broken after operation {} ({}): {}", name, done, batch.last().unwrap(), err))?;
+    }
+    Ok(Report { name, height: tree.height(), count: reference.len(), rotations: tree.rotations(), time })
+}
+
+pub fn run_all(ops: &[Op], check_every: usize) -> Result<Vec<Report>, String> {
+    Ok(vec![
+        stress("Red Black Tree", RBTree::new(), ops, check_every)?,
+        stress("AVL Tree", AvlTree::new(), ops, check_every)?,
+        stress("Binary Search Tree", BST::new(), ops, check_every)?,
+    ])
+}
+
+// cargo run stress: the same workload through every tree, exiting non-zero at the first broken one
+pub fn run(options: &Options) {
+    let size = options.size.unwrap_or(DEFAULT_SIZE);
+    let seed = options.seed.unwrap_or(DEFAULT_SEED);
+    let check_every = options.check_every.unwrap_or(size.div_ceil(DEFAULT_CHECKS));
+    let redundant = options.redundant.unwrap_or(0);
+    let ops = generate(options.workload, size, seed, redundant);
+    println!("{:?} workload, {} operations ({}% redundant), seed {}, checking every {}", options.workload, ops.len(), redundant, seed, check_every);
+    match run_all(&ops, check_every) {
+        Ok(reports) => {
+            println!("{:<20}{:>8}{:>8}{:>12}{:>12}", "tree", "keys", "height", "rotations", "time");
+            for report in reports {
+                let time = format!("{:.2?}", report.time);
+                println!("{:<20}{:>8}{:>8}{:>12}{:>12}", report.name, report.count, report.height, report.rotations, time);
+            }
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    }
+}
+
+#[test]
+pub fn test_generate() {
+    assert_eq!(generate(Workload::Sorted, 6, 1, 0), vec![Op::Insert(0), Op::Insert(1), Op::Insert(2), Op::Insert(3), Op::Delete(0), Op::Delete(2)]);
+    assert_eq!(generate(Workload::Reverse, 6, 1, 0), vec![Op::Insert(3), Op::Insert(2), Op::Insert(1), Op::Insert(0), Op::Delete(3), Op::Delete(1)]);
+    assert_eq!(generate(Workload::Sawtooth, 8, 1, 0), vec![Op::Insert(0), Op::Insert(1), Op::Delete(1), Op::Insert(2), Op::Insert(3), Op::Delete(3), Op::Insert(4), Op::Insert(5)]);
+    for workload in [Workload::Random, Workload::Sorted, Workload::Reverse, Workload::Zipfian, Workload::Sawtooth] {
+        let ops = generate(workload, 1000, 5, 0);
+        assert_eq!(ops.len(), 1000);
+        assert_eq!(ops, generate(workload, 1000, 5, 0));
+        // never a duplicate insert or a delete of a missing key
+        let mut present = HashSet::new();
+        for op in ops {
+            match op {
+                Op::Insert(key) => assert!(present.insert(key)),
+                Op::Delete(key) => assert!(present.remove(&key)),
+            }
+        }
+    }
+    // the hottest zipfian key comes up far more often than uniform picks would
+    let hot = generate(Workload::Zipfian, 1000, 5, 0).iter().filter(|op| matches!(op, Op::Insert(0) | Op::Delete(0))).count();
+    assert!(hot > 50);
+    // with redundant operations mixed in, both kinds come up
+    let ops = generate(Workload::Sorted, 1000, 5, 20);
+    assert_eq!(ops.len(), 1000);
+    let mut present = HashSet::new();
+    let (mut duplicates, mut missing) = (0, 0);
+    for op in ops {
+        match op {
+            Op::Insert(key) => duplicates += !present.insert(key) as usize,
+            Op::Delete(key) => missing += !present.remove(&key) as usize,
+        }
+    }
+    assert!(duplicates > 50 && missing > 50, "{} duplicate inserts, {} missing deletes", duplicates, missing);
+}
+
+#[test]
+pub fn test_stress_runs() {
+    for workload in [Workload::Random, Workload::Sorted, Workload::Reverse, Workload::Zipfian, Workload::Sawtooth] {
+        let ops = generate(workload, 500, 3, 10);
+        let reports = run_all(&ops, 50).unwrap();
+        assert_eq!(reports.len(), 3);
+        assert!(reports.iter().all(|report| report.count == reports[0].count));
+        // the balanced trees stay far below the worst case
+        assert!(reports[0].height <= 18 && reports[1].height <= 13);
+    }
+}
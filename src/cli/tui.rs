@@ -0,0 +1,294 @@
+use std::env;
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::Duration;
+use Rust_Trees::shape::{Attr, Shape};
+use crate::MenuTree;
+use crate::cli::args::Format;
+use crate::cli::command::Command;
+use crate::cli::key::Key;
+use crate::cli::session::{Session, describe};
+
+// how long each traced step of an insert or delete stays on screen
+const STEP_DELAY: Duration = Duration::from_millis(700);
+// space between two subtrees on the same row
+const GAP: usize = 2;
+
+const ALTERNATE_SCREEN: &str = "\x1b[?1049h";
+const MAIN_SCREEN: &str = "\x1b[?1049l";
+const CLEAR: &str = "\x1b[2J\x1b[H";
+const RED: &str = "\x1b[1;31m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// full screen mode: the tree is redrawn after every command with the prompt on the bottom row, and a
+// tree that records its steps has each rotation and recolor shown in turn
+pub fn run<K: Key, T: MenuTree<K>, F: Fn() -> T>(mut session: Session<K, T, F>, name: &str) {
+    print!("{}", ALTERNATE_SCREEN);
+    let mut message = "Enter commands such as insert 5, delete 5, undo, history or quit".to_string();
+    // false after a command printed its own output under the tree, so it stays on screen
+    let mut redraw = true;
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        // asked once per command, starting stty for every animation step would slow them down
+        let columns = columns();
+        if redraw {
+            show(&session.tree.shape(), name, &message, columns);
+        } else {
+            print!("> ");
+        }
+        io::stdout().flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let command = Command::<K>::parse(&line);
+        let redo = session.next_redo().cloned();
+        session.tree.trace(matches!(&command, Ok(Some(command)) if animated(command, redo.as_ref())));
+        redraw = true;
+        message = match command {
+            Err(err) => err,
+            Ok(None) => String::new(),
+            Ok(Some(Command::Quit)) => break,
+            Ok(Some(command)) if command.changes_tree() => {
+                let result = session.record(command.clone());
+                animate(&mut session.tree, name, columns);
+                match result {
+                    Ok(changed) => describe(&command, changed),
+                    Err(err) => err,
+                }
+            }
+            Ok(Some(command)) if matches!(command, Command::Undo | Command::Redo | Command::Replay) => {
+                let result = session.run_history(&command);
+                animate(&mut session.tree, name, columns);
+                result.map(|(text, _)| text).unwrap_or_else(|err| err)
+            }
+            Ok(Some(command)) => {
+                print!("{}", screen(&session.tree.shape(), name, columns));
+                if let Err(err) = session.run(command, Format::Text) {
+                    println!("{}", err);
+                }
+                redraw = false;
+                String::new()
+            }
+        };
+    }
+    print!("{}", MAIN_SCREEN);
+    io::stdout().flush().ok();
+}
+
+// only a single insert or delete is animated, the steps of every key a load reads would keep the
+// screen busy for minutes and take a snapshot of the whole tree each
+fn animated<K: Key>(command: &Command<K>, redo: Option<&Command<K>>) -> bool {
+    match command {
+        Command::Insert(_) | Command::Delete(_) => true,
+        Command::Redo => redo.is_some_and(|redo| animated(redo, None)),
+        _ => false,
+    }
+}
+
+// show every step the last command recorded, then leave the final tree to the caller
+fn animate<K: Key, T: MenuTree<K>>(tree: &mut T, name: &str, columns: usize) {
+    let steps = tree.take_steps();
+    for (i, step) in steps.iter().enumerate() {
+        show(&step.shape, name, &format!("step {} of {}: {}", i + 1, steps.len(), step.action), columns);
+        thread::sleep(STEP_DELAY);
+    }
+}
+
+fn show<K: Display>(shape: &Shape<K>, name: &str, message: &str, columns: usize) {
+    // the message and the prompt go on the last two rows whatever the terminal's height, 999 is
+    // clamped to the bottom row
+    print!("{}\x1b[999;1H\x1b[1A\x1b[2K{}\n\x1b[2K> ", screen(shape, name, columns), message);
+}
+
+// the cleared screen with the tree drawn from the top, sideways when it is too wide to fit
+fn screen<K: Display>(shape: &Shape<K>, name: &str, columns: usize) -> String {
+    let mut lines = draw(shape, true);
+    if width(shape) > columns {
+        lines = shape.lines();
+    }
+    let mut out = format!("{}{}{}{}\n\n", CLEAR, BOLD, name, RESET);
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+// the terminal's width as stty reports it, then COLUMNS, which shells seldom export, then 80
+fn columns() -> usize {
+    let stty = process::Command::new("stty").arg("size").stdin(Stdio::inherit()).stderr(Stdio::null()).output();
+    let reported = stty.ok().filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        // stty size prints rows then columns
+        .and_then(|size| size.split_whitespace().nth(1).and_then(|columns| columns.parse().ok()));
+    reported.or_else(|| env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok())).unwrap_or(80)
+}
+
+// where draw puts each node: the columns its subtree spans, its label and the centres of its child
+// slots, a missing child of a node that has the other one taking a single column
+struct Layout {
+    labels: Vec<String>,
+    widths: Vec<usize>,
+    lefts: Vec<usize>,
+    centers: Vec<usize>,
+    depths: Vec<usize>,
+}
+
+// children come after their parent in a shape, so walking the nodes backwards visits every child
+// before its parent and walking them forwards every parent before its children
+fn layout<K: Display>(shape: &Shape<K>) -> Layout {
+    let nodes = &shape.nodes;
+    let labels: Vec<String> = nodes.iter()
+        .map(|node| node.keys.iter().map(|key| key.to_string()).collect::<Vec<String>>().join(" "))
+        .collect();
+    let slots = |i: usize| -> &[Option<usize>] {
+        let children = &nodes[i].children;
+        if children.iter().all(Option::is_none) { &[] } else { children }
+    };
+    let mut widths = vec![0; nodes.len()];
+    for i in (0..nodes.len()).rev() {
+        let below = slots(i).iter().map(|slot| slot.map_or(1, |child| widths[child])).sum::<usize>()
+            + GAP * slots(i).len().saturating_sub(1);
+        widths[i] = below.max(labels[i].chars().count());
+    }
+    let mut lefts = vec![0; nodes.len()];
+    let mut depths = vec![0; nodes.len()];
+    // the left column of every slot, a missing child's included
+    let mut slot_lefts: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for i in 0..nodes.len() {
+        let below = slots(i).iter().map(|slot| slot.map_or(1, |child| widths[child])).sum::<usize>()
+            + GAP * slots(i).len().saturating_sub(1);
+        let mut left = lefts[i] + (widths[i] - below.min(widths[i])) / 2;
+        for slot in slots(i) {
+            slot_lefts[i].push(left);
+            if let Some(child) = slot {
+                lefts[*child] = left;
+                depths[*child] = depths[i] + 1;
+            }
+            left += slot.map_or(1, |child| widths[child]) + GAP;
+        }
+    }
+    let mut centers = vec![0; nodes.len()];
+    for i in (0..nodes.len()).rev() {
+        let len = labels[i].chars().count();
+        centers[i] = if slots(i).is_empty() {
+            lefts[i] + (widths[i] - len) / 2 + len / 2
+        } else {
+            let slot_center = |k: usize| slots(i)[k].map_or(slot_lefts[i][k], |child| centers[child]);
+            (slot_center(0) + slot_center(slots(i).len() - 1)) / 2
+        };
+    }
+    Layout { labels, widths, lefts, centers, depths }
+}
+
+fn width<K: Display>(shape: &Shape<K>) -> usize {
+    layout(shape).widths.first().copied().unwrap_or(0)
+}
+
+// the tree top down, each node's keys centred over its children and joined to them with box drawing
+// lines. with color on, red nodes are drawn in red and black ones in bold
+pub fn draw<K: Display>(shape: &Shape<K>, color: bool) -> Vec<String> {
+    if shape.nodes.is_empty() {
+        return vec!["(empty)".to_string()];
+    }
+    let Layout { labels, widths, lefts, centers, depths } = layout(shape);
+    let rows = 2 * depths.iter().max().unwrap() + 1;
+    let mut grid = vec![vec![' '; widths[0]]; rows];
+    let mut styles: Vec<Vec<Option<&'static str>>> = vec![vec![None; widths[0]]; rows];
+    for (i, node) in shape.nodes.iter().enumerate() {
+        let len = labels[i].chars().count();
+        let start = (centers[i] - len / 2).clamp(lefts[i], lefts[i] + widths[i] - len);
+        let style = node.attrs.iter().find_map(|(name, attr)| match (*name, attr) {
+            ("color", Attr::Text("red")) => Some(RED),
+            ("color", Attr::Text("black")) => Some(BOLD),
+            _ => None,
+        });
+        for (offset, c) in labels[i].chars().enumerate() {
+            grid[2 * depths[i]][start + offset] = c;
+            styles[2 * depths[i]][start + offset] = style;
+        }
+        let children: Vec<usize> = node.children.iter().flatten().map(|&child| centers[child]).collect();
+        if children.is_empty() {
+            continue;
+        }
+        let lo = children.iter().copied().chain([centers[i]]).min().unwrap();
+        let hi = children.iter().copied().chain([centers[i]]).max().unwrap();
+        for (column, cell) in grid[2 * depths[i] + 1].iter_mut().enumerate().take(hi + 1).skip(lo) {
+            *cell = junction(column == centers[i], children.contains(&column), column > lo, column < hi);
+        }
+    }
+    grid.iter().zip(&styles).map(|(row, row_styles)| {
+        let mut line = String::new();
+        let mut current = None;
+        for (&c, &style) in row.iter().zip(row_styles) {
+            if color && style != current {
+                line.push_str(style.unwrap_or(RESET));
+                current = style;
+            }
+            line.push(c);
+        }
+        if color && current.is_some() {
+            line.push_str(RESET);
+        }
+        line.trim_end().to_string()
+    }).collect()
+}
+
+// the box drawing character for a column of a connecting row, from which sides it joins
+fn junction(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (true, true, true, true) => '┼',
+        (true, true, true, false) => '┤',
+        (true, true, false, true) => '├',
+        (true, true, false, false) => '│',
+        (true, false, true, true) => '┴',
+        (true, false, true, false) => '┘',
+        (true, false, false, true) => '└',
+        (false, true, true, true) => '┬',
+        (false, true, true, false) => '┐',
+        (false, true, false, true) => '┌',
+        _ => '─',
+    }
+}
+
+#[test]
+pub fn test_draw() {
+    let mut tree = Rust_Trees::rbtree::RBTree::<u32>::new();
+    for key in [20, 10, 30, 5, 25, 40, 45] {
+        tree.insert(key);
+    }
+    assert_eq!(draw(&tree.shape(), false), vec![
+        "    20",
+        " ┌───┴───┐",
+        "10      30",
+        "┌┘     ┌─┴──┐",
+        "5     25   40",
+        "            └─┐",
+        "             45",
+    ]);
+    // red keys are wrapped in color codes, black ones in bold
+    assert_eq!(draw(&tree.shape(), true)[0], format!("    {}20{}", BOLD, RESET));
+    assert_eq!(draw(&Rust_Trees::rbtree::RBTree::<u32>::new().shape(), false), vec!["(empty)"]);
+}
+
+#[test]
+pub fn test_draw_multiway() {
+    let mut tree = Rust_Trees::btree::BTree::<u32>::with_order(3);
+    for key in 1..=5 {
+        tree.insert(key);
+    }
+    assert_eq!(draw(&tree.shape(), false), vec!["  2 4", "┌──┼──┐", "1  3  5"]);
+}
+
+#[test]
+pub fn test_animated() {
+    assert!(animated::<u32>(&Command::Insert(5), None));
+    assert!(animated::<u32>(&Command::Redo, Some(&Command::Delete(5))));
+    assert!(!animated::<u32>(&Command::Load("keys.txt".to_string()), None));
+    assert!(!animated::<u32>(&Command::Redo, Some(&Command::Load("keys.txt".to_string()))));
+    assert!(!animated::<u32>(&Command::Redo, None));
+}
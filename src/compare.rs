@@ -0,0 +1,68 @@
+use std::cmp::Ordering;
+
+// Every tree orders its keys through a Comparator instead of calling `<`/`==` on `T: Ord`
+// directly, so keys can be ordered case-insensitively, in reverse or by a projected field
+// without wrapping them in a newtype.
+pub trait Comparator<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+// Orders keys by their Ord implementation. This is the comparator used by `new()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Natural;
+
+impl<T: Ord> Comparator<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+// Orders keys in the reverse of their Ord implementation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Reverse;
+
+impl<T: Ord> Comparator<T> for Reverse {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+// Orders floats by total_cmp, so NaN and -0.0 have a place instead of making keys incomparable.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TotalOrder;
+
+impl Comparator<f64> for TotalOrder {
+    fn compare(&self, a: &f64, b: &f64) -> Ordering {
+        a.total_cmp(b)
+    }
+}
+
+impl Comparator<f32> for TotalOrder {
+    fn compare(&self, a: &f32, b: &f32) -> Ordering {
+        a.total_cmp(b)
+    }
+}
+
+// Orders keys by a projection of the key, e.g. `ByKey(|p: &(u32, char)| p.1)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ByKey<F>(pub F);
+
+impl<T, K, F> Comparator<T> for ByKey<F>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+// Any closure or function taking two keys works as a comparator.
+impl<T, F> Comparator<T> for F
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
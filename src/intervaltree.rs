@@ -0,0 +1,264 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use crate::augment::Augment;
+use crate::compare::Natural;
+use crate::rbtree::{RBTree, Iter, Tree};
+
+// closed interval [lo, hi], ordered by lo and then by hi
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval<T> {
+    pub lo: T,
+    pub hi: T,
+}
+
+impl<T: Ord+Copy> Interval<T> {
+    pub fn new(lo: T, hi: T) -> Self {
+        assert!(lo <= hi, "interval must have lo <= hi");
+        Interval { lo, hi }
+    }
+
+    // both intervals share at least one point, touching ends count
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        self.lo <= other.hi && other.lo <= self.hi
+    }
+
+    pub fn contains(&self, point: T) -> bool {
+        self.lo <= point && point <= self.hi
+    }
+}
+
+impl<T: Display> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+// every node keeps the largest hi in its subtree so whole subtrees ending before a query can be skipped
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MaxEnd;
+
+impl<T: Ord+Copy+Debug> Augment<Interval<T>> for MaxEnd {
+    type Value = T;
+
+    fn summarize(key: &Interval<T>, _occurrences: usize, left: Option<T>, right: Option<T>) -> T {
+        let mut max = key.hi;
+        for end in [left, right].into_iter().flatten() {
+            if end > max {
+                max = end;
+            }
+        }
+        max
+    }
+}
+
+// red-black tree of intervals keyed by their lo end, the same interval can be stored more than once
+#[derive(Clone, Debug)]
+pub struct IntervalTree<T: Ord+Display+Debug+Copy> {
+    tree: RBTree<Interval<T>, Natural, MaxEnd>,
+}
+
+impl<T> IntervalTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        IntervalTree {
+            tree: RBTree::multiset_augmented(Natural),
+        }
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        self.tree.insert(interval);
+    }
+
+    // remove one copy of interval, false if it was not in the tree
+    pub fn delete(&mut self, interval: Interval<T>) -> bool {
+        self.tree.remove_one(interval)
+    }
+
+    pub fn count(&self) -> u32 {
+        self.tree.count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.tree.height()
+    }
+
+    // largest hi of all the stored intervals
+    pub fn max_end(&self) -> Option<T> {
+        self.tree.root.as_ref().map(|root| root.borrow().aug)
+    }
+
+    // intervals ordered by lo and then hi
+    pub fn iter(&self) -> Iter<Interval<T>, MaxEnd> {
+        self.tree.iter()
+    }
+
+    // every stored interval sharing a point with query, in order
+    pub fn overlapping(&self, query: Interval<T>) -> Vec<Interval<T>> {
+        fn collect<T: Ord+Display+Debug+Copy>(tree: &Tree<Interval<T>, MaxEnd>, query: &Interval<T>, found: &mut Vec<Interval<T>>) {
+            let node = match tree {
+                Some(node) => node.borrow(),
+                None => return,
+            };
+            // nothing in this subtree ends late enough to reach the query
+            if node.aug < query.lo {
+                return;
+            }
+            collect(&node.left, query, found);
+            if node.key.overlaps(query) {
+                for _ in 0..node.occurrences {
+                    found.push(node.key);
+                }
+            }
+            // everything on the right starts at or after this node, so stop once that is past the query
+            if node.key.lo <= query.hi {
+                collect(&node.right, query, found);
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(&self.tree.root, &query, &mut found);
+        found
+    }
+
+    // every stored interval containing point
+    pub fn stabbing(&self, point: T) -> Vec<Interval<T>> {
+        self.overlapping(Interval::new(point, point))
+    }
+
+    // some interval overlapping query, following a single path down the tree
+    pub fn any_overlap(&self, query: Interval<T>) -> Option<Interval<T>> {
+        let mut cur = self.tree.root.clone();
+        while let Some(node) = cur {
+            let node = node.borrow();
+            if node.key.overlaps(&query) {
+                return Some(node.key);
+            }
+            // if the left side reaches query.lo it either holds an overlap or nothing on the right can
+            let go_left = match node.left {
+                Some(ref left) => left.borrow().aug >= query.lo,
+                None => false,
+            };
+            cur = if go_left { node.left.clone() } else { node.right.clone() };
+        }
+        None
+    }
+}
+
+impl<T> Default for IntervalTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Display for IntervalTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tree)
+    }
+}
+
+
+// check every node's max end against its subtree, returns the subtree's max end
+#[cfg(test)]
+fn check_max_end(tree: &Tree<Interval<u32>, MaxEnd>) -> Option<u32> {
+    let node = tree.as_ref()?.borrow();
+    let mut max = node.key.hi;
+    for end in [check_max_end(&node.left), check_max_end(&node.right)].into_iter().flatten() {
+        max = max.max(end);
+    }
+    assert_eq!(node.aug, max);
+    Some(max)
+}
+
+#[test]
+pub fn test_interval_overlaps() {
+    let a = Interval::new(1, 5);
+    assert!(a.overlaps(&Interval::new(5, 9)));
+    assert!(a.overlaps(&Interval::new(2, 3)));
+    assert!(!a.overlaps(&Interval::new(6, 9)));
+    assert!(a.contains(1));
+    assert!(!a.contains(6));
+    assert_eq!(format!("{}", a), "[1, 5]");
+}
+
+#[test]
+pub fn test_interval_tree_queries() {
+    let mut tree = IntervalTree::new();
+    for (lo, hi) in [(15, 20), (10, 30), (17, 19), (5, 20), (12, 15), (30, 40)] {
+        tree.insert(Interval::new(lo, hi));
+    }
+    assert_eq!(tree.count(), 6);
+    assert_eq!(tree.max_end(), Some(40));
+    check_max_end(&tree.tree.root);
+
+    assert_eq!(tree.overlapping(Interval::new(14, 16)),
+        vec![Interval::new(5, 20), Interval::new(10, 30), Interval::new(12, 15), Interval::new(15, 20)]);
+    assert_eq!(tree.stabbing(30), vec![Interval::new(10, 30), Interval::new(30, 40)]);
+    assert_eq!(tree.stabbing(41), vec![]);
+    assert!(tree.any_overlap(Interval::new(21, 23)).unwrap().overlaps(&Interval::new(21, 23)));
+    assert_eq!(tree.any_overlap(Interval::new(41, 50)), None);
+    assert_eq!(tree.any_overlap(Interval::new(0, 4)), None);
+
+    assert!(tree.delete(Interval::new(30, 40)));
+    assert!(!tree.delete(Interval::new(30, 40)));
+    assert_eq!(tree.max_end(), Some(30));
+    assert_eq!(tree.stabbing(35), vec![]);
+    check_max_end(&tree.tree.root);
+}
+
+#[test]
+pub fn test_interval_tree_duplicates() {
+    let mut tree = IntervalTree::new();
+    tree.insert(Interval::new(1, 3));
+    tree.insert(Interval::new(1, 3));
+    tree.insert(Interval::new(2, 8));
+    assert_eq!(tree.count(), 3);
+    assert_eq!(tree.stabbing(2), vec![Interval::new(1, 3), Interval::new(1, 3), Interval::new(2, 8)]);
+    tree.delete(Interval::new(2, 8));
+    // the max end has to drop back once the long interval is gone
+    assert_eq!(tree.max_end(), Some(3));
+    tree.delete(Interval::new(1, 3));
+    assert_eq!(tree.stabbing(2), vec![Interval::new(1, 3)]);
+}
+
+#[test]
+pub fn test_interval_tree_random() {
+    // compare against a plain list, checking the max ends after every operation
+    let mut tree = IntervalTree::new();
+    let mut list: Vec<Interval<u32>> = Vec::new();
+    let mut state: u32 = 2463534242;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+    for _ in 0..2000 {
+        let lo = next() % 200;
+        let interval = Interval::new(lo, lo + next() % 30);
+        if next() % 3 == 0 && !list.is_empty() {
+            let victim = list.remove(next() as usize % list.len());
+            assert!(tree.delete(victim));
+        } else {
+            tree.insert(interval);
+            list.push(interval);
+        }
+        check_max_end(&tree.tree.root);
+
+        let query = Interval::new(lo, lo + next() % 10);
+        let mut expected: Vec<Interval<u32>> = list.iter().copied().filter(|i| i.overlaps(&query)).collect();
+        expected.sort();
+        assert_eq!(tree.overlapping(query), expected);
+        assert_eq!(tree.any_overlap(query).is_some(), !expected.is_empty());
+    }
+    assert_eq!(tree.count() as usize, list.len());
+}
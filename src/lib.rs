@@ -1,3 +1,16 @@
 pub mod rbtree;
 pub mod avltree;
-pub mod bst;
\ No newline at end of file
+pub mod bst;
+pub mod splaytree;
+pub mod treap;
+pub mod btree;
+pub mod llrbtree;
+pub mod aatree;
+pub mod scapegoattree;
+pub mod wbtree;
+pub mod rng;
+pub mod compare;
+pub mod augment;
+pub mod intervaltree;
+pub mod shape;
+pub mod memory;
\ No newline at end of file
@@ -0,0 +1,590 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use crate::shape::{Attr, Shape};
+use crate::compare::{Comparator, Natural};
+
+type TreeNode<T> = Rc<RefCell<Node<T>>>;
+type Tree<T> = Option<TreeNode<T>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NodeColor {
+    Red,
+    Black,
+}
+
+#[derive(Clone)]
+struct Node<T: Display+Debug> {
+    key: T,
+    // color of the link from the parent, a red link glues the node to its parent as one 3-node
+    color: NodeColor,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T>
+where T: Debug+Display+Copy
+{
+    pub fn new(key: T) -> TreeNode<T> {
+        Rc::new(RefCell::new(Node {
+            key,
+            color: NodeColor::Red,
+            left: None,
+            right: None,
+        }))
+    }
+}
+
+impl<T> fmt::Debug for Node<T>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+         .field("key", &self.key)
+         .field("color", &self.color)
+         .field("right", &self.right)
+         .field("left", &self.left)
+         .finish()
+    }
+}
+
+fn is_red<T: Display+Debug>(tree: &Tree<T>) -> bool {
+    tree.as_ref().is_some_and(|node| node.borrow().color == NodeColor::Red)
+}
+
+fn left<T: Display+Debug>(node: &TreeNode<T>) -> Tree<T> {
+    node.borrow().left.clone()
+}
+
+fn right<T: Display+Debug>(node: &TreeNode<T>) -> Tree<T> {
+    node.borrow().right.clone()
+}
+
+// is the left child of the left child of tree red
+fn is_red_left_left<T: Display+Debug>(tree: &Tree<T>) -> bool {
+    is_red(&tree.as_ref().and_then(left))
+}
+
+fn rotate_left<T: Display+Debug>(h: TreeNode<T>) -> TreeNode<T> {
+    let x = h.borrow_mut().right.take().unwrap();
+    h.borrow_mut().right = x.borrow_mut().left.take();
+    let color = h.borrow().color;
+    x.borrow_mut().color = color;
+    h.borrow_mut().color = NodeColor::Red;
+    x.borrow_mut().left = Some(h);
+    x
+}
+
+fn rotate_right<T: Display+Debug>(h: TreeNode<T>) -> TreeNode<T> {
+    let x = h.borrow_mut().left.take().unwrap();
+    h.borrow_mut().left = x.borrow_mut().right.take();
+    let color = h.borrow().color;
+    x.borrow_mut().color = color;
+    h.borrow_mut().color = NodeColor::Red;
+    x.borrow_mut().right = Some(h);
+    x
+}
+
+// split a 4-node on the way up, or join siblings into one on the way down
+fn flip_colors<T: Display+Debug>(h: &TreeNode<T>) {
+    for node in [Some(h.clone()), left(h), right(h)].into_iter().flatten() {
+        let mut node = node.borrow_mut();
+        node.color = if node.color == NodeColor::Red { NodeColor::Black } else { NodeColor::Red };
+    }
+}
+
+// restore the left-leaning invariants on the way back up
+fn fix_up<T: Display+Debug>(mut h: TreeNode<T>) -> TreeNode<T> {
+    if is_red(&right(&h)) && !is_red(&left(&h)) {
+        h = rotate_left(h);
+    }
+    if is_red(&left(&h)) && is_red_left_left(&left(&h)) {
+        h = rotate_right(h);
+    }
+    if is_red(&left(&h)) && is_red(&right(&h)) {
+        flip_colors(&h);
+    }
+    h
+}
+
+// make h.left or one of its children red before descending left to delete
+fn move_red_left<T: Display+Debug>(mut h: TreeNode<T>) -> TreeNode<T> {
+    flip_colors(&h);
+    if is_red_left_left(&right(&h)) {
+        let right = h.borrow_mut().right.take().unwrap();
+        h.borrow_mut().right = Some(rotate_right(right));
+        h = rotate_left(h);
+        flip_colors(&h);
+    }
+    h
+}
+
+fn move_red_right<T: Display+Debug>(mut h: TreeNode<T>) -> TreeNode<T> {
+    flip_colors(&h);
+    if is_red_left_left(&left(&h)) {
+        h = rotate_right(h);
+        flip_colors(&h);
+    }
+    h
+}
+
+// remove the smallest key below h, returns what is left of the subtree and the key
+fn delete_min<T: Display+Debug+Copy>(mut h: TreeNode<T>) -> (Tree<T>, T) {
+    if h.borrow().left.is_none() {
+        let key = h.borrow().key;
+        return (None, key);
+    }
+    if !is_red(&left(&h)) && !is_red_left_left(&left(&h)) {
+        h = move_red_left(h);
+    }
+    let left = h.borrow_mut().left.take().unwrap();
+    let (left, key) = delete_min(left);
+    h.borrow_mut().left = left;
+    (Some(fix_up(h)), key)
+}
+
+// Sedgewick's left-leaning red-black tree: a red-black tree where red links only lean left, which
+// makes it a 1-1 encoding of a 2-3 tree. Insert and delete are short recursive functions that fix
+// the invariants on the way back up instead of the case analysis in RBTree's insert_fix/delete_fix.
+#[derive(Clone, Debug)]
+pub struct LLRBTree<T: Display+Debug+Copy, C = Natural> {
+    root: Tree<T>,
+    count: usize,
+    cmp: C,
+}
+
+impl<T> LLRBTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C> LLRBTree<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        LLRBTree {
+            root: None,
+            count: 0,
+            cmp,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn insert(&mut self, key: T) {
+        let mut inserted = false;
+        let root = self.root.take();
+        let root = self.insert_node(root, key, &mut inserted);
+        root.borrow_mut().color = NodeColor::Black;
+        self.root = Some(root);
+        if inserted {
+            self.count += 1;
+        }
+    }
+
+    fn insert_node(&self, tree: Tree<T>, key: T, inserted: &mut bool) -> TreeNode<T> {
+        let h = match tree {
+            Some(h) => h,
+            None => {
+                *inserted = true;
+                return Node::new(key);
+            }
+        };
+        let order = self.cmp.compare(&key, &h.borrow().key);
+        match order {
+            Ordering::Less => {
+                let left = h.borrow_mut().left.take();
+                h.borrow_mut().left = Some(self.insert_node(left, key, inserted));
+            },
+            Ordering::Greater => {
+                let right = h.borrow_mut().right.take();
+                h.borrow_mut().right = Some(self.insert_node(right, key, inserted));
+            },
+            // key already in tree
+            Ordering::Equal => return h,
+        }
+        fix_up(h)
+    }
+
+    pub fn delete(&mut self, key: T) {
+        // the top-down delete below assumes the key is there
+        if !self.contains(&key) {
+            return;
+        }
+        let root = self.root.take().unwrap();
+        if !is_red(&left(&root)) && !is_red(&right(&root)) {
+            root.borrow_mut().color = NodeColor::Red;
+        }
+        self.root = self.delete_node(root, &key);
+        if let Some(root) = &self.root {
+            root.borrow_mut().color = NodeColor::Black;
+        }
+        self.count -= 1;
+    }
+
+    fn delete_node(&self, mut h: TreeNode<T>, key: &T) -> Tree<T> {
+        if self.cmp.compare(key, &h.borrow().key) == Ordering::Less {
+            if !is_red(&left(&h)) && !is_red_left_left(&left(&h)) {
+                h = move_red_left(h);
+            }
+            let left = h.borrow_mut().left.take().unwrap();
+            h.borrow_mut().left = self.delete_node(left, key);
+        } else {
+            if is_red(&left(&h)) {
+                h = rotate_right(h);
+            }
+            if self.cmp.compare(key, &h.borrow().key) == Ordering::Equal && h.borrow().right.is_none() {
+                return None;
+            }
+            if !is_red(&right(&h)) && !is_red_left_left(&right(&h)) {
+                h = move_red_right(h);
+            }
+            let right = h.borrow_mut().right.take().unwrap();
+            if self.cmp.compare(key, &h.borrow().key) == Ordering::Equal {
+                // replace the key with its successor and delete that from the right subtree instead
+                let (right, successor) = delete_min(right);
+                h.borrow_mut().key = successor;
+                h.borrow_mut().right = right;
+            } else {
+                h.borrow_mut().right = self.delete_node(right, key);
+            }
+        }
+        Some(fix_up(h))
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.find(key).map(|node| node.borrow().key)
+    }
+
+    // the node holding key, private so callers cannot change a key and break the ordering
+    fn find(&self, key: &T) -> Tree<T> {
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    // check key order, that red links lean left and never come two in a row, and that every path
+    // from the root down has the same number of black links
+    pub fn validate(&self) -> Result<(), String> {
+        if is_red(&self.root) {
+            return Err("the root is red".to_string());
+        }
+        let size = self.check(&self.root, None, None)?.1;
+        if size != self.count {
+            return Err(format!("count is {} but the tree holds {} keys", self.count, size));
+        }
+        Ok(())
+    }
+
+    // returns the black height and the size of the subtree
+    fn check(&self, tree: &Tree<T>, lo: Option<T>, hi: Option<T>) -> Result<(u32, usize), String> {
+        let node = match tree {
+            Some(node) => node.borrow(),
+            None => return Ok((1, 0)),
+        };
+        if lo.is_some_and(|lo| self.cmp.compare(&node.key, &lo) != Ordering::Greater)
+            || hi.is_some_and(|hi| self.cmp.compare(&node.key, &hi) != Ordering::Less) {
+            return Err(format!("{} is out of order", node.key));
+        }
+        if is_red(&node.right) {
+            return Err(format!("red link leans right below {}", node.key));
+        }
+        if node.color == NodeColor::Red && is_red(&node.left) {
+            return Err(format!("two red links in a row below {}", node.key));
+        }
+        let (left_height, left_size) = self.check(&node.left, lo, Some(node.key))?;
+        let (right_height, right_size) = self.check(&node.right, Some(node.key), hi)?;
+        if left_height != right_height {
+            return Err(format!("black heights {} and {} differ below {}", left_height, right_height, node.key));
+        }
+        let height = left_height + if node.color == NodeColor::Black { 1 } else { 0 };
+        Ok((height, left_size + right_size + 1))
+    }
+
+    pub fn height(&self) -> u32 {
+        if self.root.is_none() {
+            return 0;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        let mut height = 0;
+        // find height by breadth first search traversal
+        while !queue.is_empty() {
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                    queue.push_back(child);
+                }
+            }
+            height += 1;
+        }
+        height
+    }
+
+    pub fn leaves(&self) -> u32 {
+        let mut stack: Vec<TreeNode<T>> = self.root.clone().into_iter().collect();
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            let left = node.borrow().left.clone();
+            let right = node.borrow().right.clone();
+            if left.is_none() && right.is_none() {
+                count += 1;
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        count
+    }
+
+    // iterate over the keys in order
+    pub fn iter(&self) -> Iter<T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    pub fn print_preorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut stack: Vec<TreeNode<T>> = vec![self.root.clone().unwrap()];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", node.borrow().key);
+            stack.extend(node.borrow().right.clone());
+            stack.extend(node.borrow().left.clone());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", node.borrow().key);
+            queue.extend(node.borrow().left.clone());
+            queue.extend(node.borrow().right.clone());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout with the color of the link into each node, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T>| {
+            let node = node.borrow();
+            let color = match node.color {
+                NodeColor::Red => "red",
+                NodeColor::Black => "black",
+            };
+            (vec![node.key], vec![("color", Attr::Text(color))], vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        };
+
+        fn pretty_print<T: Display+Debug>(node: TreeNode<T>, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&node.borrow().key.to_string());
+            if node.borrow().color == NodeColor::Red {
+                buffer.push_str(" (red)");
+            }
+            buffer.push('\n');
+            for child in [node.borrow().right.clone(), node.borrow().left.clone()].into_iter().flatten() {
+                let has_children = child.borrow().left.is_some() || child.borrow().right.is_some();
+                let (branch, indent) = if has_children { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(self.root.clone().unwrap(), &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for LLRBTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy> {
+    stack: Vec<TreeNode<T>>,
+}
+
+impl<T> Iter<T>
+where T: Display+Debug+Copy
+{
+    fn push_left(&mut self, tree: Tree<T>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T>
+where T: Display+Debug+Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let key = node.borrow().key;
+        self.push_left(node.borrow().right.clone());
+        Some(key)
+    }
+}
+
+impl<T, C> fmt::Display for LLRBTree<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LLRBTree")
+         .field("root", &self.root)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+
+#[test]
+pub fn create_empty_llrbtree() {
+    let tree: LLRBTree<u32> = LLRBTree::new();
+    assert!(tree.is_empty());
+    assert_eq!(tree.height(), 0);
+    assert!(tree.first().is_none());
+    assert_eq!(tree.validate(), Ok(()));
+}
+
+#[test]
+pub fn test_llrb_leans_left() {
+    let mut tree = LLRBTree::new();
+    tree.insert(1);
+    tree.insert(2);
+    // a right leaning red link gets rotated, so 2 is the root and 1 its red left child
+    let root = tree.root.clone().unwrap();
+    assert_eq!(root.borrow().key, 2);
+    assert!(is_red(&root.borrow().left));
+    tree.insert(3);
+    // a 4-node splits into three black nodes
+    assert_eq!(tree.root.as_ref().unwrap().borrow().key, 2);
+    assert!(!is_red(&tree.root.as_ref().unwrap().borrow().left));
+    assert_eq!(tree.validate(), Ok(()));
+}
+
+#[test]
+pub fn test_llrb_sorted_inserts_and_deletes() {
+    let mut tree = LLRBTree::new();
+    for key in 0..1024 {
+        tree.insert(key);
+    }
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.height() <= 20);
+    for key in (0..1024).step_by(2) {
+        tree.delete(key);
+    }
+    tree.delete(5000);
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.count(), 512);
+    assert_eq!(tree.first().unwrap(), 1);
+    assert_eq!(tree.last().unwrap(), 1023);
+}
+
+#[test]
+pub fn test_llrb_random() {
+    let mut tree = LLRBTree::new();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut rng = crate::rng::Rng::new(34);
+    for _ in 0..3000 {
+        let key = rng.below(500) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+            keys.remove(&key);
+        } else {
+            tree.insert(key);
+            keys.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_llrb_reverse_comparator() {
+    let mut tree = LLRBTree::with_comparator(crate::compare::Reverse);
+    for key in [3, 1, 2] {
+        tree.insert(key);
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), vec![3, 2, 1]);
+    assert_eq!(tree.validate(), Ok(()));
+}
@@ -1,283 +1,267 @@
 use Rust_Trees::rbtree::RBTree;
 use Rust_Trees::avltree::AvlTree;
+use Rust_Trees::bst::BST;
+use Rust_Trees::splaytree::SplayTree;
+use Rust_Trees::treap::Treap;
+use Rust_Trees::btree::{BTree, BPlusTree};
+use Rust_Trees::llrbtree::LLRBTree;
+use Rust_Trees::aatree::AATree;
+use Rust_Trees::compare::{Comparator, Natural, TotalOrder};
+use Rust_Trees::rbtree::Step;
+use Rust_Trees::rng::Rng;
+use Rust_Trees::shape::Shape;
 use std::env;
+use std::fmt::Debug;
 use std::io;
+use std::process;
+
+mod cli {
+    pub mod args;
+    pub mod command;
+    pub mod compare;
+    pub mod json;
+    pub mod key;
+    pub mod script;
+    pub mod session;
+    pub mod snapshot;
+    pub mod stress;
+    pub mod tui;
+}
+
+use cli::args::{Format, KeyType, Options};
+use cli::command::Command;
+use cli::key::Key;
+use cli::session::Session;
+
+// treap priorities are random unless seeded, a script has to give the same shapes on every run
+const SCRIPT_SEED: u64 = 1;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let choice = args.get(1);
-    match choice {
-        Some(n) => {
-            if n == "rb" {
-                println!("You selected Red Black Tree!");
-                run_rbtree();
-            } else if n == "avl" {
-                println!("You selected AVL Tree!");
-                run_avltree();
-            } else {
-                println!("Invalid input. Please select either rb or avl (Ex. cargo run rb or cargon run avl).");
-            }
-        },
-        None => {println!("Invalid input. Please select either rb or avl (Ex. cargo run rb or cargon run avl).");}
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    };
+    if options.tree == "stress" {
+        cli::stress::run(&options);
+        return;
+    }
+    match options.key_type {
+        KeyType::U32 => select::<u32, _>(&options, Natural),
+        KeyType::I64 => select::<i64, _>(&options, Natural),
+        KeyType::Str => select::<&'static str, _>(&options, Natural),
+        // floats are not Ord, total_cmp gives them an order anyway
+        KeyType::F64 => select::<f64, _>(&options, TotalOrder),
     }
 }
 
-fn run_rbtree() {
-    let mut tree: RBTree<u32> = RBTree::<u32>::new();
-    let mut option: u32;
-    loop {
-        print_options();
-        option = handle_user_input();
-        let num: u32;
-        match option {
-            0 => {
-                println!("Exiting Application......");
-                break;
+fn select<K: Key, C: Comparator<K>+Debug+Clone>(options: &Options, cmp: C) {
+    match options.tree.as_str() {
+        "compare" if options.tui => eprintln!("compare has no tui, run cargo run compare on its own"),
+        "compare" => cli::compare::run(cmp, options),
+        "rb" => run(|| RBTree::with_comparator(cmp.clone()), "Red Black Tree", options),
+        "llrb" => run(|| LLRBTree::with_comparator(cmp.clone()), "Left-Leaning Red Black Tree", options),
+        "aa" => run(|| AATree::with_comparator(cmp.clone()), "AA Tree", options),
+        "avl" => run(|| AvlTree::with_comparator(cmp.clone()), "AVL Tree", options),
+        "bst" => run(|| BST::with_comparator(cmp.clone()), "Binary Search Tree", options),
+        "splay" => run(|| SplayTree::with_comparator(cmp.clone()), "Splay Tree", options),
+        "treap" => {
+            // one seed for the whole session, so undo and replay rebuild the same shapes
+            let seed = if options.script.is_some() { SCRIPT_SEED } else { Rng::from_entropy().next_u64() };
+            run(|| Treap::with_comparator_and_seed(cmp.clone(), seed), "Treap", options)
+        }
+        "btree" => run(|| BTree::with_comparator(cmp.clone()), "B-Tree", options),
+        "bplus" => run(|| BPlusTree::with_comparator(cmp.clone()), "B+Tree", options),
+        _ => println!("Invalid input. Please select one of rb, llrb, aa, avl, bst, splay, treap, btree, bplus, compare or stress (Ex. cargo run rb, cargo run compare, cargo run tui rb, cargo run stress --workload sorted --size 5000, cargo run avl --load keys.txt or cargo run btree --script ops.txt --format json --key-type string)."),
+    }
+}
+
+// a script runs straight through and exits non-zero on a bad line, otherwise start the menu. either
+// way the tree starts out with the keys from --load, which undo can take back like any other load
+fn run<K: Key, T: MenuTree<K>, F: Fn() -> T>(fresh: F, name: &str, options: &Options) {
+    let mut session = Session::new(fresh);
+    if let Some(path) = &options.load {
+        match session.record(Command::Load(path.clone())) {
+            Ok(loaded) if options.script.is_none() => println!("Loaded {} key(s) from {}", loaded, path),
+            Ok(_) => {}
+            Err(message) => {
+                eprintln!("{}", message);
+                process::exit(1);
+            }
+        }
+    }
+    match &options.script {
+        Some(path) => {
+            if let Err(message) = cli::script::run_file(&mut session, path, options.format) {
+                eprintln!("{}", message);
+                process::exit(1);
             }
-            1 => {
-                // insert node
-                println!("Enter a number you would like to insert: ");
-                num = handle_user_input();
-                tree.insert(num);
-            },
-            2 => {
-                // delete node
-                println!("Enter key of node you would like to delete: ");
-                num = handle_user_input();
-                tree.delete(num);
-            },
-            3 => {
-                // count leaves
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("There are {} leaf node(s) in the tree.", tree.leaves());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            4 => {
-                // count leaves
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("The height of the tree is {}", tree.height());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            5 => {
-                // in-order traversal
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                tree.print_inorder();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            6 => {
-                // is tree empty
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("Is tree empty? {}", tree.is_empty());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            7 => {
-                // print the tree
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                //println!("{:#?}",tree);
-                tree.print_tree();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            8 => {
-                // number of nodes
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("There are {} node(s) in the tree", tree.count());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            9 => {
-                // search for a node
-                println!("Enter key of node you would like to search for: ");
-                num = handle_user_input();
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree.search(num));
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            10 => {
-                // find minimum
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree.min());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            11 => {
-                // find maximum
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree.max());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            12 => {
-                // pre-order traversal
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                tree.print_preorder();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            13 => {
-                // level-order traversal
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                tree.print_levelorder();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            14 => {
-                // debug print
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree);
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            _ => {break;}
+        }
+        None if options.tui => cli::tui::run(session, name),
+        None => {
+            println!("You selected {}!", name);
+            run_tree(session, options.format);
+        }
+    }
+}
+
+// everything the menu needs from a tree, so every tree runs through the same loop
+pub trait MenuTree<K>: Debug {
+    fn insert(&mut self, key: K);
+    fn delete(&mut self, key: K);
+    fn leaves(&self) -> u32;
+    fn height(&self) -> u32;
+    fn print_inorder(&self);
+    fn is_empty(&self) -> bool;
+    fn print_tree(&self);
+    fn count(&self) -> usize;
+    // search takes the tree mutably since a splay tree reorganises itself on every lookup
+    fn search(&mut self, key: K) -> Option<K>;
+    fn contains(&mut self, key: K) -> bool;
+    fn min(&self) -> Option<K>;
+    fn max(&self) -> Option<K>;
+    fn shape(&self) -> Shape<K>;
+    fn print_preorder(&self);
+    fn print_levelorder(&self);
+    // the keys in order, as the tree's own iterator gives them
+    fn inorder(&self) -> Vec<K>;
+    // the keys the tree holds, each node's before its children's and level by level. menu_tree!(Tree,
+    // leaf_keys) lists a B+tree's leaves for both, its separators are routing copies that can outlive
+    // a deleted key
+    fn preorder(&self) -> Vec<K> where K: Copy {
+        self.shape().preorder().into_iter().copied().collect()
+    }
+    fn levelorder(&self) -> Vec<K> where K: Copy {
+        self.shape().levelorder().into_iter().copied().collect()
+    }
+    // trees that can record how they rebalance override these, see RBTree::trace
+    fn trace(&mut self, _on: bool) {}
+    fn take_steps(&mut self) -> Vec<Step<K>> {
+        Vec::new()
+    }
+}
+
+// forwards to the tree's own methods, spelled out as <$tree<K, C>>::method so the trait method never
+// calls itself. menu_tree!(Tree, traced) also forwards trace and take_steps
+macro_rules! menu_tree {
+    (@traced $tree:ident) => {
+        fn trace(&mut self, on: bool) { <$tree<K, C>>::trace(self, on) }
+        fn take_steps(&mut self) -> Vec<Step<K>> { <$tree<K, C>>::take_steps(self) }
+    };
+    // every key sits in a leaf and the leaves run left to right, so they are read in order
+    (@leaf_keys $tree:ident) => {
+        fn preorder(&self) -> Vec<K> { <$tree<K, C>>::iter(self).collect() }
+        fn levelorder(&self) -> Vec<K> { <$tree<K, C>>::iter(self).collect() }
+    };
+    ($tree:ident $(, $extra:ident)?) => {
+        impl<K: Key, C: Comparator<K>+Debug> MenuTree<K> for $tree<K, C> {
+            fn insert(&mut self, key: K) { <$tree<K, C>>::insert(self, key) }
+            fn delete(&mut self, key: K) { <$tree<K, C>>::delete(self, key) }
+            fn leaves(&self) -> u32 { <$tree<K, C>>::leaves(self) }
+            fn height(&self) -> u32 { <$tree<K, C>>::height(self) as u32 }
+            fn print_inorder(&self) { <$tree<K, C>>::print_inorder(self) }
+            fn inorder(&self) -> Vec<K> { <$tree<K, C>>::iter(self).collect() }
+            fn is_empty(&self) -> bool { <$tree<K, C>>::is_empty(self) }
+            fn print_tree(&self) { <$tree<K, C>>::print_tree(self) }
+            fn count(&self) -> usize { <$tree<K, C>>::count(self) as usize }
+            fn search(&mut self, key: K) -> Option<K> { <$tree<K, C>>::get(self, &key) }
+            fn contains(&mut self, key: K) -> bool { <$tree<K, C>>::contains(self, &key) }
+            fn min(&self) -> Option<K> { <$tree<K, C>>::first(self) }
+            fn max(&self) -> Option<K> { <$tree<K, C>>::last(self) }
+            fn shape(&self) -> Shape<K> { <$tree<K, C>>::shape(self) }
+            fn print_preorder(&self) { <$tree<K, C>>::print_preorder(self) }
+            fn print_levelorder(&self) { <$tree<K, C>>::print_levelorder(self) }
+            $(menu_tree!(@$extra $tree);)?
         }
     };
 }
 
-fn run_avltree() {
-    let mut tree: AvlTree<u32> = AvlTree::<u32>::new();
-    let mut option: u32;
+menu_tree!(RBTree, traced);
+menu_tree!(LLRBTree);
+menu_tree!(AATree);
+menu_tree!(AvlTree);
+menu_tree!(BST);
+menu_tree!(SplayTree);
+menu_tree!(Treap);
+menu_tree!(BTree);
+menu_tree!(BPlusTree, leaf_keys);
+
+fn run_tree<K: Key, T: MenuTree<K>, F: Fn() -> T>(mut session: Session<K, T, F>, format: Format) {
     loop {
         print_options();
-        option = handle_user_input();
-        let num: u32;
-        match option {
-            0 => {
-                println!("Exiting Application......");
-                break;
-            }
-            1 => {
-                // insert node
-                println!("Enter a number you would like to insert: ");
-                num = handle_user_input();
-                tree.insert(num);
-            },
-            2 => {
-                // delete node
-                println!("Enter key of node you would like to delete: ");
-                num = handle_user_input();
-                tree.delete(num);
-            },
-            3 => {
-                // count leaves
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("There are {} leaf node(s) in the tree.", tree.leaves());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            4 => {
-                // return height
-                // height function needs to be public
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("The height of the tree is {}", tree.height());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            5 => {
-                // in-order traversal
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                tree.print_inorder();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            6 => {
-                // is tree empty
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("Is tree empty? {}", tree.is_empty());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            7 => {
-                // print the tree
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                tree.print_tree();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            8 => {
-                // number of nodes
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("There are {} node(s) in the tree", tree.count());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            9 => {
-                // search for a node
-                println!("Enter key of node you would like to search for: ");
-                num = handle_user_input();
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree.search(num));
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            10 => {
-                // find minimum
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree.min());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            11 => {
-                // find maximum
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree.max());
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            12 => {
-                // pre-order traversal
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                tree.print_preorder();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            13 => {
-                // level-order traversal
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                tree.print_levelorder();
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            14 => {
-                // debug print
-                println!(" ");
-                println!("PRINT OUTPUT ----------------------------------------------- ");
-                println!("{:#?}", tree);
-                println!("------------------------------------------------------ ");
-                println!(" ");
-            },
-            _ => {break;}
+        let command = match read_menu_command::<K>() {
+            Some(command) => command,
+            None => break,
+        };
+        if command == Command::Quit {
+            println!("Exiting Application......");
+            break;
+        }
+        if command.prints_output() {
+            println!(" ");
+            println!("PRINT OUTPUT ----------------------------------------------- ");
+            report(session.run(command, format));
+            println!("------------------------------------------------------ ");
+            println!(" ");
+        } else {
+            report(session.run(command, format));
         }
     };
 }
 
+// a file that cannot be loaded or saved is reported and the menu carries on
+fn report(result: Result<(), String>) {
+    if let Err(message) = result {
+        println!("{}", message);
+    }
+}
+
+// the command for a menu number, asking for a key where it needs one. None once stdin is closed
+fn read_menu_command<K: Key>() -> Option<Command<K>> {
+    let command = match handle_user_input(|line| line.parse::<u32>().ok())? {
+        0 => Command::Quit,
+        1 => {
+            println!("Enter a key you would like to insert: ");
+            Command::Insert(handle_user_input(K::parse_key)?)
+        },
+        2 => {
+            println!("Enter key of node you would like to delete: ");
+            Command::Delete(handle_user_input(K::parse_key)?)
+        },
+        3 => Command::Leaves,
+        4 => Command::Height,
+        5 => Command::Inorder,
+        6 => Command::IsEmpty,
+        7 => Command::Print,
+        8 => Command::Count,
+        9 => {
+            println!("Enter key of node you would like to search for: ");
+            Command::Search(handle_user_input(K::parse_key)?)
+        },
+        10 => Command::Min,
+        11 => Command::Max,
+        12 => Command::Preorder,
+        13 => Command::Levelorder,
+        14 => Command::Debug,
+        15 => {
+            println!("Enter the file to load keys from: ");
+            Command::Load(handle_user_input(read_path)?)
+        },
+        16 => {
+            println!("Enter the file to save the tree to: ");
+            Command::Save(handle_user_input(read_path)?)
+        },
+        17 => Command::Undo,
+        18 => Command::Redo,
+        19 => Command::History,
+        20 => Command::Replay,
+        _ => Command::Quit,
+    };
+    Some(command)
+}
+
 fn print_options() {
     println!("Select one of the following options: ");
     println!("0 to exit application");
@@ -295,21 +279,32 @@ fn print_options() {
     println!("12 to print pre-order traversal");
     println!("13 to print level-order traversal");
     println!("14 to debug print");
+    println!("15 to load keys from a file");
+    println!("16 to save the tree to a file");
+    println!("17 to undo the last insert, delete or load");
+    println!("18 to redo what was undone");
+    println!("19 to list the operations so far");
+    println!("20 to replay the operations on a fresh tree");
     println!(" ");
 }
 
-fn handle_user_input() -> u32 {
-    let mut selection = String::new();
-    io::stdin()
-        .read_line(&mut selection)
-        .expect("Failed to read line");
-    
-    let num;
-    match selection.trim().parse() {
-        Ok(x) => {num = x;}
-        Err(_) => {
-            panic!("Invalid input. Please try again.")
+fn read_path(line: &str) -> Option<String> {
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+// read a line with parse, asking again until it parses. None once stdin is closed
+fn handle_user_input<V, F: Fn(&str) -> Option<V>>(parse: F) -> Option<V> {
+    loop {
+        let mut selection = String::new();
+        let read = io::stdin()
+            .read_line(&mut selection)
+            .expect("Failed to read line");
+        if read == 0 {
+            return None;
+        }
+        match parse(selection.trim()) {
+            Some(value) => return Some(value),
+            None => println!("Invalid input. Please try again."),
         }
     }
-    num
 }
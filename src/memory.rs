@@ -0,0 +1,83 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::mem::{align_of, size_of};
+
+// Heap bytes held by a tree whose keys each live in their own Rc<RefCell<Node>>. Every node is one
+// allocation of the same size, laid out as Rc's strong and weak counts, RefCell's borrow flag and
+// then the node itself, so the per node fields below add up to node_bytes with padding taking the
+// rest. Only what is asked of the allocator is counted, not its own headers or size classes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemoryUsage {
+    pub nodes: usize,
+    // keys held, every occurrence in a multiset included
+    pub keys: usize,
+    pub node_bytes: usize,
+    pub key_bytes: usize,
+    pub rc_counter_bytes: usize,
+    pub borrow_flag_bytes: usize,
+    // left and right child pointers
+    pub child_bytes: usize,
+    pub parent_bytes: usize,
+    // color or height, whatever the tree balances on
+    pub balance_bytes: usize,
+    // occurrence counts and augmented summaries
+    pub extra_bytes: usize,
+    pub padding_bytes: usize,
+}
+
+// the bytes of one node's fields besides the key, as a tree reports them to MemoryUsage::of
+pub(crate) struct NodeFields {
+    pub(crate) child: usize,
+    pub(crate) parent: usize,
+    pub(crate) balance: usize,
+    pub(crate) extra: usize,
+}
+
+impl MemoryUsage {
+    // usage of nodes allocations of Rc<RefCell<N>>, N holding a K and fields
+    pub(crate) fn of<N, K>(nodes: usize, keys: usize, fields: NodeFields) -> MemoryUsage {
+        // RcBox is repr(C): the two counts, then the value at the next multiple of its alignment
+        let align = align_of::<RefCell<N>>().max(align_of::<usize>());
+        let rc_counter_bytes = 2 * size_of::<usize>();
+        let offset = rc_counter_bytes.next_multiple_of(align);
+        let node_bytes = (offset + size_of::<RefCell<N>>()).next_multiple_of(align);
+        let borrow_flag_bytes = size_of::<isize>();
+        let used = rc_counter_bytes + borrow_flag_bytes + size_of::<K>() + fields.child + fields.parent + fields.balance + fields.extra;
+        MemoryUsage {
+            nodes,
+            keys,
+            node_bytes,
+            key_bytes: size_of::<K>(),
+            rc_counter_bytes,
+            borrow_flag_bytes,
+            child_bytes: fields.child,
+            parent_bytes: fields.parent,
+            balance_bytes: fields.balance,
+            extra_bytes: fields.extra,
+            padding_bytes: node_bytes - used,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.nodes * self.node_bytes
+    }
+
+    // everything a node holds besides its key
+    pub fn overhead_per_node(&self) -> usize {
+        self.node_bytes - self.key_bytes
+    }
+
+    // total bytes over keys held, below node_bytes only when a multiset shares nodes between occurrences
+    pub fn bytes_per_key(&self) -> f64 {
+        if self.keys == 0 { 0.0 } else { self.total() as f64 / self.keys as f64 }
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} bytes in {} nodes for {} keys, {:.1} bytes per key", self.total(), self.nodes, self.keys, self.bytes_per_key())?;
+        write!(f, "each node: key {}, rc counters {}, borrow flag {}, children {}, parent {}, balance {}, extra {}, padding {}",
+            self.key_bytes, self.rc_counter_bytes, self.borrow_flag_bytes, self.child_bytes, self.parent_bytes,
+            self.balance_bytes, self.extra_bytes, self.padding_bytes)
+    }
+}
@@ -1,10 +1,17 @@
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::mem::replace;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt;
+use std::cmp::Ordering;
 use std::collections::VecDeque;
+use std::mem::size_of;
+use std::ops::Bound;
+use crate::shape::{Attr, Shape};
+use crate::compare::{Comparator, Natural};
+use crate::augment::{Augment, Aggregate, Monoid};
+use crate::bst::BST;
+use crate::memory::{MemoryUsage, NodeFields};
 
 #[allow(non_snake_case)]
 
@@ -14,26 +21,31 @@ enum NodeColor {
     Black,
 }
 
-type TreeNode<T> = Rc<RefCell<Node<T>>>;
-type Tree<T> = Option<TreeNode<T>>;
+pub(crate) type TreeNode<T, A = ()> = Rc<RefCell<Node<T, A>>>;
+pub(crate) type Tree<T, A = ()> = Option<TreeNode<T, A>>;
 
-#[derive(Clone)]
-pub struct Node<T: Ord+Display+Debug> {
+pub(crate) struct Node<T: Display+Debug, A: Augment<T> = ()> {
     color: NodeColor,
-    key: T,
-    parent: Tree<T>,
-    left: Tree<T>,
-    right: Tree<T>,
+    pub(crate) key: T,
+    // how many times key was inserted, only ever above 1 in a multiset
+    pub(crate) occurrences: u32,
+    // summary of the subtree rooted here, see Augment
+    pub(crate) aug: A::Value,
+    parent: Tree<T, A>,
+    pub(crate) left: Tree<T, A>,
+    pub(crate) right: Tree<T, A>,
 }
 
-impl<T> Node<T>
+impl<T, A> Node<T, A>
 where 
-    T: Debug+Ord+Display+Copy
+    T: Debug+Display+Copy, A: Augment<T>
 {
-    pub fn new(key: T) -> Tree<T> {
+    pub fn new(key: T) -> Tree<T, A> {
         Some(Rc::new(RefCell::new(Node {
             color: NodeColor::Red,
             key: key,
+            occurrences: 1,
+            aug: A::summarize(&key, 1, None, None),
             parent: None,
             left: None,
             right: None,
@@ -41,13 +53,31 @@ where
     }
 }
 
-impl<T> fmt::Debug for Node<T>
-where T: Debug+Ord+Display+Copy
+// written out by hand since derive would also require A: Clone
+impl<T, A> Clone for Node<T, A>
+where T: Display+Debug+Clone, A: Augment<T>
+{
+    fn clone(&self) -> Self {
+        Node {
+            color: self.color.clone(),
+            key: self.key.clone(),
+            occurrences: self.occurrences,
+            aug: self.aug,
+            parent: self.parent.clone(),
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl<T, A> fmt::Debug for Node<T, A>
+where T: Debug+Display+Copy, A: Augment<T>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Node")
          .field("color", &self.color)
          .field("key", &self.key)
+         .field("occurrences", &self.occurrences)
          .field("right", &self.right)
          .field("left", &self.left)
          .finish()
@@ -59,26 +89,197 @@ enum Direction {
     Right
 }
 
+// true if tree is the very node (not just an equal key), used to tell which side of its parent a node is on
+fn is_node<T: Display+Debug, A: Augment<T>>(tree: &Tree<T, A>, node: &TreeNode<T, A>) -> bool {
+    match tree {
+        Some(tree_node) => Rc::ptr_eq(tree_node, node),
+        None => false,
+    }
+}
+
+// the next node in order, climbing the parent pointers when there is no right subtree
+fn successor<T: Display+Debug, A: Augment<T>>(node: &TreeNode<T, A>) -> Tree<T, A> {
+    let right = node.borrow().right.clone();
+    if let Some(mut cur) = right {
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur),
+            }
+        }
+    }
+    let mut child = node.clone();
+    let mut parent = node.borrow().parent.clone();
+    while let Some(node) = parent {
+        if is_node(&node.borrow().left, &child) {
+            return Some(node);
+        }
+        parent = node.borrow().parent.clone();
+        child = node;
+    }
+    None
+}
+
+fn predecessor<T: Display+Debug, A: Augment<T>>(node: &TreeNode<T, A>) -> Tree<T, A> {
+    let left = node.borrow().left.clone();
+    if let Some(mut cur) = left {
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur),
+            }
+        }
+    }
+    let mut child = node.clone();
+    let mut parent = node.borrow().parent.clone();
+    while let Some(node) = parent {
+        if is_node(&node.borrow().right, &child) {
+            return Some(node);
+        }
+        parent = node.borrow().parent.clone();
+        child = node;
+    }
+    None
+}
+
+// None children count as black
+fn is_black<T: Display+Debug, A: Augment<T>>(tree: &Tree<T, A>) -> bool {
+    match tree {
+        Some(node) => node.borrow().color == NodeColor::Black,
+        None => true,
+    }
+}
+
+// rebuild node's summary from its key and its children's summaries
+fn update_aug<T: Display+Debug, A: Augment<T>>(node: &TreeNode<T, A>) {
+    let aug = {
+        let node = node.borrow();
+        let left = node.left.as_ref().map(|left| left.borrow().aug);
+        let right = node.right.as_ref().map(|right| right.borrow().aug);
+        A::summarize(&node.key, node.occurrences as usize, left, right)
+    };
+    node.borrow_mut().aug = aug;
+}
+
+// rebuild the summaries from node all the way up to the root
+fn update_aug_path<T: Display+Debug, A: Augment<T>>(tree: Tree<T, A>) {
+    let mut cur = tree;
+    while let Some(node) = cur {
+        update_aug(&node);
+        cur = node.borrow().parent.clone();
+    }
+}
+
 #[derive(Clone, Debug)]
-pub struct RBTree<T: Ord+Display+Debug+Copy> {
-    root: Tree<T>,
+pub struct RBTree<T: Display+Debug+Copy, C = Natural, A: Augment<T> = ()> {
+    pub(crate) root: Tree<T, A>,
     count: u32,
+    cmp: C,
+    multiset: bool,
+    rotations: u64,
+    // Some while tracing, see trace
+    steps: Option<Vec<Step<T>>>,
+}
+
+// one change made while inserting or deleting, with the tree as it was right after it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step<T> {
+    pub action: String,
+    pub shape: Shape<T>,
 }
 
 impl<T> RBTree<T>
 where T: Ord+Display+Debug+Clone+Copy
 {
     pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // a tree that keeps duplicate keys, counting every occurrence
+    pub fn new_multiset() -> Self {
+        Self::multiset_with_comparator(Natural)
+    }
+}
+
+impl<T, C> RBTree<T, C>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::augmented(cmp)
+    }
+
+    pub fn multiset_with_comparator(cmp: C) -> Self {
+        Self::multiset_augmented(cmp)
+    }
+}
+
+impl<T, C, A> RBTree<T, C, A>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>, A: Augment<T>
+{
+    // a tree whose nodes also keep A's summary of their subtree
+    pub fn augmented(cmp: C) -> Self {
         RBTree {
             root: None,
             count: 0,
+            cmp,
+            multiset: false,
+            rotations: 0,
+            steps: None,
         }
     }
 
+    pub fn multiset_augmented(cmp: C) -> Self {
+        RBTree {
+            multiset: true,
+            ..Self::augmented(cmp)
+        }
+    }
+
+    pub fn is_multiset(&self) -> bool {
+        self.multiset
+    }
+
+    // total number of keys, counting every occurrence in a multiset
     pub fn count(&self) -> u32 {
         self.count
     }
 
+    // single rotations done by inserts and deletes since the tree was made, a double rotation counts twice
+    pub fn rotations(&self) -> u64 {
+        self.rotations
+    }
+
+    // while on, every attach, removal, rotation and recolor records a Step, e.g. to animate an insert.
+    // a shape is taken for every step, so leave it off unless something reads the steps
+    pub fn trace(&mut self, on: bool) {
+        self.steps = if on { Some(Vec::new()) } else { None };
+    }
+
+    // the steps recorded since tracing started or since the last call
+    pub fn take_steps(&mut self) -> Vec<Step<T>> {
+        self.steps.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn step<F: FnOnce() -> String>(&mut self, action: F) {
+        if self.steps.is_some() {
+            let shape = self.shape();
+            if let Some(steps) = &mut self.steps {
+                steps.push(Step { action: action(), shape });
+            }
+        }
+    }
+
+    // how many times key is in the tree
+    pub fn count_of(&self, key: T) -> u32 {
+        match self.search_node(&self.root, &key) {
+            Some(node) => node.borrow().occurrences,
+            None => 0,
+        }
+    }
+
     // 6- check if tree is empty
     pub fn is_empty(&self) -> bool {
         if self.root.is_none() {
@@ -90,46 +291,51 @@ where T: Ord+Display+Debug+Clone+Copy
 
     // 1- insert a node to the red-black tree
     pub fn insert(&mut self, key: T) {
+        let multiset = self.multiset;
         // check if key already in tree
-        if self.search(key).is_none() {
-            // need to pass Tree<T> along with RBTree<T> or else we can't call associated functions
-            let root = replace(&mut self.root, None);
-            let updated_tree = self.insert_node(root, key);
-            self.root = self.insert_fix(updated_tree.1);
-        } else {
-            println!("Key already in tree");
+        match self.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(key);
+            },
+            Entry::Occupied(entry) => {
+                if multiset {
+                    // keep another occurrence in the existing node
+                    entry.node.borrow_mut().occurrences += 1;
+                    update_aug_path(Some(entry.node.clone()));
+                    entry.tree.count += 1;
+                } else {
+                    println!("Key already in tree");
+                }
+            }
         }
     }
 
-    fn insert_node(&mut self, tree: Tree<T>, key: T) -> (Tree<T>,TreeNode<T>) {
-        match tree {
-            Some(tree_node) => {
-                let sub_tree: TreeNode<T>;
-                let node_clone = tree_node.borrow().clone();
-                if key < node_clone.key {
-                    let res = self.insert_node(node_clone.left, key);
-                    let res_tree = res.0;
-                    sub_tree = res.1;
-                    res_tree.as_ref().unwrap().borrow_mut().parent = Some(tree_node.clone());
-                    tree_node.borrow_mut().left = res_tree;
-                } else {
-                    let res = self.insert_node(node_clone.right, key);
-                    let res_tree = res.0;
-                    sub_tree = res.1;
-                    res_tree.as_ref().unwrap().borrow_mut().parent = Some(tree_node.clone());
-                    tree_node.borrow_mut().right = res_tree;
-                };
-                (Some(tree_node),sub_tree)
-            },
-            None => {
-                self.count += 1;
-                let added_node = Node::<T>::new(key);
-                (added_node.clone(),added_node.unwrap())
+    // look key up once, returning either its node or the empty spot it would be inserted at
+    pub fn entry(&mut self, key: T) -> Entry<'_, T, C, A> {
+        let mut parent: Tree<T, A> = None;
+        let mut side = Direction::Left;
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(&key, &node.borrow().key);
+            match order {
+                Ordering::Equal => {
+                    return Entry::Occupied(OccupiedEntry { tree: self, node });
+                },
+                Ordering::Less => {
+                    cur = node.borrow().left.clone();
+                    side = Direction::Left;
+                },
+                Ordering::Greater => {
+                    cur = node.borrow().right.clone();
+                    side = Direction::Right;
+                }
             }
+            parent = Some(node);
         }
+        Entry::Vacant(VacantEntry { tree: self, key, parent, side })
     }
 
-    fn insert_fix(&mut self, tree_node: TreeNode<T>) -> Tree<T> {
+    fn insert_fix(&mut self, tree_node: TreeNode<T, A>) -> Tree<T, A> {
         let mut is_root = tree_node.borrow().parent.is_none(); // if parent is none, then we have root node
         let root = if is_root {
             Some(tree_node)
@@ -147,11 +353,11 @@ where T: Ord+Display+Debug+Clone+Copy
                 let node_clone = node.borrow().clone();
                 let uncle_return = match node_clone.parent {
                     Some(parent) => {
-                        let parent = parent.borrow().clone();
-                        match parent.parent {
+                        let parent_node = parent.borrow().clone();
+                        match parent_node.parent {
                             Some(grandparent) => {
                                 let grandparent = grandparent.borrow().clone();
-                                if grandparent.key < parent.key {
+                                if is_node(&grandparent.right, &parent) {
                                     Some((grandparent.left.clone(), Direction::Left))
                                 } else {
                                     Some((grandparent.right.clone(), Direction::Right))
@@ -180,10 +386,12 @@ where T: Ord+Display+Debug+Clone+Copy
                                     parent.borrow().parent.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
                                     // iteratively recurse up tree to check for any other red-black violations
                                     node = parent.borrow().clone().parent.clone().unwrap();
+                                    let key = node.borrow().key;
+                                    self.step(|| format!("recolor {} red and its children black", key));
                                 } else {
                                     // uncle is black (None counts as black too)
                                     // need to know whether current node is either on left or right side
-                                    if parent.borrow().clone().key < node.borrow().clone().key {
+                                    if is_node(&parent.borrow().right, &node) {
                                         // node is on right side 
                                         // rotate node left so that node becomes parent and parent becomes left child of node
                                         let parent_tmp = node.borrow().parent.as_ref().unwrap().clone();
@@ -195,6 +403,8 @@ where T: Ord+Display+Debug+Clone+Copy
                                     parent.borrow_mut().color = NodeColor::Black;
                                     parent.borrow().parent.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
                                     let grandparent = node.borrow().parent.as_ref().unwrap().borrow().parent.as_ref().unwrap().clone();
+                                    let (parent_key, grandparent_key) = (parent.borrow().key, grandparent.borrow().key);
+                                    self.step(|| format!("recolor {} black and {} red", parent_key, grandparent_key));
                                     // rotate parent right so that grandparent becomes right child
                                     self.rotate_right(grandparent);
                                 }
@@ -210,10 +420,12 @@ where T: Ord+Display+Debug+Clone+Copy
                                     parent.borrow().parent.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
                                     // iteratively recurse up tree to check for any other red-black violations
                                     node = parent.borrow().clone().parent.clone().unwrap();
+                                    let key = node.borrow().key;
+                                    self.step(|| format!("recolor {} red and its children black", key));
                                 } else {
                                     // uncle is black
                                     // need to know whether current node is either left or right child of parent
-                                    if parent.borrow().clone().key > node.borrow().clone().key {
+                                    if is_node(&parent.borrow().left, &node) {
                                         // node is on left side
                                         // rotate node right so that node becomes parent and parent becomes right child of node
                                         let parent_tmp = node.borrow().parent.as_ref().unwrap().clone();
@@ -224,6 +436,8 @@ where T: Ord+Display+Debug+Clone+Copy
                                     parent.borrow_mut().color = NodeColor::Black;
                                     parent.borrow().parent.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
                                     let grandparent = node.borrow().parent.as_ref().unwrap().borrow().parent.as_ref().unwrap().clone();
+                                    let (parent_key, grandparent_key) = (parent.borrow().key, grandparent.borrow().key);
+                                    self.step(|| format!("recolor {} black and {} red", parent_key, grandparent_key));
                                     self.rotate_left(grandparent);
                                 }
                             }
@@ -247,11 +461,16 @@ where T: Ord+Display+Debug+Clone+Copy
             }
             Some(node)
         };
-        root.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
+        let root_key = root.as_ref().unwrap().borrow().key;
+        if root.as_ref().unwrap().borrow().color == NodeColor::Red {
+            root.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
+            self.step(|| format!("recolor the root {} black", root_key));
+        }
         root
     }
 
-    fn rotate_left(&self, tree_node: TreeNode<T>) {
+    fn rotate_left(&mut self, tree_node: TreeNode<T, A>) {
+        self.rotations += 1;
         let cur_parent = tree_node;
         let right_child = cur_parent.borrow().right.clone();
 
@@ -273,24 +492,31 @@ where T: Ord+Display+Debug+Clone+Copy
 
         match cur_parent.borrow().clone().parent {
             Some(grandparent) => {
-                if grandparent.borrow().clone().key < cur_parent.borrow().clone().key {
+                if is_node(&grandparent.borrow().right, &cur_parent) {
                     grandparent.borrow_mut().right = right_child.clone();
                 } else {
                     grandparent.borrow_mut().left = right_child.clone();
                 }
             },
             None => {
-                // grandparent is None, so make the right_child's parent None
+                // grandparent is None, so make the right_child's parent None and make it the new root
                 right_child.as_ref().unwrap().borrow_mut().parent = None;
+                self.root = right_child.clone();
             },
         }
         // make right_child's left child equal to the parent
         right_child.as_ref().unwrap().borrow_mut().left = Some(cur_parent.clone());
         // make parent's parent equal to right_child
         cur_parent.borrow_mut().parent = right_child.clone();
+        // the parent is now below right_child, so its summary has to be rebuilt first
+        update_aug(&cur_parent);
+        update_aug(right_child.as_ref().unwrap());
+        let key = cur_parent.borrow().key;
+        self.step(|| format!("rotate left at {}", key));
     }
 
-    fn rotate_right(&self, tree_node: TreeNode<T>) {
+    fn rotate_right(&mut self, tree_node: TreeNode<T, A>) {
+        self.rotations += 1;
         let cur_parent = tree_node;
         let left_child = cur_parent.borrow().left.clone();
 
@@ -312,79 +538,104 @@ where T: Ord+Display+Debug+Clone+Copy
 
         match cur_parent.borrow().clone().parent {
             Some(grandparent) => {
-                if grandparent.borrow().clone().key < cur_parent.borrow().clone().key {
+                if is_node(&grandparent.borrow().right, &cur_parent) {
                     grandparent.borrow_mut().right = left_child.clone();
                 } else {
                     grandparent.borrow_mut().left = left_child.clone();
                 }
             },
             None => {
-                // grandparent is None, so make the left_child's parent None
+                // grandparent is None, so make the left_child's parent None and make it the new root
                 left_child.as_ref().unwrap().borrow_mut().parent = None;
+                self.root = left_child.clone();
             },
         }
         // make left_child's right child equal to the parent
         left_child.as_ref().unwrap().borrow_mut().right = Some(cur_parent.clone());
         // make parent's parent equal to left_child
         cur_parent.borrow_mut().parent = left_child.clone();
+        update_aug(&cur_parent);
+        update_aug(left_child.as_ref().unwrap());
+        let key = cur_parent.borrow().key;
+        self.step(|| format!("rotate right at {}", key));
     }
 
-    pub fn search(&self, key: T) -> Tree<T> {
-        let dummy = Node::<T>::new(key).unwrap().borrow().clone();
-        self.search_node(&self.root, &dummy)
+    pub fn contains(&self, key: &T) -> bool {
+        self.search_node(&self.root, key).is_some()
     }
 
-    fn search_node(&self, tree_node: &Tree<T>, node: &Node<T>) -> Tree<T> {
-        match tree_node {
-            Some(sub_tree) => {
-                let sub_tree_clone = sub_tree.borrow().clone();
-                if sub_tree_clone.key == node.key {
-                    Some(sub_tree.clone())
-                } else {
-                    if sub_tree_clone.key > node.key {
-                        self.search_node(&sub_tree_clone.left, node)
-                    } else {
-                        self.search_node(&sub_tree_clone.right, node)
-                    }
-                }
-            },
-            None => {None}
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.search_node(&self.root, key).map(|node| node.borrow().key)
+    }
+
+    fn search_node(&self, tree_node: &Tree<T, A>, key: &T) -> Tree<T, A> {
+        let mut cur = tree_node.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
         }
+        None
     }
 
     // 2- delete a node from the red-black tree
     pub fn delete(&mut self, key: T) {
-        let z = self.search(key);
-        if z.is_none() {
+        if self.remove_all(key) == 0 {
             println!("Key not found");
-            return;
         }
-        // key exists
-        let u = z; // node to be deleted
-        let p = u.as_ref().unwrap().borrow().parent.clone();
-        let v = u.as_ref().unwrap().borrow().left.clone(); 
-        let w = u.as_ref().unwrap().borrow().right.clone();
+    }
 
-        let mut side = Direction::Left; // set default value to left
+    // remove every occurrence of key, returns how many were removed
+    pub fn remove_all(&mut self, key: T) -> u32 {
+        let z = self.search_node(&self.root, &key);
+        if z.is_none() {
+            return 0;
+        }
+        let occurrences = z.as_ref().unwrap().borrow().occurrences;
+        self.delete_node(z);
+        self.count -= occurrences;
+        occurrences
+    }
 
-        if p.is_some() {
-            side = if p.as_ref().unwrap().borrow().clone().key > u.as_ref().unwrap().borrow().clone().key {
-                Direction::Right
-            } else {
-                Direction::Left
-            };
+    // remove a single occurrence of key, the node only goes once its last occurrence does
+    pub fn remove_one(&mut self, key: T) -> bool {
+        let z = self.search_node(&self.root, &key);
+        if z.is_none() {
+            return false;
+        }
+        let occurrences = z.as_ref().unwrap().borrow().occurrences;
+        if occurrences > 1 {
+            z.as_ref().unwrap().borrow_mut().occurrences -= 1;
+            update_aug_path(z);
+        } else {
+            self.delete_node(z);
         }
+        self.count -= 1;
+        true
+    }
+
+    fn delete_node(&mut self, z: Tree<T, A>) {
+        let u = z; // node to be deleted
+        let v = u.as_ref().unwrap().borrow().left.clone(); 
+        let w = u.as_ref().unwrap().borrow().right.clone();
 
         let mut u_original_color = u.as_ref().unwrap().borrow().color.clone();
-        let x: Tree<T>;
+        let x: Tree<T, A>;
+        // x can be None, so keep track of the node it hangs off for delete_fix
+        let x_parent: Tree<T, A>;
 
         if v.is_none() {
             // left node of u is none
             x = w.clone();
+            x_parent = u.as_ref().unwrap().borrow().parent.clone();
             self.transplant(u.clone(),w.clone());
         } else if w.is_none() {
             // right node of u is none
             x = v.clone();
+            x_parent = u.as_ref().unwrap().borrow().parent.clone();
             self.transplant(u.clone(), v.clone());
         } else {
             // both left and right nodes exist
@@ -395,12 +646,12 @@ where T: Ord+Display+Debug+Clone+Copy
             // we can safely unwrap
             // x is right subtree of y
             u_original_color = y.as_ref().unwrap().borrow().color.clone();
-            x = y.as_ref().unwrap().borrow().clone().right;
-            if y.as_ref().unwrap().borrow().clone().parent.as_ref().unwrap().borrow().clone().key == u.as_ref().unwrap().borrow().clone().key {
-                if x.is_some() {
-                    x.as_ref().unwrap().borrow_mut().parent = y.clone();
-                }
+            x = y.as_ref().unwrap().borrow().right.clone();
+            if is_node(&y.as_ref().unwrap().borrow().parent, u.as_ref().unwrap()) {
+                // y is u's right child, so x stays below y
+                x_parent = y.clone();
             } else {
+                x_parent = y.as_ref().unwrap().borrow().parent.clone();
                 self.transplant(y.clone(), y.as_ref().unwrap().borrow().right.clone());
                 y.as_ref().unwrap().borrow_mut().right = u.as_ref().unwrap().borrow().right.clone();
                 y.as_ref().unwrap().borrow().right.as_ref().unwrap().borrow_mut().parent = y.clone();
@@ -410,148 +661,130 @@ where T: Ord+Display+Debug+Clone+Copy
             v.as_ref().unwrap().borrow_mut().parent = y.clone();
             y.as_ref().unwrap().borrow_mut().color = u.as_ref().unwrap().borrow().color.clone();
         }
+        // everything from x's parent up lost a node, fix the summaries before delete_fix rotates
+        update_aug_path(x_parent.clone());
+        let key = u.as_ref().unwrap().borrow().key;
+        self.step(|| format!("remove {}", key));
         if u_original_color == NodeColor::Black {
-            self.delete_fix(x.clone(), p.clone(), side);
+            self.delete_fix(x, x_parent);
         }
-        self.count -= 1;
     }
 
-    fn delete_fix(&mut self, x: Tree<T>, p: Tree<T>, side: Direction) {
-        // x color is true if black and false if red
-        let mut x_color = if x.is_some() {
-            x.as_ref().unwrap().borrow().clone().color == NodeColor::Black
-        } else {
-            // Node is none so it is black
-            true
-        };
+    fn delete_fix(&mut self, x: Tree<T, A>, p: Tree<T, A>) {
         let mut cur_p = p;
         let mut cur_x = x;
-        let mut is_root = cur_p.is_none();
-        while !is_root && x_color {
+        // x carries an extra black until it is red or the root
+        while cur_p.is_some() && is_black(&cur_x) {
+            let parent = cur_p.as_ref().unwrap().clone();
+            // find which side the sibling is on, x might be None so check the parent's children
+            let side = match cur_x {
+                Some(ref x_node) => {
+                    if is_node(&parent.borrow().left, x_node) { Direction::Right } else { Direction::Left }
+                },
+                None => {
+                    if parent.borrow().left.is_none() { Direction::Right } else { Direction::Left }
+                }
+            };
             match side {
                 Direction::Right => {
                     // sibling on the right side of p
-                    // cur_p exists or else we wouldnt be in this while loop
-                    let mut s = cur_p.as_ref().unwrap().borrow().right.clone();
-                    if s.is_some() {
-                        if s.as_ref().unwrap().borrow().clone().color == NodeColor::Red {
-                            // DB's sibling is red
-                            // swap color of p with s
-                            // rotate parent node left
-                            s.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                            cur_p.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
-                            self.rotate_left(cur_p.as_ref().unwrap().clone());
-                            s = cur_p.as_ref().unwrap().borrow().right.clone();
+                    // the sibling of a double black node always exists
+                    let mut s = parent.borrow().right.clone().unwrap();
+                    if s.borrow().color == NodeColor::Red {
+                        // DB's sibling is red
+                        // swap color of p with s
+                        // rotate parent node left
+                        s.borrow_mut().color = NodeColor::Black;
+                        parent.borrow_mut().color = NodeColor::Red;
+                        let (s_key, p_key) = (s.borrow().key, parent.borrow().key);
+                        self.step(|| format!("recolor the red sibling {} black and {} red", s_key, p_key));
+                        self.rotate_left(parent.clone());
+                        s = parent.borrow().right.clone().unwrap();
+                    }
+                    if is_black(&s.borrow().left) && is_black(&s.borrow().right) {
+                        // both of the sibling's children are black, push the extra black up to p
+                        s.borrow_mut().color = NodeColor::Red;
+                        let (s_key, p_key) = (s.borrow().key, parent.borrow().key);
+                        self.step(|| format!("recolor {} red, the extra black moves up to {}", s_key, p_key));
+                        cur_x = Some(parent.clone());
+                        cur_p = parent.borrow().parent.clone();
+                    } else {
+                        if is_black(&s.borrow().right) {
+                            // far child is black so rotate the red near child into the sibling's place
+                            let s_left = s.borrow().left.clone().unwrap();
+                            s_left.borrow_mut().color = NodeColor::Black;
+                            s.borrow_mut().color = NodeColor::Red;
+                            let s_key = s.borrow().key;
+                            self.step(|| format!("recolor {} red and its near child black", s_key));
+                            self.rotate_right(s.clone());
+                            s = parent.borrow().right.clone().unwrap();
                         }
-                        let s_left = s.as_ref().unwrap().borrow().clone().left.clone();
-                        let s_right = s.as_ref().unwrap().borrow().clone().right.clone();
-
-                        let s_left_color = if s_left.is_some() {
-                            s_left.as_ref().unwrap().borrow().clone().color == NodeColor::Black
-                        } else {
-                            true
-                        };
-
-                        let s_right_color = if s_right.is_some() {
-                            s_right.as_ref().unwrap().borrow().clone().color == NodeColor::Black
-                        } else {
-                            true
-                        };
-
-                        if s_left_color && s_right_color {
-                            s.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
-                            cur_x = cur_p.clone();
-                            let g = cur_p.as_ref().unwrap().borrow().clone().parent.clone();
-                            cur_p = g.clone();
-                            x_color = if cur_x.is_some() {
-                                cur_x.as_ref().unwrap().borrow().clone().color == NodeColor::Black
-                            } else {
-                                true
-                            };
-                        } else {
-                            if s_right.is_some() && s_right.as_ref().unwrap().borrow().clone().color == NodeColor::Black {
-                                if s_left.is_some() {
-                                    s_left.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                                    s.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
-                                    self.rotate_right(s.unwrap());
-                                    s = cur_p.as_ref().unwrap().borrow().right.clone();
-                                }
-                            }
-                            s.as_ref().unwrap().borrow_mut().color = cur_p.as_ref().unwrap().borrow().color.clone();
-                            cur_p.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                            if s_right.is_some() {
-                                s_right.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                            }
-                            self.rotate_left(cur_p.as_ref().unwrap().clone());
-                            is_root = true;
+                        s.borrow_mut().color = parent.borrow().color.clone();
+                        parent.borrow_mut().color = NodeColor::Black;
+                        if let Some(s_right) = s.borrow().right.clone() {
+                            s_right.borrow_mut().color = NodeColor::Black;
                         }
+                        let (s_key, p_key) = (s.borrow().key, parent.borrow().key);
+                        self.step(|| format!("give {} the color of {}, which goes black with the far child", s_key, p_key));
+                        self.rotate_left(parent.clone());
+                        cur_x = self.root.clone();
+                        cur_p = None;
                     }
                 },
                 Direction::Left => {
                     // siblings are on the left side of p
-                    let mut s = cur_p.as_ref().unwrap().borrow().left.clone();
-                    if s.is_some() {
-                        if s.as_ref().unwrap().borrow().clone().color == NodeColor::Red {
-                            // DB's sibling is red
-                            // swap color of p with s
-                            // rotate parent node right
-                            s.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                            cur_p.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
-                            self.rotate_right(cur_p.as_ref().unwrap().clone());
-                            s = cur_p.as_ref().unwrap().borrow().left.clone();
+                    let mut s = parent.borrow().left.clone().unwrap();
+                    if s.borrow().color == NodeColor::Red {
+                        // DB's sibling is red
+                        // swap color of p with s
+                        // rotate parent node right
+                        s.borrow_mut().color = NodeColor::Black;
+                        parent.borrow_mut().color = NodeColor::Red;
+                        let (s_key, p_key) = (s.borrow().key, parent.borrow().key);
+                        self.step(|| format!("recolor the red sibling {} black and {} red", s_key, p_key));
+                        self.rotate_right(parent.clone());
+                        s = parent.borrow().left.clone().unwrap();
+                    }
+                    if is_black(&s.borrow().left) && is_black(&s.borrow().right) {
+                        s.borrow_mut().color = NodeColor::Red;
+                        let (s_key, p_key) = (s.borrow().key, parent.borrow().key);
+                        self.step(|| format!("recolor {} red, the extra black moves up to {}", s_key, p_key));
+                        cur_x = Some(parent.clone());
+                        cur_p = parent.borrow().parent.clone();
+                    } else {
+                        if is_black(&s.borrow().left) {
+                            let s_right = s.borrow().right.clone().unwrap();
+                            s_right.borrow_mut().color = NodeColor::Black;
+                            s.borrow_mut().color = NodeColor::Red;
+                            let s_key = s.borrow().key;
+                            self.step(|| format!("recolor {} red and its near child black", s_key));
+                            self.rotate_left(s.clone());
+                            s = parent.borrow().left.clone().unwrap();
                         }
-                        let s_left = s.as_ref().unwrap().borrow().clone().left.clone();
-                        let s_right = s.as_ref().unwrap().borrow().clone().right.clone();
-
-                        let s_left_color = if s_left.is_some() {
-                            s_left.as_ref().unwrap().borrow().clone().color == NodeColor::Black
-                        } else {
-                            true
-                        };
-
-                        let s_right_color = if s_right.is_some() {
-                            s_right.as_ref().unwrap().borrow().clone().color == NodeColor::Black
-                        } else {
-                            true
-                        };
-
-                        if s_left_color && s_right_color {
-                            s.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
-                            cur_x = cur_p.clone();
-                            let g = cur_p.as_ref().unwrap().borrow().clone().parent.clone();
-                            cur_p = g.clone();
-                            x_color = if cur_x.is_some() {
-                                cur_x.as_ref().unwrap().borrow().clone().color == NodeColor::Black
-                            } else {
-                                true
-                            };
-                        } else {
-                            if s_right.is_some() && s_right.as_ref().unwrap().borrow().clone().color == NodeColor::Black {
-                                if s_left.is_some() {
-                                    s_left.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                                    s.as_ref().unwrap().borrow_mut().color = NodeColor::Red;
-                                    self.rotate_left(s.unwrap());
-                                    s = cur_p.as_ref().unwrap().borrow().left.clone();
-                                }
-                            }
-                            s.as_ref().unwrap().borrow_mut().color = cur_p.as_ref().unwrap().borrow().color.clone();
-                            cur_p.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                            if s_left.is_some() {
-                                s_left.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
-                            }
-                            self.rotate_right(cur_p.as_ref().unwrap().clone());
-                            is_root = true;
+                        s.borrow_mut().color = parent.borrow().color.clone();
+                        parent.borrow_mut().color = NodeColor::Black;
+                        if let Some(s_left) = s.borrow().left.clone() {
+                            s_left.borrow_mut().color = NodeColor::Black;
                         }
+                        let (s_key, p_key) = (s.borrow().key, parent.borrow().key);
+                        self.step(|| format!("give {} the color of {}, which goes black with the far child", s_key, p_key));
+                        self.rotate_right(parent.clone());
+                        cur_x = self.root.clone();
+                        cur_p = None;
                     }
                 }
             }
         }
-        if cur_x.is_some() {
-            cur_x.as_ref().unwrap().borrow_mut().color = NodeColor::Black;
+        if let Some(x_node) = cur_x {
+            if x_node.borrow().color == NodeColor::Red {
+                x_node.borrow_mut().color = NodeColor::Black;
+                let key = x_node.borrow().key;
+                self.step(|| format!("recolor {} black", key));
+            }
         }
     }
 
-    fn transplant(&mut self, z: Tree<T>, v: Tree<T>) {
+    fn transplant(&mut self, z: Tree<T, A>, v: Tree<T, A>) {
         // transplant is responsible for deleting u and replacing it with v
         let u = z.unwrap();
         let u_p = u.borrow().parent.clone();
@@ -559,7 +792,7 @@ where T: Ord+Display+Debug+Clone+Copy
             // deleting root node
             self.root = v.clone();
         } else {
-            if u_p.as_ref().unwrap().borrow().clone().key > u.borrow().clone().key {
+            if is_node(&u_p.as_ref().unwrap().borrow().left, &u) {
                 // z is on the left of parent
                 u_p.as_ref().unwrap().borrow_mut().left = v.clone();
             } else {
@@ -573,7 +806,7 @@ where T: Ord+Display+Debug+Clone+Copy
         }
     }
 
-    fn find_min(&self, tree: Tree<T>) -> Tree<T> {
+    fn find_min(&self, tree: Tree<T, A>) -> Tree<T, A> {
         match tree {
             Some(sub_tree) => {
                 let mut left = Some(sub_tree.clone());
@@ -588,7 +821,7 @@ where T: Ord+Display+Debug+Clone+Copy
         }
     }
 
-    fn find_max(&self, tree: Tree<T>) -> Tree<T> {
+    fn find_max(&self, tree: Tree<T, A>) -> Tree<T, A> {
         match tree {
             Some(sub_tree) => {
                 let mut right = Some(sub_tree.clone());
@@ -603,13 +836,60 @@ where T: Ord+Display+Debug+Clone+Copy
         }
     }
 
+    // check key order and parent links, that the root is black, that no red node has a red child and
+    // that every path down has the same number of black nodes, and that count matches the
+    // occurrences held
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(root) = &self.root {
+            if root.borrow().color == NodeColor::Red {
+                return Err("the root is red".to_string());
+            }
+            if root.borrow().parent.is_some() {
+                return Err("the root has a parent".to_string());
+            }
+        }
+        let size = self.check(&self.root, None, None)?.1;
+        if size != self.count {
+            return Err(format!("count is {} but the tree holds {} keys", self.count, size));
+        }
+        Ok(())
+    }
+
+    // returns the black height and the number of keys of the subtree, occurrences included
+    fn check(&self, tree: &Tree<T, A>, lo: Option<T>, hi: Option<T>) -> Result<(u32, u32), String> {
+        let node = match tree {
+            Some(node) => node,
+            None => return Ok((1, 0)),
+        };
+        let n = node.borrow();
+        if lo.is_some_and(|lo| self.cmp.compare(&n.key, &lo) != Ordering::Greater)
+            || hi.is_some_and(|hi| self.cmp.compare(&n.key, &hi) != Ordering::Less) {
+            return Err(format!("{} is out of order", n.key));
+        }
+        for child in [&n.left, &n.right].into_iter().flatten() {
+            if !is_node(&child.borrow().parent, node) {
+                return Err(format!("{} does not point back to its parent {}", child.borrow().key, n.key));
+            }
+            if n.color == NodeColor::Red && child.borrow().color == NodeColor::Red {
+                return Err(format!("red {} has a red child", n.key));
+            }
+        }
+        let (left_height, left_size) = self.check(&n.left, lo, Some(n.key))?;
+        let (right_height, right_size) = self.check(&n.right, Some(n.key), hi)?;
+        if left_height != right_height {
+            return Err(format!("black heights {} and {} differ below {}", left_height, right_height, n.key));
+        }
+        let height = left_height + if n.color == NodeColor::Black { 1 } else { 0 };
+        Ok((height, left_size + right_size + n.occurrences))
+    }
+
     // 3- count the number of leaves in a tree
     pub fn leaves(&self) -> u32 {
         if self.root.is_none() {
             return 0;
         }
         let root = self.root.as_ref().unwrap().clone();
-        let mut stack: Vec<Tree<T>> = Vec::new();
+        let mut stack: Vec<Tree<T, A>> = Vec::new();
         stack.push(Some(root));
 
         let mut count = 0;
@@ -644,7 +924,7 @@ where T: Ord+Display+Debug+Clone+Copy
             return 0;
         }
         let root = self.root.as_ref().unwrap().clone();
-        let mut queue: VecDeque<Tree<T>> = VecDeque::new();
+        let mut queue: VecDeque<Tree<T, A>> = VecDeque::new();
         queue.push_back(Some(root));
 
         let mut height = 0;
@@ -664,6 +944,24 @@ where T: Ord+Display+Debug+Clone+Copy
         height
     }
 
+    // heap bytes held by the nodes, see MemoryUsage. steps recorded while tracing are not counted
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut stack: Vec<TreeNode<T, A>> = self.root.clone().into_iter().collect();
+        let mut nodes = 0;
+        while let Some(node) = stack.pop() {
+            nodes += 1;
+            stack.extend(node.borrow().left.clone());
+            stack.extend(node.borrow().right.clone());
+        }
+        let link = size_of::<Tree<T, A>>();
+        MemoryUsage::of::<Node<T, A>, T>(nodes, self.count as usize, NodeFields {
+            child: 2 * link,
+            parent: link,
+            balance: size_of::<NodeColor>(),
+            extra: size_of::<u32>() + size_of::<A::Value>(),
+        })
+    }
+
     // 5- print in-order traversal of tree
     pub fn print_inorder(&self) {
         if self.root.is_none() {
@@ -671,7 +969,7 @@ where T: Ord+Display+Debug+Clone+Copy
             return;
         }
         let mut root = self.root.clone();
-        let mut stack: Vec<Tree<T>> = Vec::new();
+        let mut stack: Vec<Tree<T, A>> = Vec::new();
         while !stack.is_empty() || !root.is_none() {
             if root.is_some() {
                 stack.push(root.clone());
@@ -692,9 +990,9 @@ where T: Ord+Display+Debug+Clone+Copy
             return;
         }
         let mut root = self.root.clone();
-        let mut stack: Vec<Tree<T>> = Vec::new();
+        let mut stack: Vec<Tree<T, A>> = Vec::new();
         stack.push(root);
-        let mut cur: Tree<T>;
+        let mut cur: Tree<T, A>;
         while !stack.is_empty() {
             cur = stack.pop().unwrap();
             root = cur.clone();
@@ -723,19 +1021,135 @@ where T: Ord+Display+Debug+Clone+Copy
         println!("\n");
     }
 
-    pub fn min(&self) -> Tree<T> {
-        self.find_min(self.root.clone())
+    pub fn first(&self) -> Option<T> {
+        self.find_min(self.root.clone()).map(|node| node.borrow().key)
     }
 
-    pub fn max(&self) -> Tree<T> {
-        self.find_max(self.root.clone())
+    pub fn last(&self) -> Option<T> {
+        self.find_max(self.root.clone()).map(|node| node.borrow().key)
     }
 
-    fn inorder(&self) -> VecDeque<Tree<T>> {
+    // keys k with lo <= k < hi, in the tree's order
+    pub fn range(&self, lo: T, hi: T) -> Vec<T> {
+        let mut keys: Vec<T> = Vec::new();
+        let mut stack: Vec<TreeNode<T, A>> = Vec::new();
+        let mut cur = self.root.clone();
+        loop {
+            // walk left, skipping every subtree that is entirely below lo
+            while let Some(node) = cur.take() {
+                if self.cmp.compare(&node.borrow().key, &lo) == Ordering::Less {
+                    cur = node.borrow().right.clone();
+                } else {
+                    cur = node.borrow().left.clone();
+                    stack.push(node);
+                }
+            }
+            let node = match stack.pop() {
+                Some(node) => node,
+                None => break,
+            };
+            let key = node.borrow().key;
+            if self.cmp.compare(&key, &hi) != Ordering::Less {
+                break;
+            }
+            for _ in 0..node.borrow().occurrences {
+                keys.push(key);
+            }
+            cur = node.borrow().right.clone();
+        }
+        keys
+    }
+
+    // iterate over the keys in order, a multiset repeats each key once per occurrence
+    pub fn iter(&self) -> Iter<T, A> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            repeat: None,
+        };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    // cursor at the first key above bound, past the end if there is none
+    pub fn lower_bound(&self, bound: Bound<&T>) -> Cursor<'_, T, C, A> {
+        let node = self.lower_bound_node(bound);
+        Cursor { tree: self, node }
+    }
+
+    // cursor at the last key below bound, past the end if there is none
+    pub fn upper_bound(&self, bound: Bound<&T>) -> Cursor<'_, T, C, A> {
+        let node = self.upper_bound_node(bound);
+        Cursor { tree: self, node }
+    }
+
+    pub fn lower_bound_mut(&mut self, bound: Bound<&T>) -> CursorMut<'_, T, C, A> {
+        let node = self.lower_bound_node(bound);
+        CursorMut { tree: self, node }
+    }
+
+    pub fn upper_bound_mut(&mut self, bound: Bound<&T>) -> CursorMut<'_, T, C, A> {
+        let node = self.upper_bound_node(bound);
+        CursorMut { tree: self, node }
+    }
+
+    fn lower_bound_node(&self, bound: Bound<&T>) -> Tree<T, A> {
+        let mut found = None;
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let above = match bound {
+                Bound::Included(key) => self.cmp.compare(&node.borrow().key, key) != Ordering::Less,
+                Bound::Excluded(key) => self.cmp.compare(&node.borrow().key, key) == Ordering::Greater,
+                Bound::Unbounded => true,
+            };
+            if above {
+                cur = node.borrow().left.clone();
+                found = Some(node);
+            } else {
+                cur = node.borrow().right.clone();
+            }
+        }
+        found
+    }
+
+    fn upper_bound_node(&self, bound: Bound<&T>) -> Tree<T, A> {
+        let mut found = None;
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let below = match bound {
+                Bound::Included(key) => self.cmp.compare(&node.borrow().key, key) != Ordering::Greater,
+                Bound::Excluded(key) => self.cmp.compare(&node.borrow().key, key) == Ordering::Less,
+                Bound::Unbounded => true,
+            };
+            if below {
+                cur = node.borrow().right.clone();
+                found = Some(node);
+            } else {
+                cur = node.borrow().left.clone();
+            }
+        }
+        found
+    }
+
+    // a cursor past the end steps round to the first node going forwards and the last going backwards
+    fn next_node(&self, node: &Tree<T, A>) -> Tree<T, A> {
+        match node {
+            Some(node) => successor(node),
+            None => self.find_min(self.root.clone()),
+        }
+    }
+
+    fn prev_node(&self, node: &Tree<T, A>) -> Tree<T, A> {
+        match node {
+            Some(node) => predecessor(node),
+            None => self.find_max(self.root.clone()),
+        }
+    }
+
+    fn inorder(&self) -> VecDeque<Tree<T, A>> {
         let root = self.root.as_ref().unwrap().clone();
-        let mut queue: VecDeque<Tree<T>> = VecDeque::new();
+        let mut queue: VecDeque<Tree<T, A>> = VecDeque::new();
         queue.push_back(Some(root));
-        let mut res: VecDeque<Tree<T>> = VecDeque::new();
+        let mut res: VecDeque<Tree<T, A>> = VecDeque::new();
         while !queue.is_empty() {
             let n = queue.len();
             for _ in 0..n {
@@ -751,13 +1165,29 @@ where T: Ord+Display+Debug+Clone+Copy
         res
     }
 
+    // the tree's layout with each node's color, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T, A>| {
+            let node = node.borrow();
+            let color = match node.color {
+                NodeColor::Red => "red",
+                NodeColor::Black => "black",
+            };
+            let mut attrs = vec![("color", Attr::Text(color))];
+            if node.occurrences > 1 {
+                attrs.push(("occurrences", Attr::Number(node.occurrences as u64)));
+            }
+            (vec![node.key], attrs, vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
     pub fn print_tree(&self) {
         if self.root.is_none() {
             println!("None");
             return;
         };
 
-        fn pretty_print<T: Ord+Display+Debug+Clone>(node: TreeNode<T>, buffer: &mut String, prefix: &mut String, child_prefix: &String) {
+        fn pretty_print<T: Display+Debug+Clone, A: Augment<T>>(node: TreeNode<T, A>, buffer: &mut String, prefix: &mut String, child_prefix: &String) {
             let node_color = if node.borrow().clone().color == NodeColor::Black { "(b)" }else {"(r)"};
             prefix.push_str(&node_color.to_string());
             buffer.push_str(&prefix);
@@ -789,8 +1219,328 @@ where T: Ord+Display+Debug+Clone+Copy
     }
 }
 
-impl<T> fmt::Display for RBTree<T>
-where T: Debug+Ord+Display+Copy
+impl<T, C, M> RBTree<T, C, Aggregate<M>>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>, M: Monoid<T>
+{
+    // fold of every key in the tree, read straight off the root
+    pub fn aggregate(&self) -> M::Value {
+        match self.root {
+            Some(ref root) => root.borrow().aug,
+            None => M::identity(),
+        }
+    }
+
+    // fold of the keys k with lo <= k < hi, using the cached folds of every subtree fully inside the range
+    pub fn range_aggregate(&self, lo: T, hi: T) -> M::Value {
+        self.fold_range(&self.root, Some(&lo), Some(&hi))
+    }
+
+    // a None bound leaves that side open, once both are open the whole subtree counts
+    fn fold_range(&self, tree: &Tree<T, Aggregate<M>>, lo: Option<&T>, hi: Option<&T>) -> M::Value {
+        let node = match tree {
+            Some(node) => node.borrow(),
+            None => return M::identity(),
+        };
+        if lo.is_none() && hi.is_none() {
+            return node.aug;
+        }
+        if let Some(lo) = lo {
+            if self.cmp.compare(&node.key, lo) == Ordering::Less {
+                return self.fold_range(&node.right, Some(lo), hi);
+            }
+        }
+        if let Some(hi) = hi {
+            if self.cmp.compare(&node.key, hi) != Ordering::Less {
+                return self.fold_range(&node.left, lo, Some(hi));
+            }
+        }
+        // node is inside the range, so everything right of it is above lo and everything left of it below hi
+        let left = self.fold_range(&node.left, lo, None);
+        let right = self.fold_range(&node.right, None, hi);
+        M::combine(M::combine(left, M::repeat(&node.key, node.occurrences as usize)), right)
+    }
+}
+
+pub enum Entry<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    Occupied(OccupiedEntry<'a, T, C, A>),
+    Vacant(VacantEntry<'a, T, C, A>),
+}
+
+pub struct OccupiedEntry<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a mut RBTree<T, C, A>,
+    node: TreeNode<T, A>,
+}
+
+pub struct VacantEntry<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a mut RBTree<T, C, A>,
+    key: T,
+    // the node the new key hangs off and which side, None parent means an empty tree
+    parent: Tree<T, A>,
+    side: Direction,
+}
+
+impl<'a, T, C, A> Entry<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn key(&self) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    // update the stored key in place, f must not change how it compares
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(entry) => {
+                f(&mut entry.node.borrow_mut().key);
+                // the key may carry data the summaries depend on
+                update_aug_path(Some(entry.node.clone()));
+                Entry::Occupied(entry)
+            },
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    // returns a copy of the stored key, inserting default first if the entry is vacant
+    pub fn or_insert(self, default: T) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    // the tree only holds keys, so the default for a vacant entry is the key it was looked up with
+    pub fn or_default(self) -> T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => {
+                let key = entry.key();
+                entry.insert(key)
+            }
+        }
+    }
+}
+
+impl<'a, T, C, A> OccupiedEntry<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn get(&self) -> T {
+        self.node.borrow().key
+    }
+}
+
+impl<'a, T, C, A> VacantEntry<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn key(&self) -> T {
+        self.key
+    }
+
+    // insert key into the spot found by entry(), key must compare equal to the one looked up
+    pub fn insert(self, key: T) -> T {
+        debug_assert!(self.tree.cmp.compare(&key, &self.key) == Ordering::Equal);
+        let added_node = Node::<T, A>::new(key).unwrap();
+        match self.parent {
+            Some(parent) => {
+                added_node.borrow_mut().parent = Some(parent.clone());
+                match self.side {
+                    Direction::Left => parent.borrow_mut().left = Some(added_node.clone()),
+                    Direction::Right => parent.borrow_mut().right = Some(added_node.clone()),
+                }
+                update_aug_path(Some(parent));
+            },
+            None => {
+                self.tree.root = Some(added_node.clone());
+            }
+        }
+        self.tree.count += 1;
+        self.tree.step(|| format!("attach {} as a red leaf", key));
+        self.tree.root = self.tree.insert_fix(added_node);
+        key
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy, A: Augment<T> = ()> {
+    stack: Vec<TreeNode<T, A>>,
+    // key being repeated and how many more times to yield it
+    repeat: Option<(T, u32)>,
+}
+
+impl<T, A> Iter<T, A>
+where T: Display+Debug+Copy, A: Augment<T>
+{
+    fn push_left(&mut self, tree: Tree<T, A>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T, A> Iterator for Iter<T, A>
+where T: Display+Debug+Copy, A: Augment<T>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if let Some((key, remaining)) = self.repeat {
+            if remaining > 0 {
+                self.repeat = Some((key, remaining - 1));
+                return Some(key);
+            }
+        }
+        let node = self.stack.pop()?;
+        let (key, occurrences, right) = {
+            let node = node.borrow();
+            (node.key, node.occurrences, node.right.clone())
+        };
+        self.push_left(right);
+        self.repeat = Some((key, occurrences - 1));
+        Some(key)
+    }
+}
+
+// a position in the tree that steps to its neighbours through the parent pointers instead of
+// starting again from the root, node is None when the cursor is past the ends
+pub struct Cursor<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a RBTree<T, C, A>,
+    node: Tree<T, A>,
+}
+
+impl<'a, T, C, A> Cursor<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    // a multiset key is a single position however many occurrences it has
+    pub fn key(&self) -> Option<T> {
+        self.node.as_ref().map(|node| node.borrow().key)
+    }
+
+    pub fn move_next(&mut self) {
+        self.node = self.tree.next_node(&self.node);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.node = self.tree.prev_node(&self.node);
+    }
+
+    pub fn peek_next(&self) -> Option<T> {
+        self.tree.next_node(&self.node).map(|node| node.borrow().key)
+    }
+
+    pub fn peek_prev(&self) -> Option<T> {
+        self.tree.prev_node(&self.node).map(|node| node.borrow().key)
+    }
+}
+
+pub struct CursorMut<'a, T: Display+Debug+Copy, C, A: Augment<T> = ()> {
+    tree: &'a mut RBTree<T, C, A>,
+    node: Tree<T, A>,
+}
+
+impl<'a, T, C, A> CursorMut<'a, T, C, A>
+where T: Display+Debug+Copy, C: Comparator<T>, A: Augment<T>
+{
+    pub fn key(&self) -> Option<T> {
+        self.node.as_ref().map(|node| node.borrow().key)
+    }
+
+    pub fn move_next(&mut self) {
+        self.node = self.tree.next_node(&self.node);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.node = self.tree.prev_node(&self.node);
+    }
+
+    pub fn peek_next(&self) -> Option<T> {
+        self.tree.next_node(&self.node).map(|node| node.borrow().key)
+    }
+
+    pub fn peek_prev(&self) -> Option<T> {
+        self.tree.prev_node(&self.node).map(|node| node.borrow().key)
+    }
+
+    // a read-only cursor at the same position, borrowing this one
+    pub fn as_cursor(&self) -> Cursor<'_, T, C, A> {
+        Cursor { tree: self.tree, node: self.node.clone() }
+    }
+
+    // insert key just after the cursor without searching from the root, the cursor stays where it is.
+    // past the end this inserts in front of the first key. panics unless key falls strictly between
+    // the current key and the next one
+    pub fn insert_after(&mut self, key: T) {
+        let next = self.tree.next_node(&self.node);
+        if let Some(node) = &self.node {
+            assert!(self.tree.cmp.compare(&key, &node.borrow().key) == Ordering::Greater, "key does not come after the cursor");
+        }
+        if let Some(next) = &next {
+            assert!(self.tree.cmp.compare(&key, &next.borrow().key) == Ordering::Less, "key does not come before the next key");
+        }
+        // the gap is either the current node's empty right child or the next node's left child,
+        // which is empty whenever the current node has a right subtree
+        let (parent, side) = match &self.node {
+            Some(node) if node.borrow().right.is_none() => (Some(node.clone()), Direction::Right),
+            _ => (next, Direction::Left),
+        };
+        VacantEntry { tree: &mut *self.tree, key, parent, side }.insert(key);
+    }
+
+    // insert key just before the cursor, past the end this inserts after the last key
+    pub fn insert_before(&mut self, key: T) {
+        let prev = self.tree.prev_node(&self.node);
+        if let Some(node) = &self.node {
+            assert!(self.tree.cmp.compare(&key, &node.borrow().key) == Ordering::Less, "key does not come before the cursor");
+        }
+        if let Some(prev) = &prev {
+            assert!(self.tree.cmp.compare(&key, &prev.borrow().key) == Ordering::Greater, "key does not come after the previous key");
+        }
+        let (parent, side) = match &self.node {
+            Some(node) if node.borrow().left.is_none() => (Some(node.clone()), Direction::Left),
+            _ => (prev, Direction::Right),
+        };
+        VacantEntry { tree: &mut *self.tree, key, parent, side }.insert(key);
+    }
+
+    // remove the current key, with all its occurrences in a multiset, and move on to the next one
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.node.take()?;
+        // delete_node relinks nodes rather than copying keys between them, so the next node survives
+        self.node = successor(&node);
+        let (key, occurrences) = {
+            let node = node.borrow();
+            (node.key, node.occurrences)
+        };
+        self.tree.delete_node(Some(node));
+        self.tree.count -= occurrences;
+        Some(key)
+    }
+}
+
+// the keys of a plain BST in a balanced tree, ordered by the same comparator
+impl<T, C> From<BST<T, C>> for RBTree<T, C>
+where T: Display+Debug+Clone+Copy, C: Comparator<T>
+{
+    fn from(bst: BST<T, C>) -> Self {
+        let (keys, cmp) = bst.into_parts();
+        let mut tree = Self::with_comparator(cmp);
+        for key in keys {
+            tree.insert(key);
+        }
+        tree
+    }
+}
+
+impl<T, C, A> fmt::Display for RBTree<T, C, A>
+where T: Debug+Display+Copy, A: Augment<T>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("RBTree")
@@ -906,10 +1656,10 @@ pub fn search_1() {
     x.insert(12);
     x.insert(3);
 
-    let y = x.search(8);
-    assert_eq!(8,y.as_ref().unwrap().borrow().clone().key);
-    let z = x.search(81);
-    assert!(z.is_none());
+    assert_eq!(x.get(&8), Some(8));
+    assert!(x.contains(&8));
+    assert_eq!(x.get(&81), None);
+    assert!(!x.contains(&81));
 }
 
 #[test]
@@ -1447,8 +2197,8 @@ pub fn test_min_max_1() {
     a.insert(9);
     a.insert(12);
     a.insert(1);
-    assert_eq!(a.min().as_ref().unwrap().borrow().key, 1);
-    assert_eq!(a.max().as_ref().unwrap().borrow().key, 455);
+    assert_eq!(a.first().unwrap(), 1);
+    assert_eq!(a.last().unwrap(), 455);
 }
 
 #[test]
@@ -1460,6 +2210,424 @@ pub fn test_min_max_2() {
     a.insert("g");
     a.insert("u");
     a.insert("c");
-    assert_eq!(a.min().as_ref().unwrap().borrow().key, "a");
-    assert_eq!(a.max().as_ref().unwrap().borrow().key, "u");
-}
\ No newline at end of file
+    assert_eq!(a.first().unwrap(), "a");
+    assert_eq!(a.last().unwrap(), "u");
+}
+#[cfg(test)]
+use crate::compare::{ByKey, Reverse, TotalOrder};
+#[cfg(test)]
+use crate::augment::{Count, Gcd, Min, Sum};
+
+// walks the whole tree checking parent links, no red-red edges and equal black heights
+#[cfg(test)]
+fn check_rb<T: Display+Debug+Copy, A: Augment<T>>(tree: &Tree<T, A>) -> u32 {
+    match tree {
+        None => 1,
+        Some(node) => {
+            let n = node.borrow();
+            for c in [&n.left, &n.right].into_iter().flatten() {
+                assert!(is_node(&c.borrow().parent, node));
+                if n.color == NodeColor::Red {
+                    assert_eq!(c.borrow().color, NodeColor::Black);
+                }
+            }
+            let left_height = check_rb(&n.left);
+            assert_eq!(left_height, check_rb(&n.right));
+            left_height + if n.color == NodeColor::Black { 1 } else { 0 }
+        }
+    }
+}
+
+#[test]
+pub fn test_reverse_comparator() {
+    let mut x = RBTree::with_comparator(Reverse);
+    x.insert(5);
+    x.insert(1);
+    x.insert(9);
+    x.insert(3);
+    x.insert(7);
+    assert_eq!(x.first().unwrap(), 9);
+    assert_eq!(x.last().unwrap(), 1);
+    assert_eq!(x.range(8, 2), vec![7, 5, 3]);
+}
+
+#[test]
+pub fn test_case_insensitive_comparator() {
+    let mut x = RBTree::with_comparator(|a: &&str, b: &&str| a.to_lowercase().cmp(&b.to_lowercase()));
+    x.insert("b");
+    x.insert("A");
+    x.insert("c");
+    x.insert("B");
+    assert_eq!(x.count(), 3);
+    assert!(x.contains(&"C"));
+    assert_eq!(x.first().unwrap(), "A");
+    assert_eq!(x.last().unwrap(), "c");
+}
+
+#[test]
+pub fn test_by_key_comparator() {
+    // order words by length only
+    let mut x = RBTree::with_comparator(ByKey(|word: &&str| word.len()));
+    x.insert("ccc");
+    x.insert("a");
+    x.insert("bb");
+    x.insert("dddd");
+    assert_eq!(x.first().unwrap(), "a");
+    assert_eq!(x.last().unwrap(), "dddd");
+    assert_eq!(x.range("xx", "zzzz"), vec!["bb", "ccc"]);
+}
+
+#[test]
+pub fn test_total_order_floats() {
+    let mut x = RBTree::with_comparator(TotalOrder);
+    for key in [2.5, -1.0, f64::NAN, 0.0, -0.0, f64::NEG_INFINITY] {
+        x.insert(key);
+    }
+    // -0.0 sorts before 0.0 and NaN after everything else
+    let keys: Vec<f64> = x.iter().collect();
+    assert_eq!(keys.len(), 6);
+    assert_eq!(keys[..3], [f64::NEG_INFINITY, -1.0, -0.0]);
+    assert!(keys[2].is_sign_negative() && keys[3].is_sign_positive());
+    assert_eq!(keys[4], 2.5);
+    assert!(keys[5].is_nan());
+    assert!(x.contains(&f64::NAN));
+}
+
+#[test]
+pub fn test_delete_random() {
+    let mut x = RBTree::new();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    for i in 0..2000 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let key = (seed % 500) as u32;
+        if i % 3 == 0 && keys.contains(&key) {
+            x.delete(key);
+            keys.remove(&key);
+        } else if !keys.contains(&key) {
+            x.insert(key);
+            keys.insert(key);
+        }
+        check_rb(&x.root);
+    }
+    assert_eq!(x.count() as usize, keys.len());
+    assert_eq!(x.range(0, 500), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_multiset_insert() {
+    let mut x = RBTree::new_multiset();
+    x.insert(5);
+    x.insert(3);
+    x.insert(5);
+    x.insert(8);
+    x.insert(5);
+    assert_eq!(x.count(), 5);
+    assert_eq!(x.count_of(5), 3);
+    assert_eq!(x.count_of(4), 0);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![3, 5, 5, 5, 8]);
+    assert_eq!(x.range(4, 9), vec![5, 5, 5, 8]);
+}
+
+#[test]
+pub fn test_multiset_remove() {
+    let mut x = RBTree::new_multiset();
+    for key in [10, 5, 20, 5, 5, 20, 1] {
+        x.insert(key);
+    }
+    assert!(x.remove_one(5));
+    assert_eq!(x.count_of(5), 2);
+    assert_eq!(x.count(), 6);
+    assert_eq!(x.remove_all(20), 2);
+    assert!(!x.contains(&20));
+    assert_eq!(x.count(), 4);
+    assert!(x.remove_one(1));
+    assert!(!x.remove_one(1));
+    assert_eq!(x.remove_all(1), 0);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![5, 5, 10]);
+    check_rb(&x.root);
+}
+
+#[test]
+pub fn test_set_ignores_duplicates() {
+    let mut x = RBTree::new();
+    x.insert(2);
+    x.insert(2);
+    assert_eq!(x.count(), 1);
+    assert_eq!(x.count_of(2), 1);
+    assert!(x.remove_one(2));
+    assert!(x.is_empty());
+    assert_eq!(x.count(), 0);
+}
+
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct WordCount {
+    word: &'static str,
+    count: u32,
+}
+
+#[cfg(test)]
+impl fmt::Display for WordCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.word, self.count)
+    }
+}
+
+#[test]
+pub fn test_entry_counting() {
+    // words are ordered by the word alone, so the count can be updated in place
+    let mut x = RBTree::with_comparator(ByKey(|w: &WordCount| w.word));
+    for word in ["b", "a", "b", "c", "b", "a"] {
+        x.entry(WordCount { word, count: 1 }).and_modify(|w| w.count += 1).or_default();
+    }
+    assert_eq!(x.count(), 3);
+    let counts: Vec<(&str, u32)> = x.iter().map(|w| (w.word, w.count)).collect();
+    assert_eq!(counts, vec![("a", 2), ("b", 3), ("c", 1)]);
+    check_rb(&x.root);
+}
+
+#[test]
+pub fn test_entry_or_insert() {
+    let mut x = RBTree::new();
+    assert_eq!(x.entry(5).or_insert(5), 5);
+    assert_eq!(x.entry(5).or_insert_with(|| panic!("occupied entry must not build a default")), 5);
+    match x.entry(7) {
+        Entry::Vacant(entry) => assert_eq!(entry.key(), 7),
+        Entry::Occupied(_) => panic!("7 was never inserted"),
+    }
+    assert_eq!(x.count(), 1);
+    assert_eq!(x.entry(3).or_insert_with(|| 3), 3);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), vec![3, 5]);
+}
+
+#[test]
+pub fn test_range_sum_random() {
+    // sums over random ranges of a multiset, checked against a plain list after every operation
+    let mut x: RBTree<u64, Natural, Aggregate<Sum>> = RBTree::multiset_augmented(Natural);
+    let mut keys: Vec<u64> = Vec::new();
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for i in 0..2000 {
+        let key = next() % 300;
+        if i % 3 == 0 && !keys.is_empty() {
+            let victim = keys.swap_remove(next() as usize % keys.len());
+            assert!(x.remove_one(victim));
+        } else if i % 11 == 0 {
+            let before = keys.len();
+            keys.retain(|k| *k != key);
+            assert_eq!(x.remove_all(key) as usize, before - keys.len());
+        } else {
+            x.insert(key);
+            keys.push(key);
+        }
+        check_rb(&x.root);
+        let lo = next() % 300;
+        let hi = lo + next() % 100;
+        let expected: u64 = keys.iter().filter(|k| lo <= **k && **k < hi).sum();
+        assert_eq!(x.range_aggregate(lo, hi), expected);
+        assert_eq!(x.aggregate(), keys.iter().sum::<u64>());
+    }
+}
+
+#[test]
+pub fn test_range_min_gcd_count() {
+    let mut mins: RBTree<u32, Natural, Aggregate<Min>> = RBTree::augmented(Natural);
+    let mut gcds: RBTree<u32, Natural, Aggregate<Gcd>> = RBTree::augmented(Natural);
+    // ranges follow the comparator, so with Reverse they run from high keys down to low ones
+    let mut counts: RBTree<u32, Reverse, Aggregate<Count>> = RBTree::augmented(Reverse);
+    for key in [12, 18, 30, 7, 42, 54] {
+        mins.insert(key);
+        gcds.insert(key);
+        counts.insert(key);
+    }
+    assert_eq!(mins.range_aggregate(10, 50), Some(12));
+    assert_eq!(mins.range_aggregate(43, 50), None);
+    assert_eq!(mins.aggregate(), Some(7));
+    assert_eq!(gcds.range_aggregate(12, 55), 6);
+    assert_eq!(gcds.range_aggregate(18, 19), 18);
+    assert_eq!(gcds.aggregate(), 1);
+    gcds.delete(7);
+    assert_eq!(gcds.aggregate(), 6);
+    assert_eq!(counts.range_aggregate(42, 12), 3);
+    assert_eq!(counts.aggregate(), 6);
+}
+
+#[test]
+pub fn test_cursor_walk() {
+    let mut x = RBTree::new();
+    for key in (0..100).map(|i| i * 2) {
+        x.insert(key);
+    }
+    let mut cursor = x.lower_bound(Bound::Included(&51));
+    assert_eq!(cursor.key(), Some(52));
+    assert_eq!(cursor.peek_prev(), Some(50));
+    let mut seen = Vec::new();
+    while let Some(key) = cursor.key() {
+        seen.push(key);
+        cursor.move_next();
+    }
+    assert_eq!(seen, (26..100).map(|i| i * 2).collect::<Vec<u32>>());
+    // past the end wraps round to the front
+    cursor.move_next();
+    assert_eq!(cursor.key(), Some(0));
+    cursor.move_prev();
+    assert_eq!(cursor.key(), None);
+    cursor.move_prev();
+    assert_eq!(cursor.key(), Some(198));
+
+    assert_eq!(x.lower_bound(Bound::Excluded(&52)).key(), Some(54));
+    assert_eq!(x.upper_bound(Bound::Included(&52)).key(), Some(52));
+    assert_eq!(x.upper_bound(Bound::Excluded(&52)).key(), Some(50));
+    assert_eq!(x.upper_bound(Bound::Unbounded).key(), Some(198));
+    assert_eq!(x.lower_bound(Bound::Included(&199)).key(), None);
+}
+
+#[test]
+pub fn test_cursor_mut_edits() {
+    let mut x: RBTree<u32> = RBTree::new();
+    let mut cursor = x.lower_bound_mut(Bound::Unbounded);
+    cursor.insert_after(50);
+    cursor.move_next();
+    assert_eq!(cursor.key(), Some(50));
+    // fill in both sides of the cursor, it stays on 50 throughout
+    for i in 1..50 {
+        cursor.insert_after(100 - i);
+        cursor.insert_before(i);
+    }
+    assert_eq!(cursor.key(), Some(50));
+    assert_eq!(cursor.peek_next(), Some(51));
+    assert_eq!(cursor.peek_prev(), Some(49));
+    // remove every odd key on the way through
+    let mut cursor = x.lower_bound_mut(Bound::Unbounded);
+    while let Some(key) = cursor.key() {
+        if key % 2 == 1 {
+            assert_eq!(cursor.remove_current(), Some(key));
+        } else {
+            cursor.move_next();
+        }
+    }
+    check_rb(&x.root);
+    assert_eq!(x.count(), 49);
+    assert_eq!(x.iter().collect::<Vec<u32>>(), (1..50).map(|i| i * 2).collect::<Vec<u32>>());
+}
+
+#[test]
+#[should_panic]
+pub fn test_cursor_insert_out_of_order() {
+    let mut x = RBTree::new();
+    x.insert(10);
+    x.insert(20);
+    x.lower_bound_mut(Bound::Included(&10)).insert_after(25);
+}
+
+#[test]
+pub fn test_shape() {
+    let mut x = RBTree::new();
+    for key in [5, 3, 8, 9] {
+        x.insert(key);
+    }
+    let shape = x.shape();
+    let keys: Vec<u32> = shape.nodes.iter().map(|node| node.keys[0]).collect();
+    assert_eq!(keys, vec![5, 3, 8, 9]);
+    assert_eq!(shape.nodes[2].children, vec![None, Some(3)]);
+    assert_eq!(shape.nodes[0].attrs, vec![("color", Attr::Text("black"))]);
+    assert_eq!(shape.nodes[3].attrs, vec![("color", Attr::Text("red"))]);
+    assert!(RBTree::<u32>::new().shape().root().is_none());
+}
+
+#[test]
+pub fn test_rotation_count() {
+    let mut tree = RBTree::new();
+    tree.insert(1);
+    tree.insert(2);
+    assert_eq!(tree.rotations(), 0);
+    tree.insert(3);
+    assert_eq!(tree.rotations(), 1);
+    // 3, 1, 2 needs a left rotation at 1 and a right rotation at 3
+    let mut zigzag = RBTree::new();
+    for key in [3, 1, 2] {
+        zigzag.insert(key);
+    }
+    assert_eq!(zigzag.rotations(), 2);
+}
+
+#[test]
+pub fn test_trace_steps() {
+    let mut tree = RBTree::new();
+    tree.insert(1);
+    tree.trace(true);
+    tree.insert(2);
+    tree.insert(3);
+    let steps = tree.take_steps();
+    let actions: Vec<&str> = steps.iter().map(|step| step.action.as_str()).collect();
+    assert_eq!(actions, vec!["attach 2 as a red leaf", "attach 3 as a red leaf", "recolor 2 black and 1 red", "rotate left at 1"]);
+    // the last step leaves the tree as it ends up
+    assert_eq!(steps.last().unwrap().shape, tree.shape());
+    assert_eq!(steps[1].shape.levelorder(), vec![&1, &2, &3]);
+    assert!(tree.take_steps().is_empty());
+
+    tree.delete(1);
+    tree.delete(3);
+    let actions: Vec<String> = tree.take_steps().into_iter().map(|step| step.action).collect();
+    assert_eq!(actions, vec!["remove 1", "remove 3"]);
+    // every fix-up case ends on the tree's final shape
+    for key in 10..=30 {
+        tree.insert(key);
+        assert_eq!(tree.take_steps().last().map(|step| step.shape.clone()), Some(tree.shape()));
+    }
+    for key in (10..=30).step_by(2).chain([11, 29, 17, 2]) {
+        tree.delete(key);
+        assert_eq!(tree.take_steps().last().map(|step| step.shape.clone()), Some(tree.shape()));
+    }
+    tree.trace(false);
+    tree.insert(4);
+    assert!(tree.take_steps().is_empty());
+}
+
+#[test]
+pub fn test_validate() {
+    let mut rng = crate::rng::Rng::new(48);
+    let mut tree = RBTree::new();
+    for _ in 0..2000 {
+        let key = rng.below(200) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+        } else if !tree.contains(&key) {
+            tree.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+    }
+}
+
+#[test]
+pub fn test_memory_usage() {
+    let empty = RBTree::<u32>::new().memory_usage();
+    assert_eq!((empty.total(), empty.bytes_per_key()), (0, 0.0));
+    let mut tree = RBTree::new();
+    for key in 0..100u32 {
+        tree.insert(key);
+    }
+    let usage = tree.memory_usage();
+    assert_eq!((usage.nodes, usage.keys), (100, 100));
+    assert_eq!(usage.total(), 100 * usage.node_bytes);
+    assert_eq!(usage.key_bytes + usage.rc_counter_bytes + usage.borrow_flag_bytes + usage.child_bytes + usage.parent_bytes
+        + usage.balance_bytes + usage.extra_bytes + usage.padding_bytes, usage.node_bytes);
+    assert_eq!(usage.parent_bytes, size_of::<usize>());
+    assert_eq!(usage.child_bytes, 2 * size_of::<usize>());
+    // a multiset counts repeats in the node they share
+    let mut multiset = RBTree::new_multiset();
+    for key in [5u32, 5, 5, 7] {
+        multiset.insert(key);
+    }
+    let usage = multiset.memory_usage();
+    assert_eq!((usage.nodes, usage.keys), (2, 4));
+    assert_eq!(usage.bytes_per_key(), usage.node_bytes as f64 / 2.0);
+}
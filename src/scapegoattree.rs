@@ -0,0 +1,596 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use crate::shape::Shape;
+use crate::compare::{Comparator, Natural};
+
+type TreeNode<T> = Rc<RefCell<Node<T>>>;
+type Tree<T> = Option<TreeNode<T>>;
+
+// used when no alpha is given, between 0.5 (always perfectly balanced) and 1 (never rebuilt)
+const DEFAULT_ALPHA: f64 = 0.7;
+
+#[derive(Clone)]
+struct Node<T: Display+Debug> {
+    key: T,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T>
+where T: Debug+Display+Copy
+{
+    pub fn new(key: T) -> TreeNode<T> {
+        Rc::new(RefCell::new(Node {
+            key,
+            left: None,
+            right: None,
+        }))
+    }
+}
+
+impl<T> fmt::Debug for Node<T>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+         .field("key", &self.key)
+         .field("right", &self.right)
+         .field("left", &self.left)
+         .finish()
+    }
+}
+
+fn is_node<T: Display+Debug>(tree: &Tree<T>, node: &TreeNode<T>) -> bool {
+    tree.as_ref().is_some_and(|tree| Rc::ptr_eq(tree, node))
+}
+
+// nodes are not sized, so this walks the whole subtree
+fn size<T: Display+Debug>(tree: &Tree<T>) -> usize {
+    let mut stack: Vec<TreeNode<T>> = tree.clone().into_iter().collect();
+    let mut count = 0;
+    while let Some(node) = stack.pop() {
+        count += 1;
+        stack.extend(node.borrow().left.clone());
+        stack.extend(node.borrow().right.clone());
+    }
+    count
+}
+
+// rebuild the subtree under node into a perfectly balanced one, reusing its nodes
+fn rebuild<T: Display+Debug>(node: TreeNode<T>) -> TreeNode<T> {
+    fn build<T: Display+Debug>(nodes: &[TreeNode<T>]) -> Tree<T> {
+        if nodes.is_empty() {
+            return None;
+        }
+        let mid = nodes.len() / 2;
+        let node = nodes[mid].clone();
+        node.borrow_mut().left = build(&nodes[..mid]);
+        node.borrow_mut().right = build(&nodes[mid + 1..]);
+        Some(node)
+    }
+
+    // flatten in order, unlinking every node as it is visited
+    let mut nodes = Vec::new();
+    let mut stack = Vec::new();
+    let mut cur = Some(node);
+    while cur.is_some() || !stack.is_empty() {
+        while let Some(node) = cur {
+            cur = node.borrow_mut().left.take();
+            stack.push(node);
+        }
+        let node = stack.pop().unwrap();
+        cur = node.borrow_mut().right.take();
+        nodes.push(node);
+    }
+    build(&nodes).unwrap()
+}
+
+// keys k with lo <= k < hi in order, skipping subtrees entirely outside
+fn range<T: Display+Debug+Copy, C: Comparator<T>>(tree: &Tree<T>, lo: &T, hi: &T, cmp: &C) -> Vec<T> {
+    let mut keys = Vec::new();
+    let mut stack = Vec::new();
+    let mut cur = tree.clone();
+    while cur.is_some() || !stack.is_empty() {
+        while let Some(node) = cur {
+            // nothing left of a node below lo can be in range
+            cur = if cmp.compare(&node.borrow().key, lo) == Ordering::Less {
+                None
+            } else {
+                node.borrow().left.clone()
+            };
+            stack.push(node);
+        }
+        let node = stack.pop().unwrap();
+        let key = node.borrow().key;
+        if cmp.compare(&key, hi) != Ordering::Less {
+            break;
+        }
+        if cmp.compare(&key, lo) != Ordering::Less {
+            keys.push(key);
+        }
+        cur = node.borrow().right.clone();
+    }
+    keys
+}
+
+// Galperin and Rivest's scapegoat tree: a plain BST with no balance data in the nodes. When an insert
+// lands deeper than log_{1/alpha}(n) some ancestor on the way up is too lopsided, and the subtree
+// under that scapegoat is rebuilt perfectly balanced; deletes rebuild the whole tree once enough of
+// it is gone. Rebuilds are O(size), amortized O(log n) per update.
+#[derive(Clone, Debug)]
+pub struct ScapegoatTree<T: Display+Debug+Copy, C = Natural> {
+    root: Tree<T>,
+    count: usize,
+    // the most keys held since the last full rebuild
+    max_count: usize,
+    alpha: f64,
+    cmp: C,
+}
+
+impl<T> ScapegoatTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // alpha in [0.5, 1), lower keeps the tree shallower at the price of more rebuilds
+    pub fn with_alpha(alpha: f64) -> Self {
+        Self::with_comparator_and_alpha(Natural, alpha)
+    }
+}
+
+impl<T, C> ScapegoatTree<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_comparator_and_alpha(cmp, DEFAULT_ALPHA)
+    }
+
+    pub fn with_comparator_and_alpha(cmp: C, alpha: f64) -> Self {
+        assert!((0.5..1.0).contains(&alpha), "alpha has to be in [0.5, 1)");
+        ScapegoatTree {
+            root: None,
+            count: 0,
+            max_count: 0,
+            alpha,
+            cmp,
+        }
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // deepest a node may sit, in edges from the root, before the tree counts as unbalanced
+    fn depth_limit(&self) -> f64 {
+        (self.count as f64).ln() / (1.0 / self.alpha).ln()
+    }
+
+    pub fn insert(&mut self, key: T) {
+        let mut path: Vec<TreeNode<T>> = Vec::new();
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(&key, &node.borrow().key);
+            cur = match order {
+                // key already in tree
+                Ordering::Equal => return,
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+            path.push(node);
+        }
+        let new_node = Node::new(key);
+        match path.last() {
+            None => self.root = Some(new_node.clone()),
+            Some(parent) => {
+                if self.cmp.compare(&key, &parent.borrow().key) == Ordering::Less {
+                    parent.borrow_mut().left = Some(new_node.clone());
+                } else {
+                    parent.borrow_mut().right = Some(new_node.clone());
+                }
+            }
+        }
+        self.count += 1;
+        self.max_count = self.max_count.max(self.count);
+        if path.len() as f64 > self.depth_limit() {
+            path.push(new_node);
+            self.rebuild_scapegoat(&path);
+        }
+    }
+
+    // walk back up from the new node at the end of path to the first ancestor whose subtree is
+    // lopsided by more than alpha, and rebuild under it
+    fn rebuild_scapegoat(&mut self, path: &[TreeNode<T>]) {
+        let mut child_size = 1;
+        for i in (0..path.len() - 1).rev() {
+            let parent = &path[i];
+            let sibling = if is_node(&parent.borrow().left, &path[i + 1]) {
+                parent.borrow().right.clone()
+            } else {
+                parent.borrow().left.clone()
+            };
+            let parent_size = child_size + size(&sibling) + 1;
+            if child_size as f64 > self.alpha * parent_size as f64 {
+                let rebuilt = rebuild(parent.clone());
+                if i == 0 {
+                    self.root = Some(rebuilt);
+                } else if is_node(&path[i - 1].borrow().left, parent) {
+                    path[i - 1].borrow_mut().left = Some(rebuilt);
+                } else {
+                    path[i - 1].borrow_mut().right = Some(rebuilt);
+                }
+                return;
+            }
+            child_size = parent_size;
+        }
+    }
+
+    pub fn delete(&mut self, key: T) {
+        let mut parent: Tree<T> = None;
+        let mut cur = self.root.clone();
+        let node = loop {
+            let node = match cur {
+                Some(node) => node,
+                // key not in tree
+                None => return,
+            };
+            let order = self.cmp.compare(&key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => break node,
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+            parent = Some(node);
+        };
+        let left = node.borrow().left.clone();
+        let right = node.borrow().right.clone();
+        match (left, right) {
+            (Some(_), Some(right)) => {
+                // move the successor's key up and unlink the successor, it has no left child
+                let mut successor_parent = node.clone();
+                let mut successor = right;
+                loop {
+                    let left = successor.borrow().left.clone();
+                    match left {
+                        Some(left) => {
+                            successor_parent = successor;
+                            successor = left;
+                        },
+                        None => break,
+                    }
+                }
+                node.borrow_mut().key = successor.borrow().key;
+                let successor_right = successor.borrow_mut().right.take();
+                if Rc::ptr_eq(&successor_parent, &node) {
+                    node.borrow_mut().right = successor_right;
+                } else {
+                    successor_parent.borrow_mut().left = successor_right;
+                }
+            },
+            (left, right) => {
+                let child = left.or(right);
+                match parent {
+                    None => self.root = child,
+                    Some(parent) => {
+                        if is_node(&parent.borrow().left, &node) {
+                            parent.borrow_mut().left = child;
+                        } else {
+                            parent.borrow_mut().right = child;
+                        }
+                    }
+                }
+            }
+        }
+        self.count -= 1;
+        if (self.count as f64) < self.alpha * self.max_count as f64 {
+            // enough keys are gone that the depth bound could break, rebuild everything
+            self.root = self.root.take().map(rebuild);
+            self.max_count = self.count;
+        }
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.find(key).map(|node| node.borrow().key)
+    }
+
+    // the node holding key, private so callers cannot change a key and break the ordering
+    fn find(&self, key: &T) -> Tree<T> {
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn range(&self, lo: T, hi: T) -> Vec<T> {
+        range(&self.root, &lo, &hi, &self.cmp)
+    }
+
+    pub fn height(&self) -> u32 {
+        if self.root.is_none() {
+            return 0;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        let mut height = 0;
+        // find height by breadth first search traversal
+        while !queue.is_empty() {
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                    queue.push_back(child);
+                }
+            }
+            height += 1;
+        }
+        height
+    }
+
+    pub fn leaves(&self) -> u32 {
+        let mut stack: Vec<TreeNode<T>> = self.root.clone().into_iter().collect();
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            let left = node.borrow().left.clone();
+            let right = node.borrow().right.clone();
+            if left.is_none() && right.is_none() {
+                count += 1;
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        count
+    }
+
+    // iterate over the keys in order
+    pub fn iter(&self) -> Iter<T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    pub fn print_preorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut stack: Vec<TreeNode<T>> = vec![self.root.clone().unwrap()];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", node.borrow().key);
+            stack.extend(node.borrow().right.clone());
+            stack.extend(node.borrow().left.clone());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", node.borrow().key);
+            queue.extend(node.borrow().left.clone());
+            queue.extend(node.borrow().right.clone());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T>| {
+            let node = node.borrow();
+            (vec![node.key], Vec::new(), vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        };
+
+        fn pretty_print<T: Display+Debug>(node: TreeNode<T>, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&node.borrow().key.to_string());
+            buffer.push('\n');
+            for child in [node.borrow().right.clone(), node.borrow().left.clone()].into_iter().flatten() {
+                let has_children = child.borrow().left.is_some() || child.borrow().right.is_some();
+                let (branch, indent) = if has_children { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(self.root.clone().unwrap(), &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for ScapegoatTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy> {
+    stack: Vec<TreeNode<T>>,
+}
+
+impl<T> Iter<T>
+where T: Display+Debug+Copy
+{
+    fn push_left(&mut self, tree: Tree<T>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T>
+where T: Display+Debug+Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let key = node.borrow().key;
+        self.push_left(node.borrow().right.clone());
+        Some(key)
+    }
+}
+
+impl<T, C> fmt::Display for ScapegoatTree<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScapegoatTree")
+         .field("root", &self.root)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+
+// check key order, the count and the height bound the rebuilds promise
+#[cfg(test)]
+fn check_scapegoat(tree: &ScapegoatTree<u32>) {
+    fn check(tree: &Tree<u32>, lo: Option<u32>, hi: Option<u32>) -> usize {
+        let node = match tree {
+            Some(node) => node.borrow(),
+            None => return 0,
+        };
+        assert!(lo.is_none_or(|lo| node.key > lo) && hi.is_none_or(|hi| node.key < hi));
+        1 + check(&node.left, lo, Some(node.key)) + check(&node.right, Some(node.key), hi)
+    }
+
+    assert_eq!(check(&tree.root, None, None), tree.count());
+    if !tree.is_empty() {
+        let bound = (tree.max_count as f64).ln() / (1.0 / tree.alpha).ln();
+        assert!(tree.height() as f64 <= bound.floor() + 2.0);
+    }
+}
+
+#[test]
+pub fn create_empty_scapegoat_tree() {
+    let tree: ScapegoatTree<u32> = ScapegoatTree::new();
+    assert!(tree.is_empty());
+    assert_eq!(tree.height(), 0);
+    assert_eq!(tree.alpha(), 0.7);
+    assert!(tree.first().is_none());
+}
+
+#[test]
+pub fn test_scapegoat_sorted_inserts_get_rebuilt() {
+    // a plain BST would be a path here
+    let mut tree = ScapegoatTree::with_alpha(0.6);
+    for key in 0..2000 {
+        tree.insert(key);
+        check_scapegoat(&tree);
+    }
+    assert!(tree.height() < 25);
+    assert_eq!(tree.range(10, 15), vec![10, 11, 12, 13, 14]);
+}
+
+#[test]
+pub fn test_scapegoat_deletes_rebuild_whole_tree() {
+    let mut tree = ScapegoatTree::new();
+    for key in 0..1000 {
+        tree.insert(key);
+    }
+    for key in 0..900 {
+        tree.delete(key);
+        check_scapegoat(&tree);
+    }
+    tree.delete(5000);
+    assert_eq!(tree.count(), 100);
+    assert!(tree.max_count < 1000);
+    assert_eq!(tree.first().unwrap(), 900);
+}
+
+#[test]
+pub fn test_scapegoat_random() {
+    let mut tree = ScapegoatTree::new();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut rng = crate::rng::Rng::new(36);
+    for _ in 0..3000 {
+        let key = rng.below(500) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+            keys.remove(&key);
+        } else {
+            tree.insert(key);
+            keys.insert(key);
+        }
+        check_scapegoat(&tree);
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+#[should_panic]
+pub fn test_scapegoat_alpha_out_of_range() {
+    let _tree: ScapegoatTree<u32> = ScapegoatTree::with_alpha(0.4);
+}
@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+// A read-only snapshot of a tree's layout, for drawing or exporting a tree without handing out its
+// nodes. Nodes are stored flat in breadth-first order, so the root, if there is one, is nodes[0],
+// and building, walking or dropping a shape never recurses however deep the tree is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shape<T> {
+    pub nodes: Vec<ShapeNode<T>>,
+    // every node of a binary tree has exactly a left and a right slot in children
+    pub binary: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeNode<T> {
+    pub keys: Vec<T>,
+    // what the tree balances on, e.g. ("color", Attr::Text("red")) or ("height", Attr::Number(3))
+    pub attrs: Vec<(&'static str, Attr)>,
+    // indices into nodes, None for a missing child
+    pub children: Vec<Option<usize>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attr {
+    Text(&'static str),
+    Number(u64),
+}
+
+impl<T> Shape<T> {
+    // walk the tree breadth first from root, expand gives a node's keys, attributes and child slots
+    pub(crate) fn build<N, F>(root: Option<N>, binary: bool, expand: F) -> Shape<T>
+    where F: Fn(&N) -> (Vec<T>, Vec<(&'static str, Attr)>, Vec<Option<N>>)
+    {
+        let mut nodes = Vec::new();
+        let mut queue: VecDeque<N> = root.into_iter().collect();
+        // a child's index is its place in the queue, which is the order nodes get pushed in
+        let mut next_index = queue.len();
+        while let Some(node) = queue.pop_front() {
+            let (keys, attrs, slots) = expand(&node);
+            let mut children = Vec::new();
+            for slot in slots {
+                match slot {
+                    Some(child) => {
+                        children.push(Some(next_index));
+                        next_index += 1;
+                        queue.push_back(child);
+                    }
+                    None => children.push(None),
+                }
+            }
+            nodes.push(ShapeNode { keys, attrs, children });
+        }
+        Shape { nodes, binary }
+    }
+
+    pub fn root(&self) -> Option<&ShapeNode<T>> {
+        self.nodes.first()
+    }
+
+    // keys level by level, left to right
+    pub fn levelorder(&self) -> Vec<&T> {
+        self.nodes.iter().flat_map(|node| node.keys.iter()).collect()
+    }
+
+    // each node's keys before any of its children
+    pub fn preorder(&self) -> Vec<&T> {
+        let mut keys = Vec::new();
+        let mut stack: Vec<usize> = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            keys.extend(node.keys.iter());
+            stack.extend(node.children.iter().rev().flatten());
+        }
+        keys
+    }
+
+    // keys in sorted order, child i comes before key i and child i + 1 after it
+    pub fn inorder(&self) -> Vec<&T> {
+        let mut keys = Vec::new();
+        // a node index and how many of its keys are already out
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        let mut next = if self.nodes.is_empty() { None } else { Some(0) };
+        loop {
+            while let Some(index) = next {
+                stack.push((index, 0));
+                next = self.nodes[index].children.first().copied().flatten();
+            }
+            let (index, done) = match stack.pop() {
+                Some(top) => top,
+                None => break,
+            };
+            let node = &self.nodes[index];
+            if done < node.keys.len() {
+                keys.push(&node.keys[done]);
+                stack.push((index, done + 1));
+                next = node.children.get(done + 1).copied().flatten();
+            }
+        }
+        keys
+    }
+}
+
+impl<T: Display> Shape<T> {
+    // the tree drawn one node per line, each child under its parent in slot order with ├── and └──
+    // branches. in a binary tree a lone child's missing sibling is drawn as · so left and right can
+    // be told apart. an empty tree draws as no lines
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        // a slot, the prefix of its own line and the prefix of its children's lines
+        let mut stack = if self.nodes.is_empty() { Vec::new() } else { vec![(Some(0), String::new(), String::new())] };
+        while let Some((slot, prefix, child_prefix)) = stack.pop() {
+            let index = match slot {
+                Some(index) => index,
+                None => {
+                    lines.push(format!("{}·", prefix));
+                    continue;
+                }
+            };
+            let node = &self.nodes[index];
+            lines.push(format!("{}{}", prefix, node.label()));
+            if node.children.iter().all(Option::is_none) {
+                continue;
+            }
+            let last = node.children.len() - 1;
+            // pushed last to first so the first child is drawn first
+            for (i, child) in node.children.iter().enumerate().rev() {
+                let (branch, indent) = if i == last { ("└── ", "    ") } else { ("├── ", "│   ") };
+                stack.push((*child, format!("{}{}", child_prefix, branch), format!("{}{}", child_prefix, indent)));
+            }
+        }
+        lines
+    }
+}
+
+impl<T: Display> ShapeNode<T> {
+    // the keys followed by the attributes, e.g. "5 (red)" or "10 20" or "7 (height=2)"
+    pub fn label(&self) -> String {
+        let keys: Vec<String> = self.keys.iter().map(|key| key.to_string()).collect();
+        let mut label = keys.join(" ");
+        if !self.attrs.is_empty() {
+            let attrs: Vec<String> = self.attrs.iter().map(|(name, attr)| match attr {
+                Attr::Text(text) => text.to_string(),
+                Attr::Number(number) => format!("{}={}", name, number),
+            }).collect();
+            label.push_str(&format!(" ({})", attrs.join(", ")));
+        }
+        label
+    }
+}
+
+#[test]
+pub fn test_build_shape() {
+    // 2 with children 1 and 3, where 3 only has a right child 4
+    let shape: Shape<u32> = Shape::build(Some(2u32), true, |key: &u32| {
+        let children = match key {
+            2 => vec![Some(1), Some(3)],
+            3 => vec![None, Some(4)],
+            _ => vec![None, None],
+        };
+        (vec![*key], vec![("height", Attr::Number(1))], children)
+    });
+    let keys: Vec<u32> = shape.nodes.iter().map(|node| node.keys[0]).collect();
+    assert_eq!(keys, vec![2, 1, 3, 4]);
+    assert_eq!(shape.nodes[0].children, vec![Some(1), Some(2)]);
+    assert_eq!(shape.nodes[2].children, vec![None, Some(3)]);
+    assert_eq!(shape.root().unwrap().attrs, vec![("height", Attr::Number(1))]);
+
+    assert_eq!(shape.inorder(), vec![&1, &2, &3, &4]);
+    assert_eq!(shape.preorder(), vec![&2, &1, &3, &4]);
+    assert_eq!(shape.levelorder(), vec![&2, &1, &3, &4]);
+
+    let empty: Shape<u32> = Shape::build(None, true, |_: &u32| (vec![], vec![], vec![]));
+    assert!(empty.root().is_none());
+}
+
+#[test]
+pub fn test_multiway_orders() {
+    // [10, 20] over leaves [5], [15] and [25, 30]
+    let shape: Shape<u32> = Shape::build(Some(0usize), false, |index: &usize| match index {
+        0 => (vec![10, 20], Vec::new(), vec![Some(1), Some(2), Some(3)]),
+        1 => (vec![5], Vec::new(), Vec::new()),
+        2 => (vec![15], Vec::new(), Vec::new()),
+        _ => (vec![25, 30], Vec::new(), Vec::new()),
+    });
+    assert_eq!(shape.inorder(), vec![&5, &10, &15, &20, &25, &30]);
+    assert_eq!(shape.preorder(), vec![&10, &20, &5, &15, &25, &30]);
+    assert_eq!(shape.levelorder(), vec![&10, &20, &5, &15, &25, &30]);
+}
+
+#[test]
+pub fn test_lines() {
+    // 2 with children 1 and 3, where 3 only has a right child 4
+    let shape: Shape<u32> = Shape::build(Some(2u32), true, |key: &u32| {
+        let children = match key {
+            2 => vec![Some(1), Some(3)],
+            3 => vec![None, Some(4)],
+            _ => vec![None, None],
+        };
+        let attrs = if *key == 2 { vec![("color", Attr::Text("black")), ("occurrences", Attr::Number(2))] } else { Vec::new() };
+        (vec![*key], attrs, children)
+    });
+    assert_eq!(shape.lines(), vec!["2 (black, occurrences=2)", "├── 1", "└── 3", "    ├── ·", "    └── 4"]);
+
+    let multiway: Shape<u32> = Shape::build(Some(0usize), false, |index: &usize| match index {
+        0 => (vec![10, 20], Vec::new(), vec![Some(1), Some(2)]),
+        1 => (vec![5], Vec::new(), Vec::new()),
+        _ => (vec![15, 25], Vec::new(), Vec::new()),
+    });
+    assert_eq!(multiway.lines(), vec!["10 20", "├── 5", "└── 15 25"]);
+    assert!(Shape::<u32>::build(None, true, |_: &u32| (vec![], vec![], vec![])).lines().is_empty());
+}
@@ -0,0 +1,550 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use crate::shape::Shape;
+use crate::compare::{Comparator, Natural};
+
+type TreeNode<T> = Rc<RefCell<Node<T>>>;
+type Tree<T> = Option<TreeNode<T>>;
+
+#[derive(Clone)]
+struct Node<T: Display+Debug> {
+    key: T,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T>
+where T: Debug+Display+Copy
+{
+    pub fn new(key: T) -> Tree<T> {
+        Some(Rc::new(RefCell::new(Node {
+            key,
+            left: None,
+            right: None,
+        })))
+    }
+}
+
+impl<T> fmt::Debug for Node<T>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+         .field("key", &self.key)
+         .field("right", &self.right)
+         .field("left", &self.left)
+         .finish()
+    }
+}
+
+// Self-adjusting tree: every access splays the key it touched up to the root, so keys that are
+// looked up again and again stay near the top. Not Clone since a shallow copy would share nodes
+// that the next search moves around.
+#[derive(Debug)]
+pub struct SplayTree<T: Display+Debug+Copy, C = Natural> {
+    root: Tree<T>,
+    count: usize,
+    cmp: C,
+}
+
+impl<T> SplayTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C> SplayTree<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        SplayTree {
+            root: None,
+            count: 0,
+            cmp,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // top-down splay, brings the node for key (or the last node on its search path) to the root
+    fn splay(&self, tree: Tree<T>, key: &T) -> Tree<T> {
+        let mut t = tree?;
+        // nodes known to be smaller than key hang off left_last, the biggest of them so far,
+        // and nodes known to be bigger hang off right_last, the smallest of them so far
+        let mut left_root: Tree<T> = None;
+        let mut left_last: Tree<T> = None;
+        let mut right_root: Tree<T> = None;
+        let mut right_last: Tree<T> = None;
+        loop {
+            let order = self.cmp.compare(key, &t.borrow().key);
+            match order {
+                Ordering::Less => {
+                    let left = t.borrow().left.clone();
+                    let mut child = match left {
+                        Some(child) => child,
+                        None => break,
+                    };
+                    if self.cmp.compare(key, &child.borrow().key) == Ordering::Less {
+                        // zig-zig, rotate right before going down two levels
+                        t.borrow_mut().left = child.borrow_mut().right.take();
+                        child.borrow_mut().right = Some(t);
+                        t = child;
+                        let left = t.borrow().left.clone();
+                        child = match left {
+                            Some(child) => child,
+                            None => break,
+                        };
+                    }
+                    // t and its right subtree are all bigger than key
+                    t.borrow_mut().left = None;
+                    match right_last.take() {
+                        Some(last) => last.borrow_mut().left = Some(t.clone()),
+                        None => right_root = Some(t.clone()),
+                    }
+                    right_last = Some(t);
+                    t = child;
+                },
+                Ordering::Greater => {
+                    let right = t.borrow().right.clone();
+                    let mut child = match right {
+                        Some(child) => child,
+                        None => break,
+                    };
+                    if self.cmp.compare(key, &child.borrow().key) == Ordering::Greater {
+                        // zag-zag, rotate left before going down two levels
+                        t.borrow_mut().right = child.borrow_mut().left.take();
+                        child.borrow_mut().left = Some(t);
+                        t = child;
+                        let right = t.borrow().right.clone();
+                        child = match right {
+                            Some(child) => child,
+                            None => break,
+                        };
+                    }
+                    // t and its left subtree are all smaller than key
+                    t.borrow_mut().right = None;
+                    match left_last.take() {
+                        Some(last) => last.borrow_mut().right = Some(t.clone()),
+                        None => left_root = Some(t.clone()),
+                    }
+                    left_last = Some(t);
+                    t = child;
+                },
+                Ordering::Equal => break,
+            }
+        }
+        // put t's children under the two side trees and the side trees under t
+        {
+            let mut node = t.borrow_mut();
+            if let Some(last) = left_last {
+                last.borrow_mut().right = node.left.take();
+                node.left = left_root;
+            }
+            if let Some(last) = right_last {
+                last.borrow_mut().left = node.right.take();
+                node.right = right_root;
+            }
+        }
+        Some(t)
+    }
+
+    pub fn insert(&mut self, key: T) {
+        let root = self.root.take();
+        let root = match self.splay(root, &key) {
+            Some(root) => root,
+            None => {
+                self.root = Node::new(key);
+                self.count += 1;
+                return;
+            }
+        };
+        let order = self.cmp.compare(&key, &root.borrow().key);
+        let added_node = Node::new(key).unwrap();
+        match order {
+            Ordering::Equal => {
+                // key already in tree, it is the root now
+                self.root = Some(root);
+                return;
+            },
+            Ordering::Less => {
+                added_node.borrow_mut().left = root.borrow_mut().left.take();
+                added_node.borrow_mut().right = Some(root);
+            },
+            Ordering::Greater => {
+                added_node.borrow_mut().right = root.borrow_mut().right.take();
+                added_node.borrow_mut().left = Some(root);
+            }
+        }
+        self.root = Some(added_node);
+        self.count += 1;
+    }
+
+    pub fn delete(&mut self, key: T) {
+        let root = self.root.take();
+        let root = match self.splay(root, &key) {
+            Some(root) => root,
+            None => return,
+        };
+        if self.cmp.compare(&key, &root.borrow().key) != Ordering::Equal {
+            // key not found
+            self.root = Some(root);
+            return;
+        }
+        let left = root.borrow_mut().left.take();
+        let right = root.borrow_mut().right.take();
+        self.root = match left {
+            None => right,
+            Some(left) => {
+                // every key on the left is smaller, so splaying for key brings the left side's max up
+                // and leaves it without a right child
+                let new_root = self.splay(Some(left), &key).unwrap();
+                new_root.borrow_mut().right = right;
+                Some(new_root)
+            }
+        };
+        self.count -= 1;
+    }
+
+    // splays key (or its closest neighbour) to the root, so searching is a mutating operation
+    // both lookups splay the last node reached to the root, so they need the tree mutably
+    pub fn contains(&mut self, key: &T) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&mut self, key: &T) -> Option<T> {
+        let root = self.root.take();
+        self.root = self.splay(root, key);
+        match self.root {
+            Some(ref root) if self.cmp.compare(key, &root.borrow().key) == Ordering::Equal => Some(root.borrow().key),
+            _ => None,
+        }
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        if self.root.is_none() {
+            return 0;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        let mut height = 0;
+        // breadth first, the tree can be a long path so no recursion
+        while !queue.is_empty() {
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                    queue.push_back(child);
+                }
+            }
+            height += 1;
+        }
+        height
+    }
+
+    pub fn leaves(&self) -> u32 {
+        let mut stack: Vec<TreeNode<T>> = self.root.clone().into_iter().collect();
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            let left = node.borrow().left.clone();
+            let right = node.borrow().right.clone();
+            if left.is_none() && right.is_none() {
+                count += 1;
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        count
+    }
+
+    // iterate over the keys in order, without splaying
+    pub fn iter(&self) -> Iter<T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    pub fn print_preorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut stack: Vec<TreeNode<T>> = vec![self.root.clone().unwrap()];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", node.borrow().key);
+            stack.extend(node.borrow().right.clone());
+            stack.extend(node.borrow().left.clone());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", node.borrow().key);
+            queue.extend(node.borrow().left.clone());
+            queue.extend(node.borrow().right.clone());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T>| {
+            let node = node.borrow();
+            (vec![node.key], Vec::new(), vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        };
+
+        fn pretty_print<T: Display+Debug>(node: TreeNode<T>, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&node.borrow().key.to_string());
+            buffer.push('\n');
+            for child in [node.borrow().right.clone(), node.borrow().left.clone()].into_iter().flatten() {
+                let has_children = child.borrow().left.is_some() || child.borrow().right.is_some();
+                let (branch, indent) = if has_children { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(self.root.clone().unwrap(), &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for SplayTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// a long left or right path would otherwise be dropped one recursive call per node
+impl<T, C> Drop for SplayTree<T, C>
+where T: Display+Debug+Copy
+{
+    fn drop(&mut self) {
+        let mut stack: Vec<TreeNode<T>> = self.root.take().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            // nodes still held by a search result are left alone
+            if let Ok(node) = Rc::try_unwrap(node) {
+                let mut node = node.into_inner();
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy> {
+    stack: Vec<TreeNode<T>>,
+}
+
+impl<T> Iter<T>
+where T: Display+Debug+Copy
+{
+    fn push_left(&mut self, tree: Tree<T>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T>
+where T: Display+Debug+Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let key = node.borrow().key;
+        self.push_left(node.borrow().right.clone());
+        Some(key)
+    }
+}
+
+impl<T, C> fmt::Display for SplayTree<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SplayTree")
+         .field("root", &self.root)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+
+#[cfg(test)]
+use crate::compare::Reverse;
+
+#[cfg(test)]
+fn root_key<T: Display+Debug+Copy, C>(tree: &SplayTree<T, C>) -> Option<T> {
+    tree.root.as_ref().map(|root| root.borrow().key)
+}
+
+#[test]
+pub fn create_empty_splaytree() {
+    let tree: SplayTree<u32> = SplayTree::new();
+    assert!(tree.is_empty());
+    assert_eq!(tree.height(), 0);
+    assert_eq!(tree.leaves(), 0);
+}
+
+#[test]
+pub fn test_insert_splays_to_root() {
+    let mut tree = SplayTree::new();
+    for key in [5, 3, 8, 1, 4] {
+        tree.insert(key);
+        // the newest key always ends up at the root
+        assert_eq!(root_key(&tree), Some(key));
+    }
+    tree.insert(3);
+    assert_eq!(tree.count(), 5);
+    assert_eq!(root_key(&tree), Some(3));
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), vec![1, 3, 4, 5, 8]);
+}
+
+#[test]
+pub fn test_search_hot_key() {
+    let mut tree = SplayTree::new();
+    for key in 0..100 {
+        tree.insert(key);
+    }
+    // sequential inserts leave a single path
+    assert_eq!(tree.height(), 100);
+    assert_eq!(tree.get(&0).unwrap(), 0);
+    assert_eq!(root_key(&tree), Some(0));
+    // splaying the deepest key roughly halves the path
+    assert!(tree.height() <= 52);
+    assert!(tree.contains(&42));
+    assert_eq!(root_key(&tree), Some(42));
+    assert!(!tree.contains(&1000));
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), (0..100).collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_splay_delete() {
+    let mut tree = SplayTree::new();
+    for key in [10, 5, 20, 15, 25, 1] {
+        tree.insert(key);
+    }
+    tree.delete(20);
+    tree.delete(7);
+    assert_eq!(tree.count(), 5);
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), vec![1, 5, 10, 15, 25]);
+    tree.delete(1);
+    tree.delete(25);
+    assert_eq!(tree.first().unwrap(), 5);
+    assert_eq!(tree.last().unwrap(), 15);
+    for key in [5, 10, 15] {
+        tree.delete(key);
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.count(), 0);
+}
+
+#[test]
+pub fn test_splay_reverse_comparator() {
+    let mut tree = SplayTree::with_comparator(Reverse);
+    for key in [2, 9, 4, 7] {
+        tree.insert(key);
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), vec![9, 7, 4, 2]);
+    assert_eq!(tree.first().unwrap(), 9);
+}
+
+#[test]
+pub fn test_splay_random() {
+    let mut tree = SplayTree::new();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    for i in 0..3000 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let key = (seed % 500) as u32;
+        if i % 3 == 0 {
+            tree.delete(key);
+            keys.remove(&key);
+        } else if i % 3 == 1 {
+            tree.insert(key);
+            keys.insert(key);
+        } else {
+            assert_eq!(tree.contains(&key), keys.contains(&key));
+        }
+        assert_eq!(tree.count(), keys.len());
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_drop_long_path() {
+    // sorted inserts build a 200000 node path, dropping it must not overflow the stack
+    let mut tree = SplayTree::new();
+    for key in 0..200000 {
+        tree.insert(key);
+    }
+    assert_eq!(tree.count(), 200000);
+    drop(tree);
+}
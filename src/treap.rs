@@ -0,0 +1,467 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use crate::shape::{Attr, Shape};
+use crate::compare::{Comparator, Natural};
+use crate::rng::Rng;
+
+type TreeNode<T> = Rc<RefCell<Node<T>>>;
+type Tree<T> = Option<TreeNode<T>>;
+
+#[derive(Clone)]
+struct Node<T: Display+Debug> {
+    key: T,
+    // random, a parent's priority is never below its children's
+    priority: u64,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T>
+where T: Debug+Display+Copy
+{
+    pub fn new(key: T, priority: u64) -> Tree<T> {
+        Some(Rc::new(RefCell::new(Node {
+            key,
+            priority,
+            left: None,
+            right: None,
+        })))
+    }
+}
+
+impl<T> fmt::Debug for Node<T>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+         .field("key", &self.key)
+         .field("priority", &self.priority)
+         .field("right", &self.right)
+         .field("left", &self.left)
+         .finish()
+    }
+}
+
+// Randomized search tree: a binary search tree by key and a max-heap by priority at the same time.
+// With random priorities its shape is that of a random insertion order, so the expected height is
+// O(log n) whatever order the keys arrive in.
+#[derive(Clone, Debug)]
+pub struct Treap<T: Display+Debug+Copy, C = Natural> {
+    root: Tree<T>,
+    count: usize,
+    cmp: C,
+    rng: Rng,
+}
+
+impl<T> Treap<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // same seed, same priorities, same shape
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_comparator_and_seed(Natural, seed)
+    }
+}
+
+impl<T, C> Treap<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        Treap {
+            root: None,
+            count: 0,
+            cmp,
+            rng: Rng::from_entropy(),
+        }
+    }
+
+    pub fn with_comparator_and_seed(cmp: C, seed: u64) -> Self {
+        Treap {
+            rng: Rng::new(seed),
+            ..Self::with_comparator(cmp)
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // split tree into the keys before key and the rest, or into the keys up to key and the rest with
+    // key_goes_left
+    fn split(&self, tree: Tree<T>, key: &T, key_goes_left: bool) -> (Tree<T>, Tree<T>) {
+        let node = match tree {
+            Some(node) => node,
+            None => return (None, None),
+        };
+        let order = self.cmp.compare(&node.borrow().key, key);
+        let goes_left = order == Ordering::Less || (key_goes_left && order == Ordering::Equal);
+        if goes_left {
+            // node and its left subtree stay on the left, its right subtree gets split further
+            let right = node.borrow_mut().right.take();
+            let (between, rest) = self.split(right, key, key_goes_left);
+            node.borrow_mut().right = between;
+            (Some(node), rest)
+        } else {
+            let left = node.borrow_mut().left.take();
+            let (rest, between) = self.split(left, key, key_goes_left);
+            node.borrow_mut().left = between;
+            (rest, Some(node))
+        }
+    }
+
+    // join two treaps where every key in left comes before every key in right
+    fn merge(&self, left: Tree<T>, right: Tree<T>) -> Tree<T> {
+        match (left, right) {
+            (None, right) => right,
+            (left, None) => left,
+            (Some(left), Some(right)) => {
+                // the higher priority becomes the root
+                if left.borrow().priority >= right.borrow().priority {
+                    let left_right = left.borrow_mut().right.take();
+                    let merged = self.merge(left_right, Some(right));
+                    left.borrow_mut().right = merged;
+                    Some(left)
+                } else {
+                    let right_left = right.borrow_mut().left.take();
+                    let merged = self.merge(Some(left), right_left);
+                    right.borrow_mut().left = merged;
+                    Some(right)
+                }
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: T) {
+        if self.contains(&key) {
+            // key already in tree
+            return;
+        }
+        let priority = self.rng.next_u64();
+        let root = self.root.take();
+        let (left, right) = self.split(root, &key, false);
+        let left = self.merge(left, Node::new(key, priority));
+        self.root = self.merge(left, right);
+        self.count += 1;
+    }
+
+    pub fn delete(&mut self, key: T) {
+        let root = self.root.take();
+        let (left, rest) = self.split(root, &key, false);
+        // cut off the part of rest that is not above key, that is the node for key or nothing
+        let (found, right) = self.split(rest, &key, true);
+        if found.is_some() {
+            self.count -= 1;
+        }
+        self.root = self.merge(left, right);
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.find(key).map(|node| node.borrow().key)
+    }
+
+    // the node holding key, private so callers cannot change a key and break the ordering
+    fn find(&self, key: &T) -> Tree<T> {
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        if self.root.is_none() {
+            return 0;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        let mut height = 0;
+        // find height by breadth first search traversal
+        while !queue.is_empty() {
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                    queue.push_back(child);
+                }
+            }
+            height += 1;
+        }
+        height
+    }
+
+    pub fn leaves(&self) -> u32 {
+        let mut stack: Vec<TreeNode<T>> = self.root.clone().into_iter().collect();
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            let left = node.borrow().left.clone();
+            let right = node.borrow().right.clone();
+            if left.is_none() && right.is_none() {
+                count += 1;
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        count
+    }
+
+    // iterate over the keys in order
+    pub fn iter(&self) -> Iter<T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    pub fn print_preorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut stack: Vec<TreeNode<T>> = vec![self.root.clone().unwrap()];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", node.borrow().key);
+            stack.extend(node.borrow().right.clone());
+            stack.extend(node.borrow().left.clone());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", node.borrow().key);
+            queue.extend(node.borrow().left.clone());
+            queue.extend(node.borrow().right.clone());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout with each node's priority, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T>| {
+            let node = node.borrow();
+            (vec![node.key], vec![("priority", Attr::Number(node.priority))], vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        };
+
+        fn pretty_print<T: Display+Debug>(node: TreeNode<T>, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&node.borrow().key.to_string());
+            buffer.push('\n');
+            for child in [node.borrow().right.clone(), node.borrow().left.clone()].into_iter().flatten() {
+                let has_children = child.borrow().left.is_some() || child.borrow().right.is_some();
+                let (branch, indent) = if has_children { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(self.root.clone().unwrap(), &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for Treap<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy> {
+    stack: Vec<TreeNode<T>>,
+}
+
+impl<T> Iter<T>
+where T: Display+Debug+Copy
+{
+    fn push_left(&mut self, tree: Tree<T>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T>
+where T: Display+Debug+Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let key = node.borrow().key;
+        self.push_left(node.borrow().right.clone());
+        Some(key)
+    }
+}
+
+impl<T, C> fmt::Display for Treap<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Treap")
+         .field("root", &self.root)
+         .field("length", &self.count)
+         .finish()
+    }
+}
+
+
+// check key order and heap order below every node, returns the subtree size
+#[cfg(test)]
+fn check_treap(tree: &Tree<u32>, lo: Option<u32>, hi: Option<u32>) -> usize {
+    let node = match tree {
+        Some(node) => node.borrow(),
+        None => return 0,
+    };
+    assert!(lo.is_none_or(|lo| node.key > lo) && hi.is_none_or(|hi| node.key < hi));
+    for child in [&node.left, &node.right].into_iter().flatten() {
+        assert!(child.borrow().priority <= node.priority);
+    }
+    1 + check_treap(&node.left, lo, Some(node.key)) + check_treap(&node.right, Some(node.key), hi)
+}
+
+#[test]
+pub fn create_empty_treap() {
+    let treap: Treap<u32> = Treap::new();
+    assert!(treap.is_empty());
+    assert_eq!(treap.height(), 0);
+    assert!(treap.first().is_none());
+}
+
+#[test]
+pub fn test_treap_insert_delete() {
+    let mut treap = Treap::with_seed(1);
+    for key in [50, 20, 70, 10, 30, 60, 80, 30] {
+        treap.insert(key);
+    }
+    assert_eq!(treap.count(), 7);
+    assert_eq!(check_treap(&treap.root, None, None), 7);
+    assert_eq!(treap.iter().collect::<Vec<u32>>(), vec![10, 20, 30, 50, 60, 70, 80]);
+    assert_eq!(treap.first().unwrap(), 10);
+    assert_eq!(treap.last().unwrap(), 80);
+    treap.delete(50);
+    treap.delete(55);
+    assert_eq!(treap.count(), 6);
+    assert!(!treap.contains(&50));
+    assert!(treap.contains(&60));
+    assert_eq!(check_treap(&treap.root, None, None), 6);
+}
+
+#[test]
+pub fn test_treap_seed_is_deterministic() {
+    let mut a = Treap::with_seed(99);
+    let mut b = Treap::with_seed(99);
+    for key in 0..200 {
+        a.insert(key);
+        b.insert(key);
+    }
+    assert_eq!(format!("{:?}", a.root), format!("{:?}", b.root));
+}
+
+#[test]
+pub fn test_treap_sorted_inserts_stay_shallow() {
+    // sorted input would be a path in a plain BST
+    let mut treap = Treap::with_seed(5);
+    for key in 0..10000 {
+        treap.insert(key);
+    }
+    assert!(treap.height() < 50);
+    assert_eq!(check_treap(&treap.root, None, None), 10000);
+}
+
+#[test]
+pub fn test_treap_random() {
+    let mut treap = Treap::with_seed(3);
+    let mut keys = std::collections::BTreeSet::new();
+    let mut rng = Rng::new(11);
+    for _ in 0..3000 {
+        let key = rng.below(500) as u32;
+        if rng.below(3) == 0 {
+            treap.delete(key);
+            keys.remove(&key);
+        } else {
+            treap.insert(key);
+            keys.insert(key);
+        }
+        assert_eq!(treap.count(), keys.len());
+    }
+    assert_eq!(check_treap(&treap.root, None, None), keys.len());
+    assert_eq!(treap.iter().collect::<Vec<u32>>(), keys.into_iter().collect::<Vec<u32>>());
+}
+
+#[test]
+pub fn test_treap_reverse_comparator() {
+    let mut treap = Treap::with_comparator_and_seed(crate::compare::Reverse, 8);
+    for key in [3, 1, 2] {
+        treap.insert(key);
+    }
+    assert_eq!(treap.iter().collect::<Vec<u32>>(), vec![3, 2, 1]);
+}
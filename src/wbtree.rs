@@ -0,0 +1,582 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use crate::shape::{Attr, Shape};
+use crate::compare::{Comparator, Natural};
+
+type TreeNode<T> = Rc<RefCell<Node<T>>>;
+type Tree<T> = Option<TreeNode<T>>;
+
+// the (delta, gamma) = (3, 2) parameters of Hirai and Yamamoto, the only integer pair for which
+// one single or double rotation per node is enough after an insert or delete. In BB[alpha] terms
+// that is alpha = 1/4 on weights of size + 1.
+const DELTA: usize = 3;
+const GAMMA: usize = 2;
+
+#[derive(Clone)]
+struct Node<T: Display+Debug> {
+    key: T,
+    // number of keys in the subtree rooted here
+    size: usize,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T>
+where T: Debug+Display+Copy
+{
+    pub fn new(key: T) -> TreeNode<T> {
+        Rc::new(RefCell::new(Node {
+            key,
+            size: 1,
+            left: None,
+            right: None,
+        }))
+    }
+}
+
+impl<T> fmt::Debug for Node<T>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+         .field("key", &self.key)
+         .field("size", &self.size)
+         .field("right", &self.right)
+         .field("left", &self.left)
+         .finish()
+    }
+}
+
+fn size<T: Display+Debug>(tree: &Tree<T>) -> usize {
+    tree.as_ref().map_or(0, |node| node.borrow().size)
+}
+
+fn update_size<T: Display+Debug>(node: &TreeNode<T>) {
+    let size = size(&node.borrow().left) + size(&node.borrow().right) + 1;
+    node.borrow_mut().size = size;
+}
+
+fn rotate_left<T: Display+Debug>(h: TreeNode<T>) -> TreeNode<T> {
+    let x = h.borrow_mut().right.take().unwrap();
+    h.borrow_mut().right = x.borrow_mut().left.take();
+    update_size(&h);
+    x.borrow_mut().left = Some(h);
+    update_size(&x);
+    x
+}
+
+fn rotate_right<T: Display+Debug>(h: TreeNode<T>) -> TreeNode<T> {
+    let x = h.borrow_mut().left.take().unwrap();
+    h.borrow_mut().left = x.borrow_mut().right.take();
+    update_size(&h);
+    x.borrow_mut().right = Some(h);
+    update_size(&x);
+    x
+}
+
+// one side may weigh at most DELTA times the other, otherwise rotate once, or twice when the heavy
+// child leans inward by GAMMA or more
+fn balance<T: Display+Debug>(h: TreeNode<T>) -> TreeNode<T> {
+    let left_weight = size(&h.borrow().left) + 1;
+    let right_weight = size(&h.borrow().right) + 1;
+    if right_weight > DELTA * left_weight {
+        let right = h.borrow_mut().right.take().unwrap();
+        let inner = size(&right.borrow().left) + 1;
+        let outer = size(&right.borrow().right) + 1;
+        h.borrow_mut().right = Some(if inner < GAMMA * outer { right } else { rotate_right(right) });
+        return rotate_left(h);
+    }
+    if left_weight > DELTA * right_weight {
+        let left = h.borrow_mut().left.take().unwrap();
+        let inner = size(&left.borrow().right) + 1;
+        let outer = size(&left.borrow().left) + 1;
+        h.borrow_mut().left = Some(if inner < GAMMA * outer { left } else { rotate_left(left) });
+        return rotate_right(h);
+    }
+    update_size(&h);
+    h
+}
+
+// remove the smallest key below h, returns what is left of the subtree and the key
+fn delete_min<T: Display+Debug+Copy>(h: TreeNode<T>) -> (Tree<T>, T) {
+    let left = h.borrow_mut().left.take();
+    match left {
+        None => {
+            let key = h.borrow().key;
+            let right = h.borrow_mut().right.take();
+            (right, key)
+        },
+        Some(left) => {
+            let (left, key) = delete_min(left);
+            h.borrow_mut().left = left;
+            (Some(balance(h)), key)
+        }
+    }
+}
+
+// keys k with lo <= k < hi in order, skipping subtrees entirely outside
+fn range<T: Display+Debug+Copy, C: Comparator<T>>(tree: &Tree<T>, lo: &T, hi: &T, cmp: &C) -> Vec<T> {
+    let mut keys = Vec::new();
+    let mut stack = Vec::new();
+    let mut cur = tree.clone();
+    while cur.is_some() || !stack.is_empty() {
+        while let Some(node) = cur {
+            // nothing left of a node below lo can be in range
+            cur = if cmp.compare(&node.borrow().key, lo) == Ordering::Less {
+                None
+            } else {
+                node.borrow().left.clone()
+            };
+            stack.push(node);
+        }
+        let node = stack.pop().unwrap();
+        let key = node.borrow().key;
+        if cmp.compare(&key, hi) != Ordering::Less {
+            break;
+        }
+        if cmp.compare(&key, lo) != Ordering::Less {
+            keys.push(key);
+        }
+        cur = node.borrow().right.clone();
+    }
+    keys
+}
+
+// Weight-balanced tree, BB[alpha]: every node keeps its subtree size and the sizes of its two
+// subtrees stay within a constant factor of each other, which bounds the height by O(log n). The
+// sizes double as an order statistics index, see select and rank.
+#[derive(Clone, Debug)]
+pub struct WBTree<T: Display+Debug+Copy, C = Natural> {
+    root: Tree<T>,
+    cmp: C,
+}
+
+impl<T> WBTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C> WBTree<T, C>
+where T: Display+Debug+Copy, C: Comparator<T>
+{
+    // order keys with cmp instead of their Ord implementation
+    pub fn with_comparator(cmp: C) -> Self {
+        WBTree {
+            root: None,
+            cmp,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn insert(&mut self, key: T) {
+        let root = self.root.take();
+        self.root = Some(self.insert_node(root, key));
+    }
+
+    fn insert_node(&self, tree: Tree<T>, key: T) -> TreeNode<T> {
+        let h = match tree {
+            Some(h) => h,
+            None => return Node::new(key),
+        };
+        let order = self.cmp.compare(&key, &h.borrow().key);
+        match order {
+            Ordering::Less => {
+                let left = h.borrow_mut().left.take();
+                h.borrow_mut().left = Some(self.insert_node(left, key));
+            },
+            Ordering::Greater => {
+                let right = h.borrow_mut().right.take();
+                h.borrow_mut().right = Some(self.insert_node(right, key));
+            },
+            // key already in tree
+            Ordering::Equal => return h,
+        }
+        balance(h)
+    }
+
+    pub fn delete(&mut self, key: T) {
+        let root = self.root.take();
+        self.root = self.delete_node(root, &key);
+    }
+
+    fn delete_node(&self, tree: Tree<T>, key: &T) -> Tree<T> {
+        let h = tree?;
+        let order = self.cmp.compare(key, &h.borrow().key);
+        match order {
+            Ordering::Less => {
+                let left = h.borrow_mut().left.take();
+                h.borrow_mut().left = self.delete_node(left, key);
+            },
+            Ordering::Greater => {
+                let right = h.borrow_mut().right.take();
+                h.borrow_mut().right = self.delete_node(right, key);
+            },
+            Ordering::Equal => {
+                let left = h.borrow_mut().left.take();
+                let right = h.borrow_mut().right.take();
+                match (left, right) {
+                    (None, child) | (child, None) => return child,
+                    (left, Some(right)) => {
+                        // the successor takes the deleted key's place
+                        let (right, successor) = delete_min(right);
+                        h.borrow_mut().key = successor;
+                        h.borrow_mut().left = left;
+                        h.borrow_mut().right = right;
+                    }
+                }
+            }
+        }
+        Some(balance(h))
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.find(key).map(|node| node.borrow().key)
+    }
+
+    // the node holding key, private so callers cannot change a key and break the ordering
+    fn find(&self, key: &T) -> Tree<T> {
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+
+    pub fn first(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let left = cur.borrow().left.clone();
+            match left {
+                Some(left) => cur = left,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<T> {
+        let mut cur = self.root.clone()?;
+        loop {
+            let right = cur.borrow().right.clone();
+            match right {
+                Some(right) => cur = right,
+                None => return Some(cur.borrow().key),
+            }
+        }
+    }
+
+    // the key with k smaller keys in the tree, counting from 0
+    pub fn select(&self, k: usize) -> Option<T> {
+        let mut k = k;
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let left_size = size(&node.borrow().left);
+            cur = match k.cmp(&left_size) {
+                Ordering::Equal => return Some(node.borrow().key),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => {
+                    k -= left_size + 1;
+                    node.borrow().right.clone()
+                }
+            };
+        }
+        None
+    }
+
+    // how many keys in the tree come before key, whether or not key is in it
+    pub fn rank(&self, key: T) -> usize {
+        let mut rank = 0;
+        let mut cur = self.root.clone();
+        while let Some(node) = cur {
+            let order = self.cmp.compare(&key, &node.borrow().key);
+            cur = match order {
+                Ordering::Equal => return rank + size(&node.borrow().left),
+                Ordering::Less => node.borrow().left.clone(),
+                Ordering::Greater => {
+                    rank += size(&node.borrow().left) + 1;
+                    node.borrow().right.clone()
+                }
+            };
+        }
+        rank
+    }
+
+    pub fn range(&self, lo: T, hi: T) -> Vec<T> {
+        range(&self.root, &lo, &hi, &self.cmp)
+    }
+
+    pub fn height(&self) -> u32 {
+        if self.root.is_none() {
+            return 0;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        let mut height = 0;
+        // find height by breadth first search traversal
+        while !queue.is_empty() {
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                for child in [node.borrow().left.clone(), node.borrow().right.clone()].into_iter().flatten() {
+                    queue.push_back(child);
+                }
+            }
+            height += 1;
+        }
+        height
+    }
+
+    pub fn leaves(&self) -> u32 {
+        let mut stack: Vec<TreeNode<T>> = self.root.clone().into_iter().collect();
+        let mut count = 0;
+        while let Some(node) = stack.pop() {
+            let left = node.borrow().left.clone();
+            let right = node.borrow().right.clone();
+            if left.is_none() && right.is_none() {
+                count += 1;
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        count
+    }
+
+    // iterate over the keys in order
+    pub fn iter(&self) -> Iter<T> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.clone());
+        iter
+    }
+
+    pub fn print_inorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        for key in self.iter() {
+            print!(" {} ", key);
+        }
+        println!("\n");
+    }
+
+    pub fn print_preorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut stack: Vec<TreeNode<T>> = vec![self.root.clone().unwrap()];
+        while let Some(node) = stack.pop() {
+            print!(" {} ", node.borrow().key);
+            stack.extend(node.borrow().right.clone());
+            stack.extend(node.borrow().left.clone());
+        }
+        println!("\n");
+    }
+
+    pub fn print_levelorder(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        }
+        let mut queue: VecDeque<TreeNode<T>> = VecDeque::new();
+        queue.push_back(self.root.clone().unwrap());
+        while let Some(node) = queue.pop_front() {
+            print!(" {} ", node.borrow().key);
+            queue.extend(node.borrow().left.clone());
+            queue.extend(node.borrow().right.clone());
+        }
+        println!("\n");
+    }
+
+    // the tree's layout with each subtree's size, see Shape
+    pub fn shape(&self) -> Shape<T> {
+        Shape::build(self.root.clone(), true, |node: &TreeNode<T>| {
+            let node = node.borrow();
+            (vec![node.key], vec![("size", Attr::Number(node.size as u64))], vec![node.left.clone(), node.right.clone()])
+        })
+    }
+
+    pub fn print_tree(&self) {
+        if self.root.is_none() {
+            println!("None");
+            return;
+        };
+
+        fn pretty_print<T: Display+Debug>(node: TreeNode<T>, buffer: &mut String, prefix: &str, child_prefix: &str) {
+            buffer.push_str(prefix);
+            buffer.push_str(&node.borrow().key.to_string());
+            buffer.push('\n');
+            for child in [node.borrow().right.clone(), node.borrow().left.clone()].into_iter().flatten() {
+                let has_children = child.borrow().left.is_some() || child.borrow().right.is_some();
+                let (branch, indent) = if has_children { ("├── ", "│   ") } else { ("└── ", "    ") };
+                pretty_print(child, buffer, &format!("{}{}", child_prefix, branch), &format!("{}{}", child_prefix, indent));
+            }
+        }
+
+        let mut buffer = String::new();
+        pretty_print(self.root.clone().unwrap(), &mut buffer, "", "");
+        println!("{}", buffer);
+    }
+}
+
+impl<T> Default for WBTree<T>
+where T: Ord+Display+Debug+Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<T: Display+Debug+Copy> {
+    stack: Vec<TreeNode<T>>,
+}
+
+impl<T> Iter<T>
+where T: Display+Debug+Copy
+{
+    fn push_left(&mut self, tree: Tree<T>) {
+        let mut cur = tree;
+        while let Some(node) = cur {
+            cur = node.borrow().left.clone();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T>
+where T: Display+Debug+Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let key = node.borrow().key;
+        self.push_left(node.borrow().right.clone());
+        Some(key)
+    }
+}
+
+impl<T, C> fmt::Display for WBTree<T, C>
+where T: Debug+Display+Copy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WBTree")
+         .field("root", &self.root)
+         .field("length", &size(&self.root))
+         .finish()
+    }
+}
+
+
+// check key order, stored sizes and the weight bound, returns the subtree size
+#[cfg(test)]
+fn check_wb(tree: &Tree<u32>, lo: Option<u32>, hi: Option<u32>) -> usize {
+    let node = match tree {
+        Some(node) => node.borrow(),
+        None => return 0,
+    };
+    assert!(lo.is_none_or(|lo| node.key > lo) && hi.is_none_or(|hi| node.key < hi));
+    let left = check_wb(&node.left, lo, Some(node.key));
+    let right = check_wb(&node.right, Some(node.key), hi);
+    assert!(left < DELTA * (right + 1) && right < DELTA * (left + 1));
+    assert_eq!(node.size, left + right + 1);
+    node.size
+}
+
+#[test]
+pub fn create_empty_wbtree() {
+    let tree: WBTree<u32> = WBTree::new();
+    assert!(tree.is_empty());
+    assert_eq!(tree.count(), 0);
+    assert_eq!(tree.select(0), None);
+    assert_eq!(tree.rank(10), 0);
+}
+
+#[test]
+pub fn test_wb_sorted_inserts_and_deletes() {
+    let mut tree = WBTree::new();
+    for key in 0..1024 {
+        tree.insert(key);
+    }
+    assert_eq!(check_wb(&tree.root, None, None), 1024);
+    assert!(tree.height() <= 20);
+    for key in (0..1024).step_by(2) {
+        tree.delete(key);
+    }
+    tree.delete(5000);
+    assert_eq!(check_wb(&tree.root, None, None), 512);
+    assert_eq!(tree.first().unwrap(), 1);
+    assert_eq!(tree.last().unwrap(), 1023);
+}
+
+#[test]
+pub fn test_wb_select_rank() {
+    let mut tree = WBTree::new();
+    for key in (0..100).map(|key| key * 10) {
+        tree.insert(key);
+    }
+    assert_eq!(tree.select(0), Some(0));
+    assert_eq!(tree.select(42), Some(420));
+    assert_eq!(tree.select(100), None);
+    assert_eq!(tree.rank(420), 42);
+    assert_eq!(tree.rank(425), 43);
+    assert_eq!(tree.rank(5000), 100);
+    assert_eq!(tree.range(415, 460), vec![420, 430, 440, 450]);
+}
+
+#[test]
+pub fn test_wb_random() {
+    let mut tree = WBTree::new();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut rng = crate::rng::Rng::new(37);
+    for _ in 0..3000 {
+        let key = rng.below(500) as u32;
+        if rng.below(3) == 0 {
+            tree.delete(key);
+            keys.remove(&key);
+        } else {
+            tree.insert(key);
+            keys.insert(key);
+        }
+        assert_eq!(check_wb(&tree.root, None, None), keys.len());
+    }
+    let sorted: Vec<u32> = keys.into_iter().collect();
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), sorted);
+    for (k, key) in sorted.iter().enumerate() {
+        assert_eq!(tree.select(k), Some(*key));
+        assert_eq!(tree.rank(*key), k);
+    }
+}
+
+#[test]
+pub fn test_wb_reverse_comparator() {
+    let mut tree = WBTree::with_comparator(crate::compare::Reverse);
+    for key in [3, 1, 2] {
+        tree.insert(key);
+    }
+    assert_eq!(tree.iter().collect::<Vec<u32>>(), vec![3, 2, 1]);
+    assert_eq!(tree.select(0), Some(3));
+    assert_eq!(tree.rank(1), 2);
+}